@@ -0,0 +1,741 @@
+use crate::export::{self, Filter, Format};
+use crate::git::{self, Git};
+use crate::lock::Lock;
+use crate::mirror::Mirror;
+use crate::patch::PatchExport;
+use crate::stash::Stash;
+use crate::{configure, Config};
+use argh::FromArgs;
+use chrono::{NaiveDateTime, Utc};
+use std::error;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use store::store::{Occurrence, SearchQuery, Store};
+
+#[derive(FromArgs)]
+/// Automatically stashes every change made below the watched directory.
+#[argh(error_code(2, "a command failed, e.g. the store is missing or unreadable"))]
+pub struct Args {
+    /// path of the config file
+    #[argh(option, default = "String::from(\"config.toml\")")]
+    pub config: String,
+    /// attach to a store owned by another instance for browsing only
+    #[argh(switch)]
+    pub read_only: bool,
+    #[argh(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum Command {
+    Fsck(Fsck),
+    ImportGit(ImportGit),
+    Mirror(MirrorArgs),
+    Stash(StashArgs),
+    ExportPatch(ExportPatch),
+    Export(Export),
+    Tag(TagArgs),
+    Tags(Tags),
+    Untag(Untag),
+    Restore(Restore),
+    Note(NoteArgs),
+    Notes(Notes),
+    Search(Search),
+    Diff(DiffArgs),
+}
+
+#[derive(FromArgs)]
+/// Check the store for inconsistencies.
+#[argh(
+    subcommand,
+    name = "fsck",
+    error_code(1, "problems were left unrepaired"),
+    error_code(2, "the check failed")
+)]
+pub struct Fsck {
+    /// drop orphans, rebuild version trees, cut off versions that no longer
+    /// replay and re-baseline broken files
+    #[argh(switch)]
+    pub repair: bool,
+    /// with --repair, also delete tags pointing at a missing version
+    #[argh(switch)]
+    pub drop_dangling_tags: bool,
+}
+
+#[derive(FromArgs)]
+/// Seed the history of every stored file with its git commits.
+#[argh(subcommand, name = "import-git")]
+pub struct ImportGit {
+    /// how many commits per file to import at most
+    #[argh(option, default = "50")]
+    pub limit: usize,
+}
+
+#[derive(FromArgs)]
+/// Commit every stored version onto refs/autostash/<branch>.
+#[argh(subcommand, name = "mirror")]
+pub struct MirrorArgs {
+    /// mirror into this bare repository instead of the watched one
+    #[argh(option)]
+    pub bare: Option<String>,
+    /// branch name used for the reference, defaults to the checked out one
+    #[argh(option)]
+    pub branch: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Create a git stash entry from stored versions.
+#[argh(subcommand, name = "stash")]
+pub struct StashArgs {
+    /// only stash this file
+    #[argh(option)]
+    pub path: Option<String>,
+    /// stash the version stored at this time, as "YYYY-MM-DD HH:MM:SS" or unix seconds
+    #[argh(option)]
+    pub at: Option<String>,
+    /// start of the time range, defaults to the first version
+    #[argh(option)]
+    pub from: Option<String>,
+    /// end of the time range, defaults to now
+    #[argh(option)]
+    pub to: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Write stored versions as unified diff .patch files.
+#[argh(subcommand, name = "export-patch")]
+pub struct ExportPatch {
+    /// only export this file
+    #[argh(option)]
+    pub path: Option<String>,
+    /// export the version stored at this time, as "YYYY-MM-DD HH:MM:SS" or unix seconds
+    #[argh(option)]
+    pub at: Option<String>,
+    /// start of the time range, defaults to the first version
+    #[argh(option)]
+    pub from: Option<String>,
+    /// end of the time range, defaults to now
+    #[argh(option)]
+    pub to: Option<String>,
+    /// write a single patch spanning the whole range
+    #[argh(switch)]
+    pub squash: bool,
+    /// directory the patches are written to
+    #[argh(option, default = "String::from(\".\")")]
+    pub out: String,
+}
+
+#[derive(FromArgs)]
+/// Write the history or hits of code as JSON Lines or CSV.
+#[argh(subcommand, name = "export")]
+pub struct Export {
+    /// jsonl or csv
+    #[argh(option, default = "Format::JsonLines")]
+    pub format: Format,
+    /// only export files matching this glob, relative to the watched directory
+    #[argh(option)]
+    pub glob: Option<String>,
+    /// start of the time range, defaults to the first version
+    #[argh(option)]
+    pub from: Option<String>,
+    /// end of the time range, defaults to now
+    #[argh(option)]
+    pub to: Option<String>,
+    /// export hits of code per file and day instead of changed lines
+    #[argh(switch)]
+    pub metrics: bool,
+    /// file to write to, defaults to stdout
+    #[argh(option)]
+    pub out: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Name a version of a file, or a moment of the whole tree.
+#[argh(subcommand, name = "tag")]
+pub struct TagArgs {
+    #[argh(positional)]
+    pub name: String,
+    /// tag a version of this file only
+    #[argh(option)]
+    pub path: Option<String>,
+    /// time of the version or moment, defaults to now
+    #[argh(option)]
+    pub at: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// List all tags.
+#[argh(subcommand, name = "tags")]
+pub struct Tags {}
+
+#[derive(FromArgs)]
+/// Delete a tag.
+#[argh(subcommand, name = "untag")]
+pub struct Untag {
+    #[argh(positional)]
+    pub name: String,
+}
+
+#[derive(FromArgs)]
+/// Restore the files of a tag.
+#[argh(subcommand, name = "restore")]
+pub struct Restore {
+    #[argh(positional)]
+    pub tag: String,
+}
+
+#[derive(FromArgs)]
+/// Attach a note to a version, read from stdin unless --text is given.
+#[argh(subcommand, name = "note")]
+pub struct NoteArgs {
+    #[argh(positional)]
+    pub path: String,
+    /// time of the version, defaults to the newest one
+    #[argh(option)]
+    pub at: Option<String>,
+    /// text of the note, an empty text removes it
+    #[argh(option)]
+    pub text: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// List notes.
+#[argh(subcommand, name = "notes")]
+pub struct Notes {
+    /// only list notes containing this text, ignoring case
+    #[argh(option)]
+    pub search: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// List the versions in which a line containing the pattern appeared or disappeared.
+#[argh(
+    subcommand,
+    name = "search",
+    error_code(1, "nothing was found"),
+    error_code(2, "the search failed")
+)]
+pub struct Search {
+    #[argh(positional)]
+    pub pattern: String,
+    /// treat the pattern as a regular expression
+    #[argh(switch)]
+    pub regex: bool,
+    /// only search files matching this glob, relative to the watched directory
+    #[argh(option)]
+    pub glob: Option<String>,
+    /// start of the time range, defaults to the first version
+    #[argh(option)]
+    pub from: Option<String>,
+    /// end of the time range, defaults to now
+    #[argh(option)]
+    pub to: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Compare two versions of a file, or a version with the file on disk.
+#[argh(
+    subcommand,
+    name = "diff",
+    error_code(1, "the versions differ"),
+    error_code(2, "the comparison failed")
+)]
+pub struct DiffArgs {
+    #[argh(positional)]
+    pub path: String,
+    /// time of the version to compare from
+    #[argh(option)]
+    pub from: Option<String>,
+    /// id of the version to compare from, as listed by tags, notes and search
+    #[argh(option)]
+    pub from_id: Option<i64>,
+    /// time of the version to compare to, defaults to the file on disk
+    #[argh(option)]
+    pub to: Option<String>,
+    /// id of the version to compare to
+    #[argh(option)]
+    pub to_id: Option<i64>,
+}
+
+/// exit code of a command that failed, 1 is a result like grep and diff use it
+pub const FAILED: i32 = 2;
+
+///
+/// runs a command without the tui and returns the exit code, an error
+/// is to be reported with `FAILED`
+///
+pub fn run(config: &Config, command: Command) -> Result<i32, Box<dyn error::Error>> {
+    match command {
+        Command::Fsck(fsck) => run_fsck(config, fsck),
+        Command::ImportGit(import_git) => run_import_git(config, import_git),
+        Command::Mirror(mirror) => run_mirror(config, mirror),
+        Command::Stash(stash) => run_stash(config, stash),
+        Command::ExportPatch(export_patch) => run_export_patch(config, export_patch),
+        Command::Export(export) => run_export(config, export),
+        Command::Tag(tag) => run_tag(config, tag),
+        Command::Tags(_) => run_tags(config),
+        Command::Untag(untag) => run_untag(config, untag),
+        Command::Restore(restore) => run_restore(config, restore),
+        Command::Note(note) => run_note(config, note),
+        Command::Notes(notes) => run_notes(config, notes),
+        Command::Search(search) => run_search(config, search),
+        Command::Diff(diff) => run_diff(config, diff),
+    }
+}
+
+fn run_fsck(config: &Config, fsck: Fsck) -> Result<i32, Box<dyn error::Error>> {
+    let (mut store, _lock) = if fsck.repair {
+        let lock = Lock::acquire(&config.store_path, &config.watch_path)?;
+        (Store::open(&config.store_path)?, Some(lock))
+    } else {
+        (Store::open_read_only(&config.store_path)?, None)
+    };
+    configure(&mut store, config);
+
+    let problems = store.fsck(fsck.repair, fsck.drop_dangling_tags)?;
+    problems.iter().for_each(|problem| println!("{}", problem));
+    let unrepaired = problems.iter().filter(|problem| !problem.repaired).count();
+    println!(
+        "{} problem(s) found, {} repaired",
+        problems.len(),
+        problems.len() - unrepaired
+    );
+
+    Ok(if unrepaired > 0 { 1 } else { 0 })
+}
+
+fn run_import_git(config: &Config, import_git: ImportGit) -> Result<i32, Box<dyn error::Error>> {
+    let _lock = Lock::acquire(&config.store_path, &config.watch_path)?;
+    let mut store = Store::new(
+        &config.store_path,
+        &config.watch_path,
+        config.exclude.files.clone(),
+        config.exclude.paths.clone(),
+    )?;
+    configure(&mut store, config);
+    let git = Git::discover(&config.watch_path)?;
+
+    let mut imported = 0;
+    for path in store.paths() {
+        let relative = match git.relative(&path) {
+            Some(relative) => relative,
+            None => continue,
+        };
+        // commits that removed the file are skipped, the next one adding it
+        // back diffs against the content before the removal
+        let history = git
+            .commits(&relative, import_git.limit)?
+            .into_iter()
+            .filter_map(|(hash, timestamp)| match git.show(&hash, &relative) {
+                Ok(Some(content)) => Some(Ok((timestamp, diff::merge::to_lines(&content)))),
+                Ok(None) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count = store.import_history(&path, &history)?;
+        if count > 0 {
+            println!("{}: {} version(s)", path, count);
+        }
+        imported += count;
+    }
+    println!("imported {} version(s)", imported);
+
+    Ok(0)
+}
+
+fn run_mirror(config: &Config, mirror: MirrorArgs) -> Result<i32, Box<dyn error::Error>> {
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+    let mirror = Mirror::new(&config.watch_path, mirror.bare, mirror.branch)?;
+
+    let commits = mirror.export(&store)?;
+    println!("{} new commit(s) on {}", commits, mirror.reference);
+
+    Ok(0)
+}
+
+fn run_stash(config: &Config, stash: StashArgs) -> Result<i32, Box<dyn error::Error>> {
+    let (from, to) = time_range(stash.at, stash.from, stash.to)?;
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+
+    let entry = Stash::new(&config.watch_path)?.create(&store, stash.path.as_deref(), from, to)?;
+    println!("{}", entry);
+
+    Ok(0)
+}
+
+fn run_export_patch(
+    config: &Config,
+    export_patch: ExportPatch,
+) -> Result<i32, Box<dyn error::Error>> {
+    let (from, to) = time_range(export_patch.at, export_patch.from, export_patch.to)?;
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+
+    let files = PatchExport::new(&config.watch_path, &export_patch.out)?.export(
+        &store,
+        export_patch.path.as_deref(),
+        from,
+        to,
+        export_patch.squash,
+    )?;
+    files.iter().for_each(|file| println!("{}", file.display()));
+    println!(
+        "wrote {} patch(es), apply them from {} with `patch -p0` or `git apply`",
+        files.len(),
+        config.watch_path
+    );
+
+    Ok(0)
+}
+
+fn run_export(config: &Config, export: Export) -> Result<i32, Box<dyn error::Error>> {
+    let (from, to) = time_range(None, export.from, export.to)?;
+    let filter = Filter::new(&config.watch_path, export.glob.as_deref(), from, to)?;
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+    let writer: Box<dyn io::Write> = match &export.out {
+        Some(out) => Box::new(File::create(out)?),
+        None => Box::new(io::stdout()),
+    };
+
+    if export.metrics {
+        export::write_rows(
+            &export::hits_rows(&mut store, &filter)?,
+            export.format,
+            writer,
+        )?;
+    } else {
+        export::write_rows(
+            &export::change_rows(&mut store, &filter)?,
+            export.format,
+            writer,
+        )?;
+    }
+
+    Ok(0)
+}
+
+fn run_tag(config: &Config, tag: TagArgs) -> Result<i32, Box<dyn error::Error>> {
+    let _lock = Lock::acquire(&config.store_path, &config.watch_path)?;
+    let mut store = Store::open(&config.store_path)?;
+    configure(&mut store, config);
+    let path = tag
+        .path
+        .map(|path| stored_path(&store, config, &path))
+        .transpose()?;
+    let at = match tag.at {
+        Some(at) => parse_time(&at)?,
+        None => Utc::now().timestamp(),
+    };
+
+    let tag = store.tag(&tag.name, path.as_deref(), at)?;
+    println!("tagged {}", describe(&tag));
+
+    Ok(0)
+}
+
+fn run_tags(config: &Config) -> Result<i32, Box<dyn error::Error>> {
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+    store
+        .tags()
+        .iter()
+        .for_each(|tag| println!("{}", describe(tag)));
+
+    Ok(0)
+}
+
+fn run_untag(config: &Config, untag: Untag) -> Result<i32, Box<dyn error::Error>> {
+    let _lock = Lock::acquire(&config.store_path, &config.watch_path)?;
+    let mut store = Store::open(&config.store_path)?;
+    configure(&mut store, config);
+    store.untag(&untag.name)?;
+
+    Ok(0)
+}
+
+fn run_restore(config: &Config, restore: Restore) -> Result<i32, Box<dyn error::Error>> {
+    let _lock = Lock::acquire(&config.store_path, &config.watch_path)?;
+    let mut store = Store::open(&config.store_path)?;
+    configure(&mut store, config);
+
+    let paths = store.restore_tag(&restore.tag)?;
+    paths.iter().for_each(|path| println!("restored {}", path));
+
+    Ok(0)
+}
+
+fn run_note(config: &Config, note: NoteArgs) -> Result<i32, Box<dyn error::Error>> {
+    let _lock = Lock::acquire(&config.store_path, &config.watch_path)?;
+    let mut store = Store::open(&config.store_path)?;
+    configure(&mut store, config);
+    let path = stored_path(&store, config, &note.path)?;
+    let at = match note.at {
+        Some(at) => parse_time(&at)?,
+        None => Utc::now().timestamp(),
+    };
+    let version = store
+        .nearest_version(&path, at)
+        .ok_or("no version recorded before the given time")?;
+    let text = match note.text {
+        Some(text) => text,
+        None => io::read_to_string(io::stdin())?,
+    };
+
+    store.set_note(&path, version, &text)?;
+
+    Ok(0)
+}
+
+fn run_notes(config: &Config, notes: Notes) -> Result<i32, Box<dyn error::Error>> {
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+    let notes = match notes.search {
+        Some(query) => store.search_notes(&query),
+        None => store.notes(),
+    };
+    notes.iter().for_each(|note| {
+        let datetime = store
+            .version_datetime(&note.path, note.version)
+            .map_or_else(
+                || format!("version {}", note.version),
+                |datetime| datetime.to_string(),
+            );
+        println!(
+            "{} @ {}, version {}\n{}\n",
+            note.path, datetime, note.version, note.text
+        );
+    });
+
+    Ok(0)
+}
+
+fn run_search(config: &Config, search: Search) -> Result<i32, Box<dyn error::Error>> {
+    let (from, to) = time_range(None, search.from, search.to)?;
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+    let hits = store.search(&SearchQuery {
+        pattern: search.pattern,
+        regex: search.regex,
+        glob: search.glob,
+        from,
+        to,
+    })?;
+
+    hits.iter().for_each(|hit| {
+        let datetime = hit.datetime.format("%Y-%m-%d %H:%M:%S");
+        let sign = match hit.occurrence {
+            Occurrence::Appeared => '+',
+            Occurrence::Disappeared => '-',
+        };
+        println!(
+            "{}\t{}\t{}:{}\t{}{}",
+            datetime, hit.version, hit.path, hit.line_number, sign, hit.line
+        );
+    });
+
+    // like grep, nothing found is not an error but worth a distinct code
+    Ok(if hits.is_empty() { 1 } else { 0 })
+}
+
+fn run_diff(config: &Config, diff: DiffArgs) -> Result<i32, Box<dyn error::Error>> {
+    let mut store = Store::open_read_only(&config.store_path)?;
+    configure(&mut store, config);
+    let path = stored_path(&store, config, &diff.path)?;
+    let version = |time: &str| -> Result<i64, Box<dyn error::Error>> {
+        store
+            .nearest_version(&path, parse_time(time)?)
+            .ok_or_else(|| format!("no version of {} recorded before {}", path, time).into())
+    };
+    let from = match (diff.from_id, diff.from.as_deref()) {
+        (Some(_), Some(_)) => return Err("give either --from or --from-id".into()),
+        (Some(id), None) => id,
+        (None, Some(time)) => version(time)?,
+        (None, None) => return Err("--from or --from-id is required".into()),
+    };
+    let to = match (diff.to_id, diff.to.as_deref()) {
+        (Some(_), Some(_)) => return Err("give either --to or --to-id".into()),
+        (Some(id), None) => Some(id),
+        (None, time) => time.map(version).transpose()?,
+    };
+
+    let changes = store.diff_between(&path, from, to)?;
+    changes.iter().for_each(|change| {
+        if let Some(line) = &change.line {
+            println!("{}\t-{}", change.line_number, line);
+        }
+        if let Some(changed_line) = &change.changed_line {
+            println!("{}\t+{}", change.line_number, changed_line);
+        }
+    });
+
+    // like diff, differences are worth a distinct code
+    Ok(if changes.is_empty() { 0 } else { 1 })
+}
+
+/// name, time, scope and version id of a tag, for a moment the newest id it covers
+fn describe(tag: &store::store::Tag) -> String {
+    let datetime = NaiveDateTime::from_timestamp(tag.timestamp, 0);
+    // file tags of old stores point at the version stored at their time
+    let version = tag
+        .version
+        .or(tag.path.as_ref().map(|_| tag.timestamp))
+        .map_or_else(|| "-".to_string(), |version| version.to_string());
+    format!(
+        "{}\t{}\t{}\t{}",
+        tag.name,
+        datetime,
+        tag.path.as_deref().unwrap_or("(whole tree)"),
+        version
+    )
+}
+
+/// the path a file is stored under, given relative to the working directory
+fn stored_path(
+    store: &Store,
+    config: &Config,
+    path: &str,
+) -> Result<String, Box<dyn error::Error>> {
+    let base = PathBuf::from(&config.watch_path).canonicalize()?;
+    let relative = git::relative(&base, path);
+
+    store
+        .paths()
+        .into_iter()
+        .find(|stored| relative.is_some() && git::relative(&base, stored) == relative)
+        .ok_or_else(|| format!("{} has no versions", path).into())
+}
+
+/// a single point in time with `at`, otherwise everything up to now by default
+fn time_range(
+    at: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(i64, i64), Box<dyn error::Error>> {
+    if let Some(at) = at {
+        let at = parse_time(&at)?;
+        return Ok((at, at));
+    }
+    let from = from.as_deref().map(parse_time).transpose()?;
+    let to = to.as_deref().map(parse_time).transpose()?;
+
+    Ok((
+        from.unwrap_or(i64::MIN),
+        to.unwrap_or_else(|| Utc::now().timestamp()),
+    ))
+}
+
+/// unix seconds or a UTC date time as shown in the snapshot list
+fn parse_time(time: &str) -> Result<i64, Box<dyn error::Error>> {
+    if let Ok(timestamp) = time.parse() {
+        return Ok(timestamp);
+    }
+    let datetime = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .map_err(|_| format!("{:?} is neither unix seconds nor YYYY-MM-DD HH:MM:SS", time))?;
+
+    Ok(datetime.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Repo;
+    use itertools::Itertools;
+    use std::fs;
+
+    fn config(repo: &Repo, settings: &str) -> Config {
+        toml::from_str(&format!(
+            "store_path = {:?}\nwatch_path = {:?}\ndebounce_time = 333\n{}\n\
+             [exclude]\npaths = []\nfiles = []\n",
+            repo.store_path, repo.watch_path, settings
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn export_compares_configured_files_by_their_keys() {
+        let repo = Repo::new("test_cli_export");
+        let mut store = repo.store();
+        repo.edit(&mut store, "config.json", "{\"server\": {\"port\": 80}}\n");
+        repo.edit(
+            &mut store,
+            "config.json",
+            "{\"server\": {\"port\": 8080}}\n",
+        );
+        drop(store);
+        let config = config(&repo, "[diff]\nstructural = true");
+        let out = repo.out.join("changes.jsonl");
+
+        let code = run(
+            &config,
+            Command::Export(Export {
+                format: Format::JsonLines,
+                glob: Some("*.json".to_string()),
+                from: None,
+                to: None,
+                metrics: false,
+                out: Some(out.to_str().unwrap().to_string()),
+            }),
+        )
+        .unwrap();
+        let rows = fs::read_to_string(&out).unwrap();
+
+        assert_eq!(code, 0);
+        assert!(
+            rows.lines()
+                .any(|row| row.contains("\"key\":\"server.port\"")
+                    && row.contains("\"old_line\":\"80\"")
+                    && row.contains("\"new_line\":\"8080\"")),
+            "{}",
+            rows
+        );
+    }
+
+    #[test]
+    fn import_skips_commits_without_the_file() {
+        let repo = Repo::new("test_cli_import");
+        let commit = |message: &str, date: &str| {
+            let date = format!("{} +0000", date);
+            git::run_with(
+                &repo.root,
+                &["commit", "-q", "-m", message],
+                &[("GIT_AUTHOR_DATE", &date), ("GIT_COMMITTER_DATE", &date)],
+                None,
+            )
+            .unwrap();
+        };
+        fs::write(repo.file("b.txt"), "1\n").unwrap();
+        repo.git(&["add", "src/b.txt"]);
+        commit("add", "1000000000");
+        repo.git(&["rm", "-q", "src/b.txt"]);
+        commit("remove", "1000001000");
+        fs::write(repo.file("b.txt"), "2\n").unwrap();
+        repo.git(&["add", "src/b.txt"]);
+        commit("add again", "1000002000");
+        drop(repo.store());
+
+        let code = run(
+            &config(&repo, ""),
+            Command::ImportGit(ImportGit { limit: 50 }),
+        )
+        .unwrap();
+        let versions = Store::open_read_only(&repo.store_path)
+            .unwrap()
+            .snapshots(|_, _| true)
+            .unwrap()
+            .into_iter()
+            .filter(|snapshot| snapshot.path.ends_with("b.txt"))
+            .map(|snapshot| (snapshot.datetime.timestamp(), snapshot.content().unwrap()))
+            .collect_vec();
+
+        assert_eq!(code, 0);
+        assert_eq!(versions.len(), 3);
+        assert_eq!(versions[0], (1000000000, "1\n".to_string()));
+        assert_eq!(versions[1], (1000002000, "2\n".to_string()));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::git;
+use chrono::{DateTime, NaiveDate, Utc};
+use diff::ChangeKind;
+use glob::Pattern;
+use itertools::Itertools;
+use serde::Serialize;
+use std::error;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use store::store::{Store, TimeFrame, Version};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    JsonLines,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Format, String> {
+        match format {
+            "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {:?}, use jsonl or csv", format)),
+        }
+    }
+}
+
+///
+/// one changed line of the history, or one changed key of a file
+/// compared by its structure
+///
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ChangeRow {
+    pub timestamp: String,
+    pub path: String,
+    /// 0 for changed keys
+    pub line_number: usize,
+    /// none for added lines
+    pub old_line: Option<String>,
+    /// none for removed lines
+    pub new_line: Option<String>,
+    pub kind: ChangeKind,
+    /// note of the version the change belongs to
+    pub note: String,
+    /// key path of a changed key, like server.port
+    pub key: Option<String>,
+}
+
+///
+/// hits of code of one file on one day
+///
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HitsRow {
+    pub date: NaiveDate,
+    pub path: String,
+    pub hits: u64,
+}
+
+///
+/// selects the files whose path relative to the watched directory,
+/// or absolute path, matches `glob`, and the changes in `from..=to`
+///
+pub struct Filter {
+    base: PathBuf,
+    glob: Option<Pattern>,
+    pub from: i64,
+    pub to: i64,
+}
+
+impl Filter {
+    pub fn new(
+        watch_path: &str,
+        glob: Option<&str>,
+        from: i64,
+        to: i64,
+    ) -> Result<Filter, Box<dyn error::Error>> {
+        Ok(Filter {
+            base: PathBuf::from(watch_path).canonicalize()?,
+            glob: glob.map(Pattern::new).transpose()?,
+            from,
+            to,
+        })
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        match &self.glob {
+            Some(glob) => {
+                glob.matches(path)
+                    || git::relative(&self.base, path).is_some_and(|path| glob.matches(&path))
+            }
+            None => true,
+        }
+    }
+
+    fn matches_time(&self, timestamp: i64) -> bool {
+        (self.from..=self.to).contains(&timestamp)
+    }
+}
+
+/// every stored change passing the filter, oldest first
+pub fn change_rows(
+    store: &mut Store,
+    filter: &Filter,
+) -> Result<Vec<ChangeRow>, Box<dyn error::Error>> {
+    store.change_time_frame(TimeFrame::ALL);
+
+    Ok(store
+        .view()?
+        .into_iter()
+        .flatten()
+        .filter(|file_versions| filter.matches_path(&file_versions.path))
+        .flat_map(|file_versions| {
+            let path = file_versions.path;
+            file_versions
+                .versions
+                .into_iter()
+                .flat_map(move |version| version_rows(&path, version))
+        })
+        .filter(|(timestamp, _)| filter.matches_time(timestamp.timestamp()))
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, row)| row)
+        .collect_vec())
+}
+
+///
+/// the changed keys of a version if it has them, its changed lines
+/// otherwise. files stored whole have no rows.
+///
+fn version_rows(path: &str, version: Version) -> Vec<(DateTime<Utc>, ChangeRow)> {
+    let note = version.note.unwrap_or_default();
+    match version.structure {
+        Some(structure) => {
+            let timestamp = DateTime::<Utc>::from_utc(version.datetime, Utc);
+            structure
+                .into_iter()
+                .map(|change| {
+                    let row = ChangeRow {
+                        timestamp: timestamp.to_rfc3339(),
+                        path: path.to_string(),
+                        line_number: 0,
+                        kind: change.kind(),
+                        old_line: change.old,
+                        new_line: change.new,
+                        note: note.clone(),
+                        key: Some(change.key),
+                    };
+                    (timestamp, row)
+                })
+                .collect_vec()
+        }
+        // a blob holds the whole file, not a line
+        None => version
+            .changes
+            .into_iter()
+            .filter(|change| change.kind != ChangeKind::Blob)
+            .map(|change| {
+                let row = ChangeRow {
+                    kind: change.kind,
+                    timestamp: change.date_time.to_rfc3339(),
+                    path: change.path,
+                    line_number: change.line_number,
+                    old_line: change.line,
+                    new_line: change.changed_line,
+                    note: note.clone(),
+                    key: None,
+                };
+                (change.date_time, row)
+            })
+            .collect_vec(),
+    }
+}
+
+/// hits of code per file and day passing the filter
+pub fn hits_rows(
+    store: &mut Store,
+    filter: &Filter,
+) -> Result<Vec<HitsRow>, Box<dyn error::Error>> {
+    store.change_time_frame(TimeFrame::ALL);
+
+    Ok(store
+        .view()?
+        .into_iter()
+        .flatten()
+        .filter(|file_versions| filter.matches_path(&file_versions.path))
+        .flat_map(|file_versions| {
+            let path = file_versions.path;
+            file_versions
+                .hits_of_codes
+                .into_iter()
+                .filter(|hits_of_code| {
+                    let day = hits_of_code.date.and_hms(0, 0, 0).timestamp();
+                    // a day counts if it overlaps the range
+                    filter.matches_time(day) || (day..day + 24 * 60 * 60).contains(&filter.from)
+                })
+                .map(move |hits_of_code| HitsRow {
+                    date: hits_of_code.date,
+                    path: path.clone(),
+                    hits: hits_of_code.hits,
+                })
+        })
+        .sorted_by(|a, b| (a.date, &a.path).cmp(&(b.date, &b.path)))
+        .collect_vec())
+}
+
+pub fn write_rows<T: Serialize, W: Write>(
+    rows: &[T],
+    format: Format,
+    writer: W,
+) -> Result<(), Box<dyn error::Error>> {
+    match format {
+        Format::JsonLines => {
+            let mut writer = writer;
+            for row in rows {
+                serde_json::to_writer(&mut writer, row)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Repo;
+
+    #[test]
+    fn rows_as_json_lines_and_csv() {
+        let rows = vec![ChangeRow {
+            timestamp: "2021-03-01T10:00:00+00:00".to_string(),
+            path: "a.txt".to_string(),
+            line_number: 2,
+            old_line: Some("a, b".to_string()),
+            new_line: None,
+            kind: ChangeKind::Removed,
+            note: "before\nrelease".to_string(),
+            key: None,
+        }];
+        let mut json_lines = vec![];
+        let mut csv = vec![];
+
+        write_rows(&rows, Format::JsonLines, &mut json_lines).unwrap();
+        write_rows(&rows, Format::Csv, &mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(json_lines).unwrap(),
+            "{\"timestamp\":\"2021-03-01T10:00:00+00:00\",\"path\":\"a.txt\",\"line_number\":2,\
+             \"old_line\":\"a, b\",\"new_line\":null,\"kind\":\"removed\",\"note\":\"before\\nrelease\",\"key\":null}\n"
+        );
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "timestamp,path,line_number,old_line,new_line,kind,note,key\n\
+             2021-03-01T10:00:00+00:00,a.txt,2,\"a, b\",,removed,\"before\nrelease\",\n"
+        );
+    }
+
+    #[test]
+    fn files_stored_whole_have_no_rows() {
+        let repo = Repo::new("test_export_blob");
+        let mut store = repo.store();
+        store.change_blob_limit(8);
+        let filter = Filter::new(&repo.watch_path, None, i64::MIN, i64::MAX).unwrap();
+
+        repo.edit(&mut store, "a.txt", "a\nb\n");
+        repo.edit(&mut store, "a.txt", "a\nb\nneedle\nc\n");
+        let rows = change_rows(&mut store, &filter).unwrap();
+
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.kind, row.new_line.clone()))
+                .collect_vec(),
+            vec![
+                (ChangeKind::Added, Some("a".to_string())),
+                (ChangeKind::Added, Some("b".to_string()))
+            ]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use crate::git;
+use std::fs;
+use std::path::{Path, PathBuf};
+use store::store::Store;
+
+///
+/// a git repository `name` in the temp dir for tests, with one commit of
+/// `src/a.txt` on `main`. `src` is the watched directory, the store and a
+/// scratch directory `out` live next to the repository. all of them are
+/// removed when the fixture is dropped, even after a failed assert.
+/// declare it before the store.
+///
+pub struct Repo {
+    pub root: PathBuf,
+    pub watch_path: String,
+    pub store_path: String,
+    pub out: PathBuf,
+}
+
+impl Repo {
+    pub fn new(name: &str) -> Repo {
+        let root = std::env::temp_dir().join(name);
+        let repo = Repo {
+            watch_path: root.join("src").to_str().unwrap().to_string(),
+            store_path: format!("{}.db", root.to_str().unwrap()),
+            out: PathBuf::from(format!("{}-out", root.to_str().unwrap())),
+            root,
+        };
+        // leftovers of a run that was killed
+        repo.remove();
+        fs::create_dir_all(&repo.watch_path).unwrap();
+        fs::create_dir_all(&repo.out).unwrap();
+        fs::write(repo.file("a.txt"), "a\n").unwrap();
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "test"]);
+        repo.git(&["config", "user.email", "test@localhost"]);
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "initial"]);
+
+        repo
+    }
+
+    /// a new store watching `src`
+    pub fn store(&self) -> Store {
+        Store::new(&self.store_path, &self.watch_path, vec![], vec![]).unwrap()
+    }
+
+    /// path of a file in the watched directory
+    pub fn file(&self, name: &str) -> String {
+        Path::new(&self.watch_path)
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// writes a file and records the new version, as a file event would
+    pub fn edit(&self, store: &mut Store, name: &str, content: &str) {
+        let path = self.file(name);
+        fs::write(&path, content).unwrap();
+        store.create_new_file_entry(&path).unwrap();
+        let changes = store.find_changes(&path).unwrap();
+        store.store_changes(&path, &changes).unwrap();
+    }
+
+    /// removes a file and records it, as a file event would
+    pub fn remove_file(&self, store: &mut Store, name: &str) {
+        let path = self.file(name);
+        fs::remove_file(&path).unwrap();
+        let current = store.current_lines(&path).to_vec();
+        let changes = diff::find_between(
+            &path,
+            &current,
+            &[],
+            store.diff_algorithm(&path),
+            store.ignore(&path),
+            0,
+        );
+        store.store_changes(&path, &changes).unwrap();
+    }
+
+    /// runs git in the repository, returns its output
+    pub fn git(&self, args: &[&str]) -> String {
+        git::run(&self.root, args).unwrap()
+    }
+
+    fn remove(&self) {
+        let _ = fs::remove_dir_all(&self.root);
+        let _ = fs::remove_file(&self.store_path);
+        let _ = fs::remove_dir_all(&self.out);
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
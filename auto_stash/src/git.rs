@@ -0,0 +1,222 @@
+use std::error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static PRIVATE_INDEXES: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// thin wrapper around the `git` command line of a local repository
+///
+pub struct Git {
+    pub root: PathBuf,
+}
+
+impl Git {
+    /// finds the repository containing the given path
+    pub fn discover(path: &str) -> Result<Git, Box<dyn error::Error>> {
+        let root = run(Path::new(path), &["rev-parse", "--show-toplevel"])?;
+
+        Ok(Git {
+            root: PathBuf::from(root.trim()).canonicalize()?,
+        })
+    }
+
+    /// opens a bare repository, creating it if it doesn't exist yet
+    pub fn bare(path: &str) -> Result<Git, Box<dyn error::Error>> {
+        if !Path::new(path).exists() {
+            let output = Command::new("git")
+                .args(["init", "-q", "--bare", path])
+                .output()?;
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+            }
+        }
+
+        Ok(Git {
+            root: PathBuf::from(path).canonicalize()?,
+        })
+    }
+
+    /// path of a file relative to the repository root
+    pub fn relative(&self, path: &str) -> Option<String> {
+        relative(&self.root, path)
+    }
+
+    /// name of the checked out branch
+    pub fn branch(&self) -> Result<String, Box<dyn error::Error>> {
+        let branch = self.git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+        Ok(branch.trim().to_string())
+    }
+
+    /// hash and commit time of the latest commits touching a file, oldest first
+    pub fn commits(
+        &self,
+        relative: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, i64)>, Box<dyn error::Error>> {
+        let log = self.git(&[
+            "log",
+            "--format=%H %ct",
+            "-n",
+            &limit.to_string(),
+            "--",
+            relative,
+        ])?;
+
+        log.lines()
+            .rev()
+            .map(|line| -> Result<(String, i64), Box<dyn error::Error>> {
+                let mut parts = line.split_whitespace();
+                let hash = parts.next().ok_or("malformed git log")?.to_string();
+                let timestamp = parts.next().ok_or("malformed git log")?.parse()?;
+                Ok((hash, timestamp))
+            })
+            .collect()
+    }
+
+    /// content of a file at a commit, `None` if it didn't exist there
+    pub fn show(
+        &self,
+        hash: &str,
+        relative: &str,
+    ) -> Result<Option<String>, Box<dyn error::Error>> {
+        let listed = self.git(&["ls-tree", "--name-only", hash, "--", relative])?;
+        if listed.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            self.git(&["show", &format!("{}:{}", hash, relative)])?,
+        ))
+    }
+
+    /// commit a reference points at, `None` if it doesn't exist
+    pub fn resolve(&self, reference: &str) -> Option<String> {
+        self.git(&["rev-parse", "--verify", "-q", reference])
+            .ok()
+            .map(|hash| hash.trim().to_string())
+    }
+
+    ///
+    /// runs `f` with the path of a fresh index file outside the repository,
+    /// so trees can be built without touching the user's index
+    ///
+    pub fn with_private_index<T>(
+        &self,
+        f: impl FnOnce(&str) -> Result<T, Box<dyn error::Error>>,
+    ) -> Result<T, Box<dyn error::Error>> {
+        let index = std::env::temp_dir().join(format!(
+            "autostash-{}-{}.idx",
+            process::id(),
+            PRIVATE_INDEXES.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_file(&index);
+
+        let result = f(index.to_str().ok_or("temp dir is not valid utf-8")?);
+        let _ = fs::remove_file(&index);
+        result
+    }
+
+    /// puts the given content of a file into a private index, none removes it
+    pub fn stage(
+        &self,
+        index: &str,
+        relative: &str,
+        content: Option<&str>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let index_env = [("GIT_INDEX_FILE", index)];
+        let content = match content {
+            Some(content) => content,
+            None => {
+                self.git_with(
+                    &["update-index", "--force-remove", relative],
+                    &index_env,
+                    None,
+                )?;
+                return Ok(());
+            }
+        };
+
+        let blob = self.git_with(&["hash-object", "-w", "--stdin"], &[], Some(content))?;
+        let cache_info = format!("100644,{},{}", blob.trim(), relative);
+        self.git_with(
+            &["update-index", "--add", "--cacheinfo", &cache_info],
+            &index_env,
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn git(&self, args: &[&str]) -> Result<String, Box<dyn error::Error>> {
+        run(&self.root, args)
+    }
+
+    pub fn git_with(
+        &self,
+        args: &[&str],
+        envs: &[(&str, &str)],
+        stdin: Option<&str>,
+    ) -> Result<String, Box<dyn error::Error>> {
+        run_with(&self.root, args, envs, stdin)
+    }
+}
+
+///
+/// path relative to `base`, also for files that were deleted already
+///
+pub fn relative(base: &Path, path: &str) -> Option<String> {
+    let path = Path::new(path);
+    let path = path.canonicalize().ok().or_else(|| {
+        let parent = path.parent()?.canonicalize().ok()?;
+        Some(parent.join(path.file_name()?))
+    })?;
+
+    path.strip_prefix(base)
+        .ok()
+        .and_then(|path| path.to_str())
+        .map(|path| path.to_string())
+}
+
+pub(crate) fn run(dir: &Path, args: &[&str]) -> Result<String, Box<dyn error::Error>> {
+    run_with(dir, args, &[], None)
+}
+
+pub(crate) fn run_with(
+    dir: &Path,
+    args: &[&str],
+    envs: &[(&str, &str)],
+    stdin: Option<&str>,
+) -> Result<String, Box<dyn error::Error>> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(input) = stdin {
+        child
+            .stdin
+            .take()
+            .ok_or("couldn't open stdin of git")?
+            .write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
@@ -1,12 +1,42 @@
+pub mod cli;
+pub mod export;
+#[cfg(test)]
+mod fixture;
+pub mod git;
+pub mod lock;
+pub mod mirror;
+pub mod patch;
+pub mod stash;
+
+use lock::Lock;
 use serde::Deserialize;
+use stash::Stash;
 use std::error;
 use std::time::Duration;
+use store::store::{
+    ConflictStrategy, DiffAlgorithms, IgnoreRules, DEFAULT_BLOB_LIMIT, DEFAULT_CACHE_LIMIT,
+};
 
 #[derive(Clone, Deserialize)]
 pub struct Config {
     pub store_path: String,
     pub watch_path: String,
     pub debounce_time: u64,
+    #[serde(default)]
+    pub on_conflict: ConflictStrategy,
+    #[serde(default)]
+    pub diff: DiffAlgorithms,
+    #[serde(default)]
+    pub ignore: IgnoreRules,
+    /// files larger than this many bytes are stored whole instead of diffed
+    #[serde(default = "default_blob_limit")]
+    pub blob_limit: u64,
+    /// bytes the recorded contents kept in memory may take together
+    #[serde(default = "default_cache_limit")]
+    pub cache_limit: u64,
+    /// blocks of at least this many lines are recorded as moved, 0 turns it off
+    #[serde(default = "default_min_moved_lines")]
+    pub min_moved_lines: usize,
     pub exclude: Exclude,
 }
 
@@ -16,6 +46,18 @@ pub struct Exclude {
     pub files: Vec<String>,
 }
 
+fn default_blob_limit() -> u64 {
+    DEFAULT_BLOB_LIMIT
+}
+
+fn default_cache_limit() -> u64 {
+    DEFAULT_CACHE_LIMIT
+}
+
+fn default_min_moved_lines() -> usize {
+    diff::MIN_MOVED_LINES
+}
+
 impl Config {
     pub fn new(config_path: String) -> Result<Config, Box<dyn error::Error>> {
         let config: Config = toml::from_str(&std::fs::read_to_string(&config_path)?)?;
@@ -24,6 +66,16 @@ impl Config {
     }
 }
 
+/// applies the settings of `config` to a store opened by the tui or a command
+pub fn configure(store: &mut Store, config: &Config) {
+    store.change_conflict_strategy(config.on_conflict);
+    store.change_diff_algorithms(config.diff.clone());
+    store.change_ignore_rules(config.ignore.clone());
+    store.change_blob_limit(config.blob_limit);
+    store.change_cache_limit(config.cache_limit);
+    store.change_min_moved_lines(config.min_moved_lines);
+}
+
 use event_handle::event_handle::{EventHandle, EventHandleCommunication};
 use filewatch::FileWatch;
 use store::store::Store;
@@ -31,25 +83,46 @@ use store::store::Store;
 pub struct AutoStash {
     pub watch_path: String,
     pub watch: FileWatch,
+    pub read_only: bool,
+    _lock: Option<Lock>,
 }
 
 impl AutoStash {
     pub fn new(
         config: &Config,
+        read_only: bool,
         communication: EventHandleCommunication,
         on_quit: flume::Receiver<()>,
     ) -> Result<AutoStash, Box<dyn std::error::Error>> {
-        let store = Store::new(
-            config.store_path.as_str(),
-            config.watch_path.as_str(),
-            config.exclude.files.clone(),
-            config.exclude.paths.clone(),
-        )?;
+        let (mut store, lock) = if read_only {
+            (Store::open_read_only(config.store_path.as_str())?, None)
+        } else {
+            let lock = Lock::acquire(config.store_path.as_str(), config.watch_path.as_str())?;
+            let store = Store::new(
+                config.store_path.as_str(),
+                config.watch_path.as_str(),
+                config.exclude.files.clone(),
+                config.exclude.paths.clone(),
+            )?;
+            (store, Some(lock))
+        };
+        configure(&mut store, config);
 
         let mut event_handle = EventHandle::new(store, communication);
         event_handle.init_file_versions();
         event_handle.on_redo();
         event_handle.on_undo();
+        event_handle.on_jump();
+        event_handle.on_note();
+        event_handle.on_search();
+        event_handle.on_blame();
+        event_handle.on_preview_restore();
+        event_handle.on_restore_changes();
+        event_handle.on_diff_between();
+        let watch_path = config.watch_path.clone();
+        event_handle.on_stash(move |store, path, from, to| {
+            Stash::new(&watch_path)?.create(store, path.as_deref(), from, to)
+        });
         event_handle.on_time_frame_change();
         let watch = FileWatch::new(
             Duration::from_millis(config.debounce_time),
@@ -62,9 +135,77 @@ impl AutoStash {
         Ok(AutoStash {
             watch,
             watch_path: config.watch_path.clone(),
+            read_only,
+            _lock: lock,
         })
     }
     pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.read_only {
+            self.watch.idle(Duration::from_secs(2));
+            return Ok(());
+        }
         self.watch.start_watching(self.watch_path.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diff::Algorithm;
+
+    #[test]
+    fn diff_algorithms_are_chosen_by_extension() {
+        let config: Config = toml::from_str(
+            r#"
+            store_path = "demo.db"
+            watch_path = "/tmp"
+            debounce_time = 333
+            [diff]
+            default = "patience"
+            extensions = { rs = "histogram" }
+            [exclude]
+            paths = []
+            files = []
+            "#,
+        )
+        .unwrap();
+        let fallback: Config = toml::from_str(
+            "store_path = \"demo.db\"\nwatch_path = \"/tmp\"\ndebounce_time = 1\n\
+             [exclude]\npaths = []\nfiles = []\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.diff.of("src/main.rs"), Algorithm::Histogram);
+        assert_eq!(config.diff.of("README"), Algorithm::Patience);
+        assert_eq!(fallback.diff.of("src/main.rs"), Algorithm::Myers);
+        assert_eq!(fallback.blob_limit, DEFAULT_BLOB_LIMIT);
+        assert_eq!(fallback.cache_limit, DEFAULT_CACHE_LIMIT);
+        assert_eq!(fallback.min_moved_lines, diff::MIN_MOVED_LINES);
+    }
+
+    #[test]
+    fn ignore_rules_are_global_or_by_path_pattern() {
+        let config: Config = toml::from_str(
+            r#"
+            store_path = "demo.db"
+            watch_path = "/tmp"
+            debounce_time = 333
+            [ignore]
+            whitespace_change = true
+            skip_versions = true
+            [[ignore.paths]]
+            pattern = "*.md"
+            case = true
+            [exclude]
+            paths = []
+            files = []
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.ignore.skip_versions);
+        assert!(config.ignore.of("src/main.rs").whitespace_change);
+        assert!(!config.ignore.of("docs/README.md").whitespace_change);
+        assert!(config.ignore.of("docs/README.md").case);
+    }
+}
@@ -0,0 +1,158 @@
+use std::error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+///
+/// advisory lock held by a running instance.
+/// one lock file lives next to the store, a second one in the temp
+/// directory guards the watched root. both are removed on drop.
+///
+#[derive(Debug)]
+pub struct Lock {
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct LockError {
+    pub path: PathBuf,
+    pub pid: u32,
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is locked by another AutoStash instance (pid {}), use --read-only to browse it",
+            self.path.display(),
+            self.pid
+        )
+    }
+}
+
+impl error::Error for LockError {}
+
+impl Lock {
+    pub fn acquire(store_path: &str, watch_path: &str) -> Result<Lock, Box<dyn error::Error>> {
+        let mut lock = Lock { paths: vec![] };
+        lock.add(store_lock_path(store_path))?;
+        lock.add(watch_lock_path(watch_path))?;
+
+        Ok(lock)
+    }
+
+    fn add(&mut self, path: PathBuf) -> Result<(), Box<dyn error::Error>> {
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(process::id().to_string().as_bytes())?;
+                    self.paths.push(path);
+                    return Ok(());
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    match holder(&path) {
+                        Some(pid) if is_running(pid) => {
+                            return Err(LockError { path, pid }.into());
+                        }
+                        // left behind by an instance that is gone
+                        _ => fs::remove_file(&path)?,
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        self.paths.iter().for_each(|path| {
+            fs::remove_file(path).unwrap_or_else(|err| {
+                eprintln!("Could not release lock {:?}: {:?}", path, err);
+            });
+        });
+    }
+}
+
+fn store_lock_path(store_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.lock", store_path))
+}
+
+fn watch_lock_path(watch_path: &str) -> PathBuf {
+    let watch_path = fs::canonicalize(watch_path).unwrap_or_else(|_| PathBuf::from(watch_path));
+    let hash = fnv1a(watch_path.to_string_lossy().as_bytes());
+
+    std::env::temp_dir().join(format!("autostash-{:016x}.lock", hash))
+}
+
+///
+/// 64 bit FNV-1a, which unlike the std hashers stays the same across
+/// Rust releases, so every build finds the lock of another
+///
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn is_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if !proc.exists() {
+        // no way to tell, better assume the holder is alive
+        return true;
+    }
+    pid == process::id() || proc.join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_lock_names_the_holder() {
+        let store_path = "test_lock.db";
+        let watch_path = "test_lock";
+        fs::create_dir_all(watch_path).unwrap();
+
+        let lock = Lock::acquire(store_path, watch_path).unwrap();
+        let err = Lock::acquire(store_path, watch_path).unwrap_err();
+        drop(lock);
+        let relocked = Lock::acquire(store_path, watch_path).is_ok();
+        fs::remove_dir_all(watch_path).unwrap();
+
+        let err = err.downcast_ref::<LockError>().unwrap();
+        assert_eq!(err.pid, process::id());
+        assert!(relocked);
+    }
+
+    #[test]
+    fn watch_lock_name_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            watch_lock_path("/nonexistent/watched"),
+            std::env::temp_dir().join("autostash-cfced0262c821edc.lock")
+        );
+    }
+
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let store_path = "test_stale_lock.db";
+        let watch_path = "test_stale_lock";
+        fs::create_dir_all(watch_path).unwrap();
+        fs::write(store_lock_path(store_path), u32::MAX.to_string()).unwrap();
+
+        let lock = Lock::acquire(store_path, watch_path);
+        let holder = holder(&store_lock_path(store_path));
+        drop(lock);
+        fs::remove_dir_all(watch_path).unwrap();
+
+        assert_eq!(holder, Some(process::id()));
+    }
+}
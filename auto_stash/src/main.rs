@@ -1,3 +1,4 @@
+use auto_stash::cli::{self, Args};
 use auto_stash::{AutoStash, Config};
 use event_handle::event_handle::EventHandleCommunication;
 use flume::unbounded;
@@ -5,22 +6,94 @@ use std::{process, thread};
 use ui::ui::{UICommunication, UI};
 
 fn main() {
+    let args: Args = argh::from_env();
+    let config = Config::new(args.config).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        process::exit(cli::FAILED);
+    });
+
+    if let Some(command) = args.command {
+        let code = cli::run(&config, command).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            cli::FAILED
+        });
+        process::exit(code);
+    }
+
     let (file_versions_to_ui, on_file_versions) = unbounded();
     let (undo_to_handle, on_undo) = unbounded();
     let (redo_to_handle, on_redo) = unbounded();
+    let (jump_to_handle, on_jump) = unbounded();
+    let (stash_to_handle, on_stash) = unbounded();
+    let (note_to_handle, on_note) = unbounded();
+    let (search_to_handle, on_search) = unbounded();
+    let (hits_to_ui, on_hits) = unbounded();
+    let (blame_to_handle, on_blame) = unbounded();
+    let (blame_to_ui, on_blame_result) = unbounded();
+    let (preview_to_handle, on_preview_restore) = unbounded();
+    let (preview_to_ui, on_preview) = unbounded();
+    let (restore_changes_to_handle, on_restore_changes) = unbounded();
+    let (diff_to_handle, on_diff_between) = unbounded();
+    let (diff_to_ui, on_diff) = unbounded();
+    let (error_to_ui, on_error) = unbounded();
+    let (status_to_ui, on_status) = unbounded();
     let (time_frame_change_to_handle, on_time_frame_change) = unbounded();
     let (key_to_ui, on_key) = unbounded();
     let (quit_to_ui, on_quit) = unbounded();
     let (quit_to_handle, on_handle_quit) = unbounded();
 
+    let mut auto_stash = AutoStash::new(
+        &config,
+        args.read_only,
+        EventHandleCommunication {
+            file_versions_to_ui,
+            on_undo,
+            on_redo,
+            on_jump,
+            on_stash,
+            on_note,
+            on_search,
+            hits_to_ui,
+            on_blame,
+            blame_to_ui,
+            on_preview_restore,
+            preview_to_ui,
+            on_restore_changes,
+            on_diff_between,
+            diff_to_ui,
+            error_to_ui,
+            status_to_ui,
+            on_time_frame_change,
+        },
+        on_handle_quit,
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Problem creating auto stash: {}", err);
+        process::exit(1);
+    });
+
     let ui = UI::new(
         "".to_string(),
         UICommunication {
             on_file_versions,
+            on_error,
+            on_status,
             on_key,
             on_quit,
             undo_to_handle,
             redo_to_handle,
+            jump_to_handle,
+            stash_to_handle,
+            note_to_handle,
+            search_to_handle,
+            on_hits,
+            blame_to_handle,
+            on_blame: on_blame_result,
+            preview_to_handle,
+            on_preview,
+            restore_changes_to_handle,
+            diff_to_handle,
+            on_diff,
             time_frame_change_to_handle,
             key_to_ui,
             quit_to_ui,
@@ -35,26 +108,6 @@ fn main() {
         });
     });
 
-    let config = Config::new("config.toml".to_string()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {}", err);
-        process::exit(1);
-    });
-
-    let mut auto_stash = AutoStash::new(
-        &config,
-        EventHandleCommunication {
-            file_versions_to_ui,
-            on_undo,
-            on_redo,
-            on_time_frame_change,
-        },
-        on_handle_quit,
-    )
-    .unwrap_or_else(|err| {
-        eprintln!("Problem creating auto stash: {:?}", err);
-        process::exit(1);
-    });
-
     auto_stash.run().unwrap_or_else(|err| {
         eprintln!("Could not run auto stash: {:?}", err);
         process::exit(1);
@@ -0,0 +1,270 @@
+use crate::git::{self, Git};
+use chrono::NaiveDateTime;
+use itertools::Itertools;
+use std::error;
+use std::path::PathBuf;
+use store::store::Store;
+
+/// trailer of a mirrored commit naming the newest version id it covers
+static VERSION_TRAILER: &str = "AutoStash-Version: ";
+
+static IDENTITY: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "AutoStash"),
+    ("GIT_AUTHOR_EMAIL", "autostash@localhost"),
+    ("GIT_COMMITTER_NAME", "AutoStash"),
+    ("GIT_COMMITTER_EMAIL", "autostash@localhost"),
+];
+
+///
+/// exports stored versions as commits on a private reference,
+/// either in the watched repository or in a separate bare one.
+/// only plumbing commands and a private index file are used, so
+/// the user's index, working tree and branches stay untouched.
+///
+pub struct Mirror {
+    git: Git,
+    base: PathBuf,
+    pub reference: String,
+}
+
+impl Mirror {
+    pub fn new(
+        watch_path: &str,
+        bare: Option<String>,
+        branch: Option<String>,
+    ) -> Result<Mirror, Box<dyn error::Error>> {
+        let (git, base) = match bare {
+            Some(bare) => (Git::bare(&bare)?, PathBuf::from(watch_path).canonicalize()?),
+            None => {
+                let git = Git::discover(watch_path)?;
+                let base = git.root.clone();
+                (git, base)
+            }
+        };
+        let branch = match branch {
+            Some(branch) => branch,
+            None => Git::discover(watch_path)
+                .and_then(|git| git.branch())
+                .unwrap_or_else(|_| "main".to_string()),
+        };
+
+        Ok(Mirror {
+            git,
+            base,
+            reference: format!("refs/autostash/{}", branch),
+        })
+    }
+
+    ///
+    /// commits every version stored after the newest one the tip of the
+    /// reference covers, versions recorded in the same second share one
+    /// commit. returns the number of new commits.
+    ///
+    pub fn export(&self, store: &Store) -> Result<usize, Box<dyn error::Error>> {
+        self.git
+            .with_private_index(|index| self.export_with_index(store, index))
+    }
+
+    fn export_with_index(
+        &self,
+        store: &Store,
+        index: &str,
+    ) -> Result<usize, Box<dyn error::Error>> {
+        let index_env = [("GIT_INDEX_FILE", index)];
+        let mut parent = self.git.resolve(&self.reference);
+        let mirrored = match &parent {
+            Some(parent) => {
+                self.git
+                    .git_with(&["read-tree", parent], &index_env, None)?;
+                self.mirrored(parent)?
+            }
+            None => Mirrored::Version(i64::MIN),
+        };
+
+        let mut commits = 0;
+        let mut newest = i64::MIN;
+        // git dates have a resolution of seconds, versions stored within
+        // the same second end up in one commit
+        for (timestamp, snapshots) in &store
+            .snapshots(|id, datetime| match mirrored {
+                Mirrored::Version(mirrored) => id > mirrored,
+                Mirrored::Second(mirrored) => datetime.timestamp() > mirrored,
+            })?
+            .into_iter()
+            .group_by(|snapshot| snapshot.datetime.timestamp())
+        {
+            let datetime = NaiveDateTime::from_timestamp(timestamp, 0);
+            let snapshots = snapshots
+                .filter_map(|snapshot| {
+                    git::relative(&self.base, &snapshot.path).map(|relative| (relative, snapshot))
+                })
+                .collect_vec();
+            if snapshots.is_empty() {
+                continue;
+            }
+            snapshots
+                .iter()
+                .map(|(relative, snapshot)| {
+                    self.git
+                        .stage(index, relative, snapshot.content().as_deref())
+                })
+                .find(|result| result.is_err())
+                .unwrap_or(Ok(()))?;
+
+            let tree = self.git.git_with(&["write-tree"], &index_env, None)?;
+            let notes = snapshots
+                .iter()
+                .filter_map(|(relative, snapshot)| {
+                    let note = store.note(&snapshot.path, snapshot.id)?;
+                    Some(format!("\n{}:\n{}\n", relative, note))
+                })
+                .join("");
+            newest = snapshots
+                .iter()
+                .map(|(_, snapshot)| snapshot.id)
+                .fold(newest, i64::max);
+            let message = format!(
+                "AutoStash {}\n\n{}\n{}\n{}{}\n",
+                datetime,
+                snapshots.iter().map(|(relative, _)| relative).join("\n"),
+                notes,
+                VERSION_TRAILER,
+                newest
+            );
+            let date = format!("{} +0000", timestamp);
+            let envs = [
+                IDENTITY.to_vec(),
+                vec![("GIT_AUTHOR_DATE", &date), ("GIT_COMMITTER_DATE", &date)],
+            ]
+            .concat();
+            let mut args = vec!["commit-tree", tree.trim()];
+            if let Some(parent) = &parent {
+                args.extend(&["-p", parent]);
+            }
+            let commit = self.git.git_with(&args, &envs, Some(&message))?;
+
+            parent = Some(commit.trim().to_string());
+            commits += 1;
+        }
+
+        if let Some(parent) = &parent {
+            self.git.git(&["update-ref", &self.reference, parent])?;
+        }
+
+        Ok(commits)
+    }
+
+    ///
+    /// the newest version the commit covers, commits mirrored before
+    /// the trailer existed only tell their second
+    ///
+    fn mirrored(&self, commit: &str) -> Result<Mirrored, Box<dyn error::Error>> {
+        let message = self.git.git(&["log", "-1", "--format=%B", commit])?;
+        let version = message
+            .lines()
+            .rev()
+            .find_map(|line| line.strip_prefix(VERSION_TRAILER));
+
+        Ok(match version {
+            Some(version) => Mirrored::Version(version.trim().parse()?),
+            None => Mirrored::Second(
+                self.git
+                    .git(&["log", "-1", "--format=%ct", commit])?
+                    .trim()
+                    .parse()?,
+            ),
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mirrored {
+    Version(i64),
+    Second(i64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Repo;
+    use chrono::Utc;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn versions_become_commits_on_a_private_reference() {
+        let repo = Repo::new("autostash_test_mirror");
+        let mut store = repo.store();
+        fs::write(repo.file("a.txt"), "a\nb\n").unwrap();
+        repo.git(&["add", "src/a.txt"]);
+        let head = repo.git(&["rev-parse", "HEAD"]);
+        let index = repo.git(&["ls-files", "--stage"]);
+        repo.edit(&mut store, "b.txt", "x\n");
+
+        let mirror = Mirror::new(&repo.watch_path, None, None).unwrap();
+        let commits = mirror.export(&store).unwrap();
+        let message = repo.git(&["log", "-1", "--format=%B", &mirror.reference]);
+        let newest = store
+            .snapshots(|_, _| true)
+            .unwrap()
+            .last()
+            .unwrap()
+            .datetime;
+        let datetime = NaiveDateTime::from_timestamp(newest.timestamp(), 0);
+
+        assert_eq!(mirror.reference, "refs/autostash/main");
+        assert!(commits >= 1);
+        assert!(message.starts_with(&format!("AutoStash {}\n", datetime)));
+        assert!(message.contains("src/b.txt"));
+        assert_eq!(
+            repo.git(&["show", &format!("{}:src/b.txt", mirror.reference)]),
+            "x\n"
+        );
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+        assert_eq!(repo.git(&["ls-files", "--stage"]), index);
+        assert_eq!(mirror.export(&store).unwrap(), 0);
+    }
+
+    #[test]
+    fn versions_of_a_mirrored_second_are_mirrored_later() {
+        let repo = Repo::new("autostash_test_mirror_second");
+        let mut store = repo.store();
+        let mirror = Mirror::new(&repo.watch_path, None, None).unwrap();
+        mirror.export(&store).unwrap();
+        // both versions and the first export have to fit in one second
+        while Utc::now().timestamp_subsec_millis() > 100 {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        repo.edit(&mut store, "b.txt", "x\n");
+        let first = mirror.export(&store).unwrap();
+        repo.edit(&mut store, "b.txt", "y\n");
+        let second = mirror.export(&store).unwrap();
+        let seconds = store
+            .snapshots(|_, _| true)
+            .unwrap()
+            .iter()
+            .filter(|snapshot| snapshot.path.ends_with("b.txt"))
+            .map(|snapshot| snapshot.datetime.timestamp())
+            .unique()
+            .count();
+        let message = repo.git(&["log", "-1", "--format=%B", &mirror.reference]);
+
+        assert_eq!(seconds, 1);
+        assert_eq!((first, second), (1, 1));
+        assert_eq!(
+            repo.git(&["show", &format!("{}:src/b.txt", mirror.reference)]),
+            "y\n"
+        );
+        assert_eq!(
+            message.trim_end().lines().last().unwrap(),
+            format!(
+                "{}{}",
+                VERSION_TRAILER,
+                store.version_tree(&repo.file("b.txt")).unwrap().current
+            )
+        );
+        assert_eq!(mirror.export(&store).unwrap(), 0);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::git;
+use itertools::Itertools;
+use std::error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use store::store::{Snapshot, Store};
+
+static CONTEXT: usize = 3;
+static NO_FILE: &str = "/dev/null";
+
+///
+/// writes stored versions as unified diffs which apply from the
+/// watched directory with `patch -p0` or `git apply`
+///
+pub struct PatchExport {
+    base: PathBuf,
+    out: PathBuf,
+}
+
+impl PatchExport {
+    pub fn new(watch_path: &str, out: &str) -> Result<PatchExport, Box<dyn error::Error>> {
+        fs::create_dir_all(out)?;
+
+        Ok(PatchExport {
+            base: PathBuf::from(watch_path).canonicalize()?,
+            out: PathBuf::from(out),
+        })
+    }
+
+    ///
+    /// exports every version in `from..=to`, of `path` only if given.
+    /// either one patch per version, numbered in the order they have
+    /// to be applied, or with `squash` a single patch spanning the range.
+    ///
+    pub fn export(
+        &self,
+        store: &Store,
+        path: Option<&str>,
+        from: i64,
+        to: i64,
+        squash: bool,
+    ) -> Result<Vec<PathBuf>, Box<dyn error::Error>> {
+        let path = path.and_then(|path| git::relative(&self.base, path));
+        let snapshots = store
+            .snapshots(|_, _| true)?
+            .into_iter()
+            .filter_map(|snapshot| {
+                let relative = git::relative(&self.base, &snapshot.path)?;
+                match &path {
+                    Some(path) if path.ne(&relative) => None,
+                    _ => Some((relative, snapshot)),
+                }
+            })
+            .collect_vec();
+        // every version paired with the state it started from
+        let versions = snapshots
+            .iter()
+            .enumerate()
+            .map(|(index, (relative, snapshot))| {
+                let previous = snapshots[..index]
+                    .iter()
+                    .rev()
+                    .find(|(other, _)| other.eq(relative))
+                    .map(|(_, previous)| previous);
+                (relative, previous, snapshot)
+            })
+            .filter(|(_, _, snapshot)| (from..=to).contains(&snapshot.datetime.timestamp()))
+            .collect_vec();
+        if versions.is_empty() {
+            return Err("no versions in the selected range".into());
+        }
+
+        if squash {
+            let patch = versions
+                .iter()
+                .unique_by(|(relative, _, _)| relative.as_str())
+                .map(|(relative, previous, _)| {
+                    let last = versions
+                        .iter()
+                        .rev()
+                        .find(|(other, _, _)| other.eq(relative))
+                        .map(|(_, _, snapshot)| *snapshot);
+                    patch(relative, *previous, last)
+                })
+                .join("");
+            let first = versions.first().unwrap().2.datetime.timestamp();
+            let last = versions.last().unwrap().2.datetime.timestamp();
+            let file = self.out.join(format!("autostash-{}-{}.patch", first, last));
+            fs::write(&file, patch)?;
+            return Ok(vec![file]);
+        }
+
+        versions
+            .iter()
+            .map(|(relative, previous, snapshot)| {
+                (
+                    relative,
+                    snapshot,
+                    patch(relative, *previous, Some(*snapshot)),
+                )
+            })
+            .filter(|(_, _, patch)| !patch.is_empty())
+            .map(|(relative, snapshot, patch)| {
+                // text before the first header is ignored by patch and git apply,
+                // prefixed so no line of the note passes for a header
+                let note = store.note(&snapshot.path, snapshot.id);
+                match note {
+                    Some(note) => {
+                        let note = note.lines().map(|line| format!("# {}\n", line)).join("");
+                        (relative, snapshot, format!("{}\n{}", note, patch))
+                    }
+                    None => (relative, snapshot, patch),
+                }
+            })
+            .enumerate()
+            .map(|(index, (relative, snapshot, patch))| {
+                let name = Path::new(relative.as_str())
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or("file");
+                let file = self.out.join(format!(
+                    "{:04}-{}-{}.patch",
+                    index + 1,
+                    name,
+                    snapshot.datetime.timestamp()
+                ));
+                fs::write(&file, patch)?;
+                Ok(file)
+            })
+            .collect()
+    }
+}
+
+///
+/// unified diff of one file. it is missing before its first version and
+/// after a version that removed it, a file emptied by a version stays.
+///
+fn patch(relative: &str, old: Option<&Snapshot>, new: Option<&Snapshot>) -> String {
+    let name = format!("./{}", relative);
+    let exists = |snapshot: Option<&Snapshot>| snapshot.is_some_and(|snapshot| !snapshot.removed);
+    let old_name = if exists(old) { name.as_str() } else { NO_FILE };
+    let new_name = if exists(new) { name.as_str() } else { NO_FILE };
+    let lines = |snapshot: Option<&Snapshot>| {
+        snapshot
+            .map(|snapshot| (snapshot.lines.clone(), snapshot.layout))
+            .unwrap_or_default()
+    };
+    let (old, old_layout) = lines(old);
+    let (new, new_layout) = lines(new);
+
+    diff::unified_with_layout(
+        old_name, new_name, &old, old_layout, &new, new_layout, CONTEXT,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Repo;
+    use std::process::Command;
+
+    /// runs a command in `dir`, panics with its output if it fails
+    fn run(dir: &Path, program: &str, args: &[&str]) {
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} {} failed: {}{}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn exported_history_applies_with_git_and_patch() {
+        let repo = Repo::new("autostash_test_patch");
+        fs::write(repo.file("gone.txt"), "x\n").unwrap();
+        fs::write(repo.file("emptied.txt"), "y\nz\n").unwrap();
+        let mut store = repo.store();
+        repo.edit(&mut store, "a.txt", "a\nb\n");
+        repo.edit(&mut store, "new.txt", "n\n");
+        repo.edit(&mut store, "emptied.txt", "");
+        repo.remove_file(&mut store, "gone.txt");
+        let out = repo.out.join("patches");
+        let applied = repo.out.join("applied");
+        fs::create_dir_all(&applied).unwrap();
+
+        let patches = PatchExport::new(&repo.watch_path, out.to_str().unwrap())
+            .unwrap()
+            .export(&store, None, i64::MIN, i64::MAX, false)
+            .unwrap();
+        let last = fs::read_to_string(patches.last().unwrap()).unwrap();
+        // every version in order, starting from an empty directory
+        for patch in &patches {
+            let patch = patch.canonicalize().unwrap();
+            let patch = patch.to_str().unwrap();
+            run(&applied, "git", &["apply", "--check", patch]);
+            run(&applied, "patch", &["-p0", "--dry-run", "-s", "-i", patch]);
+            run(&applied, "git", &["apply", patch]);
+        }
+
+        assert_eq!(patches.len(), 7);
+        assert!(last.starts_with("--- ./gone.txt\n+++ /dev/null\n"));
+        assert_eq!(fs::read_to_string(applied.join("a.txt")).unwrap(), "a\nb\n");
+        assert_eq!(fs::read_to_string(applied.join("new.txt")).unwrap(), "n\n");
+        assert_eq!(fs::read_to_string(applied.join("emptied.txt")).unwrap(), "");
+        assert!(!applied.join("gone.txt").exists());
+    }
+
+    #[test]
+    fn patches_keep_line_endings() {
+        let repo = Repo::new("autostash_test_patch_layout");
+        let files = [
+            ("open.txt", "a\nb", "a\nc"),
+            ("crlf.txt", "a\r\nb\r\n", "a\r\nc\r\n"),
+        ];
+        files.iter().for_each(|(name, old, _)| {
+            fs::write(repo.file(name), old).unwrap();
+        });
+        let mut store = repo.store();
+        files.iter().for_each(|(name, _, new)| {
+            repo.edit(&mut store, name, new);
+        });
+        let export = PatchExport::new(&repo.watch_path, repo.out.to_str().unwrap()).unwrap();
+
+        for (name, old, new) in files.iter() {
+            let created = repo.out.join("created");
+            let applied = repo.out.join("applied");
+            fs::create_dir_all(&created).unwrap();
+            fs::create_dir_all(&applied).unwrap();
+            fs::write(applied.join(name), old).unwrap();
+            let patches = export
+                .export(&store, Some(&repo.file(name)), i64::MIN, i64::MAX, false)
+                .unwrap();
+            let edit = patches[1].canonicalize().unwrap();
+            let edit = edit.to_str().unwrap();
+            // on top of the file as it was before the store
+            run(&applied, "git", &["apply", "--check", edit]);
+            run(&applied, "patch", &["-p0", "--dry-run", "-s", "-i", edit]);
+            run(&applied, "git", &["apply", edit]);
+            for patch in &patches {
+                let patch = patch.canonicalize().unwrap();
+                run(
+                    &created,
+                    "patch",
+                    &["-p0", "-s", "-i", patch.to_str().unwrap()],
+                );
+            }
+
+            assert_eq!(patches.len(), 2);
+            assert_eq!(fs::read_to_string(applied.join(name)).unwrap(), *new);
+            assert_eq!(fs::read_to_string(created.join(name)).unwrap(), *new);
+            fs::remove_dir_all(&created).unwrap();
+            fs::remove_dir_all(&applied).unwrap();
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::git::Git;
+use itertools::Itertools;
+use std::error;
+use store::store::{Snapshot, Store};
+
+///
+/// turns stored versions into regular `git stash` entries whose
+/// working tree is HEAD with the reconstructed files laid on top,
+/// so they can be applied anywhere with `git stash apply`
+///
+pub struct Stash {
+    git: Git,
+}
+
+impl Stash {
+    pub fn new(watch_path: &str) -> Result<Stash, Box<dyn error::Error>> {
+        Ok(Stash {
+            git: Git::discover(watch_path)?,
+        })
+    }
+
+    ///
+    /// stashes the newest state of every file with a version in
+    /// `from..=to`, or only of `path` if given. returns the name and
+    /// message of the new entry.
+    ///
+    pub fn create(
+        &self,
+        store: &Store,
+        path: Option<&str>,
+        from: i64,
+        to: i64,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let path = path.and_then(|path| self.git.relative(path));
+        let snapshots = store
+            .snapshots(|_, datetime| (from..=to).contains(&datetime.timestamp()))?
+            .into_iter()
+            .filter(|snapshot| match &path {
+                Some(path) => self.git.relative(&snapshot.path).as_ref() == Some(path),
+                None => true,
+            })
+            .rev()
+            .unique_by(|snapshot| snapshot.path.clone())
+            .collect_vec();
+        if snapshots.is_empty() {
+            return Err("no versions in the selected range".into());
+        }
+        let message = match (path, snapshots.as_slice()) {
+            (Some(_), [snapshot]) => format!("AutoStash {}", snapshot.datetime),
+            _ => format!(
+                "AutoStash {} file(s) until {}",
+                snapshots.len(),
+                snapshots
+                    .iter()
+                    .map(|snapshot| snapshot.datetime)
+                    .max()
+                    .unwrap()
+            ),
+        };
+
+        self.git
+            .with_private_index(|index| self.store_entry(index, &snapshots, &message))
+    }
+
+    fn store_entry(
+        &self,
+        index: &str,
+        snapshots: &[Snapshot],
+        message: &str,
+    ) -> Result<String, Box<dyn error::Error>> {
+        let index_env = [("GIT_INDEX_FILE", index)];
+        let head = self
+            .git
+            .resolve("HEAD")
+            .ok_or("git stash needs a commit to stash on top of")?;
+        let head_tree = self
+            .git
+            .git(&["rev-parse", &format!("{}^{{tree}}", head)])?;
+        let branch = self.git.branch()?;
+
+        self.git.git_with(&["read-tree", &head], &index_env, None)?;
+        snapshots
+            .iter()
+            .map(|snapshot| {
+                let relative = self
+                    .git
+                    .relative(&snapshot.path)
+                    .ok_or_else(|| format!("{} is outside of the repository", snapshot.path))?;
+                self.git
+                    .stage(index, &relative, snapshot.content().as_deref())
+            })
+            .find(|result| result.is_err())
+            .unwrap_or(Ok(()))?;
+        let tree = self.git.git_with(&["write-tree"], &index_env, None)?;
+        if tree.trim() == head_tree.trim() {
+            return Err("the selected versions don't differ from HEAD".into());
+        }
+
+        // a stash is a merge of HEAD and a commit holding the index,
+        // which stays untouched here
+        let subject = self.git.git(&["log", "-1", "--format=%h %s", &head])?;
+        let index_message = format!("index on {}: {}", branch, subject.trim());
+        let index_commit = self.git.git_with(
+            &["commit-tree", head_tree.trim(), "-p", &head],
+            &[],
+            Some(&index_message),
+        )?;
+        let stash_message = format!("On {}: {}", branch, message);
+        let stash_commit = self.git.git_with(
+            &[
+                "commit-tree",
+                tree.trim(),
+                "-p",
+                &head,
+                "-p",
+                index_commit.trim(),
+            ],
+            &[],
+            Some(&stash_message),
+        )?;
+        self.git
+            .git(&["stash", "store", "-m", &stash_message, stash_commit.trim()])?;
+
+        Ok(format!("stash@{{0}}: {}", stash_message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Repo;
+    use std::fs;
+
+    #[test]
+    fn stashed_versions_apply_with_git() {
+        let repo = Repo::new("autostash_test_stash");
+        let mut store = repo.store();
+        repo.edit(&mut store, "a.txt", "a\nb\n");
+        let head = repo.git(&["rev-parse", "HEAD"]);
+
+        let entry = Stash::new(&repo.watch_path)
+            .unwrap()
+            .create(&store, Some(&repo.file("a.txt")), 0, i64::MAX)
+            .unwrap();
+        repo.git(&["checkout", "-q", "--", "src/a.txt"]);
+        let restored = fs::read_to_string(repo.file("a.txt")).unwrap();
+        repo.git(&["stash", "apply", "-q"]);
+
+        assert!(entry.starts_with("stash@{0}: On main: AutoStash "));
+        assert!(repo.git(&["stash", "list"]).contains("On main: AutoStash "));
+        assert_eq!(restored, "a\n");
+        assert_eq!(fs::read_to_string(repo.file("a.txt")).unwrap(), "a\nb\n");
+        assert_eq!(repo.git(&["rev-parse", "HEAD"]), head);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{ChangeKind, Layout, LineDifference};
+use std::error;
+use std::fmt;
+
+///
+/// a change that doesn't fit the content it is applied to: the line it
+/// points at is missing or holds something else than it recorded
+///
+#[derive(Debug, PartialEq, Eq)]
+pub enum ApplyError {
+    OutOfRange {
+        line_number: usize,
+        lines: usize,
+    },
+    Mismatch {
+        line_number: usize,
+        expected: String,
+        found: String,
+    },
+    /// a blob doesn't record the content it replaced
+    Blob,
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfRange { line_number, lines } => write!(
+                f,
+                "line {} doesn't exist, the content has {} line(s)",
+                line_number, lines
+            ),
+            Self::Mismatch {
+                line_number,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {} should be {:?} but is {:?}",
+                line_number, expected, found
+            ),
+            Self::Blob => write!(f, "a file stored whole can't be reverted"),
+        }
+    }
+}
+
+impl error::Error for ApplyError {}
+
+///
+/// applies `changes` in the order they were found to `base`, the result
+/// keeps the line endings and final newline of `base`. any change that
+/// doesn't fit is refused.
+///
+pub fn apply(base: &str, changes: &[LineDifference]) -> Result<String, Box<dyn error::Error>> {
+    apply_with_layout(base, changes, Layout::split(base).1)
+}
+
+///
+/// like `apply`, but lays the result out as `layout`, the layout of the
+/// content the changes lead to, so it comes back byte for byte
+///
+pub fn apply_with_layout(
+    base: &str,
+    changes: &[LineDifference],
+    layout: Layout,
+) -> Result<String, Box<dyn error::Error>> {
+    let mut lines = Layout::split(base).0;
+    apply_lines(&mut lines, changes)?;
+
+    Ok(layout.join(&lines))
+}
+
+///
+/// undoes `changes` that were applied to get `current`, the result keeps
+/// the line endings and final newline of `current`
+///
+pub fn revert(current: &str, changes: &[LineDifference]) -> Result<String, Box<dyn error::Error>> {
+    revert_with_layout(current, changes, Layout::split(current).1)
+}
+
+/// like `revert`, but lays the result out as `layout`
+pub fn revert_with_layout(
+    current: &str,
+    changes: &[LineDifference],
+    layout: Layout,
+) -> Result<String, Box<dyn error::Error>> {
+    let inverse = changes
+        .iter()
+        .rev()
+        .map(inverse)
+        .collect::<Result<Vec<LineDifference>, ApplyError>>()?;
+
+    apply_with_layout(current, &inverse, layout)
+}
+
+///
+/// applies `changes` in the order they were found to `lines`, the way
+/// `patch` does, but refuses any change that doesn't fit instead of
+/// clamping it
+///
+pub fn apply_lines(lines: &mut Vec<String>, changes: &[LineDifference]) -> Result<(), ApplyError> {
+    changes
+        .iter()
+        .try_for_each(|change| apply_change(lines, change))
+}
+
+fn apply_change(lines: &mut Vec<String>, change: &LineDifference) -> Result<(), ApplyError> {
+    let changed_line = change.changed_line.clone().unwrap_or_default();
+    match change.kind {
+        ChangeKind::Added if change.line_number > lines.len() => Err(ApplyError::OutOfRange {
+            line_number: change.line_number,
+            lines: lines.len(),
+        }),
+        ChangeKind::Added => {
+            lines.insert(change.line_number, changed_line);
+            Ok(())
+        }
+        ChangeKind::Removed => {
+            expect(lines, change)?;
+            lines.remove(change.line_number);
+            Ok(())
+        }
+        ChangeKind::Modified => {
+            expect(lines, change)?;
+            lines[change.line_number] = changed_line;
+            Ok(())
+        }
+        ChangeKind::Blob => {
+            *lines = Layout::split(&changed_line).0;
+            Ok(())
+        }
+    }
+}
+
+/// checks that the line a change points at is the one it recorded
+fn expect(lines: &[String], change: &LineDifference) -> Result<(), ApplyError> {
+    let found = lines
+        .get(change.line_number)
+        .ok_or(ApplyError::OutOfRange {
+            line_number: change.line_number,
+            lines: lines.len(),
+        })?;
+    match &change.line {
+        Some(expected) if expected != found => Err(ApplyError::Mismatch {
+            line_number: change.line_number,
+            expected: expected.clone(),
+            found: found.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// the change that takes back `change` at the same position
+fn inverse(change: &LineDifference) -> Result<LineDifference, ApplyError> {
+    let (line, changed_line) = (change.line.clone(), change.changed_line.clone());
+    let path = change.path.clone();
+    let inverse = match change.kind {
+        ChangeKind::Added => {
+            LineDifference::removed(path, change.line_number, changed_line.unwrap_or_default())
+        }
+        ChangeKind::Removed => {
+            LineDifference::added(path, change.line_number, line.unwrap_or_default())
+        }
+        ChangeKind::Modified => LineDifference::modified(
+            path,
+            change.line_number,
+            changed_line.unwrap_or_default(),
+            line.unwrap_or_default(),
+        ),
+        ChangeKind::Blob => return Err(ApplyError::Blob),
+    };
+
+    Ok(LineDifference {
+        moved: change.moved,
+        ..inverse
+    })
+}
+
+/// lines the way files are written by default, each ending with a newline
+pub fn content(lines: &[String]) -> String {
+    Layout::default().join(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_between, Algorithm, Ignore, MIN_MOVED_LINES};
+    use proptest::prelude::*;
+
+    ///
+    /// content drawn from a few lines, so both sides share some of them.
+    /// lines end with "\n", "\r\n" or a mix of both, the last one may
+    /// have no ending, and the content may be empty.
+    ///
+    fn content() -> impl Strategy<Value = String> {
+        let lines = prop::collection::vec(
+            prop_oneof![
+                Just(String::new()),
+                Just("    ".to_string()),
+                Just("a\r".to_string()),
+                "[a-c]",
+                "[a-z ]{0,12}",
+            ],
+            0..40,
+        );
+        // none picks the ending of every line on its own
+        let ending = prop_oneof![Just(Some("\n")), Just(Some("\r\n")), Just(None)];
+        let mixed = prop::collection::vec(prop_oneof![Just("\n"), Just("\r\n")], 40);
+
+        (lines, ending, mixed, any::<bool>()).prop_map(|(lines, ending, mixed, final_newline)| {
+            let last = lines.len().saturating_sub(1);
+            lines
+                .iter()
+                .zip(mixed)
+                .enumerate()
+                .map(
+                    |(index, (line, newline))| match index == last && !final_newline {
+                        true => line.clone(),
+                        false => format!("{}{}", line, ending.unwrap_or(newline)),
+                    },
+                )
+                .collect()
+        })
+    }
+
+    fn algorithm() -> impl Strategy<Value = Algorithm> {
+        prop_oneof![
+            Just(Algorithm::Myers),
+            Just(Algorithm::Patience),
+            Just(Algorithm::Histogram),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn applying_found_changes_gives_the_new_content(
+            old in content(),
+            new in content(),
+            algorithm in algorithm(),
+            min_moved in prop_oneof![Just(0), Just(1), Just(MIN_MOVED_LINES)],
+        ) {
+            let (old_lines, old_layout) = Layout::split(&old);
+            let (new_lines, new_layout) = Layout::split(&new);
+            let changes = find_between("test.txt", &old_lines, &new_lines, algorithm, Ignore::default(), min_moved);
+
+            prop_assert_eq!(apply_with_layout(&old, &changes, new_layout).unwrap(), new.clone());
+            prop_assert_eq!(revert_with_layout(&new, &changes, old_layout).unwrap(), old.clone());
+            prop_assert_eq!(apply(&old, &changes).unwrap(), old_layout.join(&new_lines));
+            prop_assert_eq!(revert(&new, &changes).unwrap(), new_layout.join(&old_lines));
+        }
+    }
+
+    #[test]
+    fn changes_that_dont_fit_are_refused() {
+        let path = "test.txt".to_string();
+        let removed = LineDifference::removed(path.clone(), 1, "b".to_string());
+        let added = LineDifference::added(path.clone(), 5, "x".to_string());
+        let blob = LineDifference::blob(path.clone(), "a\n".to_string());
+
+        let mismatch = apply("a\nc\n", std::slice::from_ref(&removed)).unwrap_err();
+        let out_of_range = apply("a\n", &[added]).unwrap_err();
+
+        assert_eq!(apply("", &[]).unwrap(), "");
+        assert_eq!(apply("a\nb\n", &[removed]).unwrap(), "a\n");
+        assert_eq!(
+            mismatch.downcast_ref::<ApplyError>(),
+            Some(&ApplyError::Mismatch {
+                line_number: 1,
+                expected: "b".to_string(),
+                found: "c".to_string(),
+            })
+        );
+        assert_eq!(
+            out_of_range.downcast_ref::<ApplyError>(),
+            Some(&ApplyError::OutOfRange {
+                line_number: 5,
+                lines: 1,
+            })
+        );
+        assert_eq!(apply("b\n", std::slice::from_ref(&blob)).unwrap(), "a\n");
+        assert!(revert("a\n", &[blob]).is_err());
+    }
+
+    #[test]
+    fn line_endings_and_the_final_newline_are_kept() {
+        let added = LineDifference::added("test.txt".to_string(), 1, "b".to_string());
+        let (_, unended) = Layout::split("a");
+        let (_, crlf) = Layout::split("a\r\nb");
+
+        assert_eq!(apply("a", &[]).unwrap(), "a");
+        assert_eq!(
+            apply("a\r\n", std::slice::from_ref(&added)).unwrap(),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(
+            apply_with_layout("a", std::slice::from_ref(&added), crlf).unwrap(),
+            "a\r\nb"
+        );
+        assert_eq!(
+            revert_with_layout("a\r\nb", &[added], unended).unwrap(),
+            "a"
+        );
+    }
+}
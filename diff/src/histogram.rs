@@ -0,0 +1,101 @@
+use crate::myers;
+use std::collections::HashMap;
+
+/// lines occurring more often than this are no good anchors
+const MAX_OCCURRENCES: usize = 64;
+
+///
+/// longest common subsequence found by histogram diff, the way git does
+/// it: the common run containing the rarest line of the old side splits
+/// both sides, which are then diffed on their own. parts where every
+/// line is too common fall back to Myers.
+///
+pub fn matches(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; old.len()];
+    common(old, new, 0, 0, &mut matches);
+
+    matches
+}
+
+fn common(old: &[String], new: &[String], x: usize, y: usize, matches: &mut [Option<usize>]) {
+    let (prefix, suffix) = myers::trim(old, new, x, y, matches);
+    let (old, new) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let (x, y) = (x + prefix, y + prefix);
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+
+    let (start_old, start_new, length) = match rarest_run(old, new) {
+        Some(run) => run,
+        None => return myers::fallback(old, new, x, y, matches),
+    };
+    common(&old[..start_old], &new[..start_new], x, y, matches);
+    (0..length).for_each(|index| matches[x + start_old + index] = Some(y + start_new + index));
+    let (end_old, end_new) = (start_old + length, start_new + length);
+    common(
+        &old[end_old..],
+        &new[end_new..],
+        x + end_old,
+        y + end_new,
+        matches,
+    );
+}
+
+///
+/// the run of common lines whose rarest line occurs least often on the
+/// old side, longer runs win ties. none if no line is rare enough.
+///
+fn rarest_run(old: &[String], new: &[String]) -> Option<(usize, usize, usize)> {
+    let mut positions: HashMap<&String, Vec<usize>> = HashMap::new();
+    old.iter()
+        .enumerate()
+        .for_each(|(index, line)| positions.entry(line).or_default().push(index));
+
+    // (occurrences, length, start in old, start in new)
+    let mut best: Option<(usize, usize, usize, usize)> = None;
+    let mut j = 0;
+    while j < new.len() {
+        let mut next = j + 1;
+        if let Some(indexes) = positions.get(&new[j]) {
+            if indexes.len() <= MAX_OCCURRENCES {
+                for &i in indexes {
+                    let (mut start_old, mut start_new) = (i, j);
+                    while start_old > 0 && start_new > 0 && old[start_old - 1] == new[start_new - 1]
+                    {
+                        start_old -= 1;
+                        start_new -= 1;
+                    }
+                    let (mut end_old, mut end_new) = (i + 1, j + 1);
+                    while end_old < old.len() && end_new < new.len() && old[end_old] == new[end_new]
+                    {
+                        end_old += 1;
+                        end_new += 1;
+                    }
+                    let occurrences = old[start_old..end_old]
+                        .iter()
+                        .map(|line| positions[line].len())
+                        .min()
+                        .unwrap_or(usize::MAX);
+                    let length = end_old - start_old;
+                    let better = match best {
+                        Some((best_occurrences, best_length, _, _)) => {
+                            occurrences < best_occurrences
+                                || (occurrences == best_occurrences && length > best_length)
+                        }
+                        None => true,
+                    };
+                    if better {
+                        best = Some((occurrences, length, start_old, start_new));
+                    }
+                    next = next.max(end_new);
+                }
+            }
+        }
+        j = next;
+    }
+
+    best.map(|(_, length, start_old, start_new)| (start_old, start_new, length))
+}
@@ -0,0 +1,99 @@
+use crate::LineDifference;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+///
+/// differences that don't count as changes, the way `diff -b -w -B -i`
+/// ignores them. lines are still stored as they are, ignoring only
+/// decides which lines are matched up and which changes are shown.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Ignore {
+    /// changes in the amount of whitespace
+    pub whitespace_change: bool,
+    /// any whitespace
+    pub all_whitespace: bool,
+    /// added or removed blank lines
+    pub blank_lines: bool,
+    /// letter case
+    pub case: bool,
+}
+
+impl Ignore {
+    /// the line as it is compared
+    pub fn normalize(&self, line: &str) -> String {
+        let line = if self.all_whitespace {
+            line.chars().filter(|c| !c.is_whitespace()).collect()
+        } else if self.whitespace_change {
+            let leading = if line.starts_with(char::is_whitespace) {
+                " "
+            } else {
+                ""
+            };
+            format!("{}{}", leading, line.split_whitespace().join(" "))
+        } else {
+            line.to_string()
+        };
+
+        if self.case {
+            line.to_lowercase()
+        } else {
+            line
+        }
+    }
+
+    /// whether a change is left after ignoring what should be ignored
+    pub fn is_significant(&self, change: &LineDifference) -> bool {
+        match (&change.line, &change.changed_line) {
+            (Some(line), Some(changed_line)) => {
+                self.normalize(line) != self.normalize(changed_line)
+            }
+            (Some(line), None) | (None, Some(line)) => {
+                !(self.blank_lines && line.trim().is_empty())
+            }
+            (None, None) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignored_differences_are_insignificant() {
+        let modified = |line: &str, changed_line: &str| {
+            LineDifference::modified(
+                "test.rs".to_string(),
+                0,
+                line.to_string(),
+                changed_line.to_string(),
+            )
+        };
+        let blank = LineDifference::added("test.rs".to_string(), 1, "  ".to_string());
+        let whitespace_change = Ignore {
+            whitespace_change: true,
+            ..Ignore::default()
+        };
+        let all_whitespace = Ignore {
+            all_whitespace: true,
+            ..Ignore::default()
+        };
+        let case = Ignore {
+            case: true,
+            blank_lines: true,
+            ..Ignore::default()
+        };
+
+        assert!(Ignore::default().is_significant(&modified("  a  b", "    a b")));
+        assert!(!whitespace_change.is_significant(&modified("  a  b", "    a b ")));
+        assert!(whitespace_change.is_significant(&modified("a", "  a")));
+        assert!(whitespace_change.is_significant(&modified("ab", "a b")));
+        assert!(!all_whitespace.is_significant(&modified("ab", " a b")));
+        assert!(!case.is_significant(&modified("Let A", "let a")));
+        assert!(case.is_significant(&modified("let a", "let  a")));
+        assert!(Ignore::default().is_significant(&blank));
+        assert!(!case.is_significant(&blank));
+    }
+}
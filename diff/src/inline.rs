@@ -0,0 +1,142 @@
+use crate::myers;
+
+///
+/// how finely a modified line is split to find what changed in it.
+/// `Line` leaves it whole.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Granularity {
+    #[default]
+    Line,
+    Word,
+    Char,
+}
+
+impl Granularity {
+    /// the next finer granularity, wrapping around to whole lines
+    pub fn next(&self) -> Granularity {
+        match self {
+            Self::Line => Self::Word,
+            Self::Word => Self::Char,
+            Self::Char => Self::Line,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Line => "line",
+            Self::Word => "word",
+            Self::Char => "char",
+        }
+    }
+}
+
+/// a piece of a line that either survived the change or not
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    pub changed: bool,
+}
+
+///
+/// splits both sides of a modified line into segments, marking what
+/// was removed from `old` and what was added to `new`
+///
+pub fn inline(old: &str, new: &str, granularity: Granularity) -> (Vec<Segment>, Vec<Segment>) {
+    let (old_tokens, new_tokens) = (tokens(old, granularity), tokens(new, granularity));
+    let matches = myers::matches(&old_tokens, &new_tokens);
+    let mut kept = vec![false; new_tokens.len()];
+    matches
+        .iter()
+        .flatten()
+        .for_each(|index| kept[*index] = true);
+
+    (
+        segments(&old_tokens, |index| matches[index].is_some()),
+        segments(&new_tokens, |index| kept[index]),
+    )
+}
+
+/// words are runs of alphanumerics, whitespace runs and any other single character
+fn tokens(line: &str, granularity: Granularity) -> Vec<String> {
+    match granularity {
+        Granularity::Line => vec![line.to_string()],
+        Granularity::Char => line.chars().map(|c| c.to_string()).collect(),
+        Granularity::Word => {
+            let class = |c: char| {
+                if c.is_alphanumeric() || c == '_' {
+                    0
+                } else if c.is_whitespace() {
+                    1
+                } else {
+                    2
+                }
+            };
+            let mut tokens: Vec<String> = vec![];
+            let mut previous = None;
+            for c in line.chars() {
+                match tokens.last_mut() {
+                    Some(token) if previous == Some(class(c)) && class(c) != 2 => token.push(c),
+                    _ => tokens.push(c.to_string()),
+                }
+                previous = Some(class(c));
+            }
+            tokens
+        }
+    }
+}
+
+/// joins neighbouring tokens that are both kept or both changed
+fn segments<F: Fn(usize) -> bool>(tokens: &[String], kept: F) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = vec![];
+    tokens.iter().enumerate().for_each(|(index, token)| {
+        let changed = !kept(index);
+        match segments.last_mut() {
+            Some(segment) if segment.changed == changed => segment.text.push_str(token),
+            _ => segments.push(Segment {
+                text: token.clone(),
+                changed,
+            }),
+        }
+    });
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(segments: &[Segment]) -> Vec<&str> {
+        segments
+            .iter()
+            .filter(|segment| segment.changed)
+            .map(|segment| segment.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn only_changed_spans_are_marked() {
+        let old = "let total = price * amount;";
+        let new = "let total = prize * amount + tax;";
+
+        let (old_words, new_words) = inline(old, new, Granularity::Word);
+        let (old_chars, new_chars) = inline(old, new, Granularity::Char);
+        let (old_line, new_line) = inline(old, new, Granularity::Line);
+
+        assert_eq!(changed(&old_words), vec!["price"]);
+        assert_eq!(changed(&new_words), vec!["prize", " + tax"]);
+        assert_eq!(changed(&old_chars), vec!["c"]);
+        // any of the equally long ways to insert " + tax" will do
+        assert_eq!(changed(&new_chars).concat().len(), "z + tax".len());
+        assert_eq!(changed(&old_line), vec![old]);
+        assert_eq!(changed(&new_line), vec![new]);
+        assert_eq!(
+            new_words
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect::<String>(),
+            new
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+///
+/// how the lines of a file are put together: whether they end with
+/// "\r\n" instead of "\n", and whether the last one has an ending at
+/// all. the lines and their layout give back the exact content.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub crlf: bool,
+    pub final_newline: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            crlf: false,
+            final_newline: true,
+        }
+    }
+}
+
+impl Layout {
+    ///
+    /// splits content into its lines and their layout. a "\r" only counts
+    /// as part of the line ending if every line ends with "\r\n", in files
+    /// mixing both it stays in the line.
+    ///
+    pub fn split(content: &str) -> (Vec<String>, Layout) {
+        if content.is_empty() {
+            return (vec![], Layout::default());
+        }
+        let final_newline = content.ends_with('\n');
+        let mut lines = content.split('\n').map(str::to_string).collect_vec();
+        if final_newline {
+            lines.pop();
+        }
+        let ended = if final_newline {
+            lines.len()
+        } else {
+            lines.len() - 1
+        };
+        let crlf = ended > 0 && lines[..ended].iter().all(|line| line.ends_with('\r'));
+        if crlf {
+            lines[..ended].iter_mut().for_each(|line| {
+                line.pop();
+            });
+        }
+
+        (
+            lines,
+            Layout {
+                crlf,
+                final_newline,
+            },
+        )
+    }
+
+    /// puts the lines together again
+    pub fn join(&self, lines: &[String]) -> String {
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+        let mut content = lines.join(newline);
+        if self.final_newline && !lines.is_empty() {
+            content.push_str(newline);
+        }
+
+        content
+    }
+
+    /// lines ending with "\n", the last one included
+    pub fn is_default(&self) -> bool {
+        *self == Layout::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_is_split_and_joined_exactly() {
+        let contents = [
+            "",
+            "\n",
+            "a",
+            "a\nb\n",
+            "a\r\nb\r\n",
+            "a\r\nb",
+            "a\r\nb\n",
+            "a\r",
+            "\r\n\r\n",
+        ];
+
+        for content in contents.iter() {
+            let (lines, layout) = Layout::split(content);
+            assert_eq!(layout.join(&lines), *content);
+        }
+        assert_eq!(
+            Layout::split("a\r\nb"),
+            (
+                vec!["a".to_string(), "b".to_string()],
+                Layout {
+                    crlf: true,
+                    final_newline: false
+                }
+            )
+        );
+        assert_eq!(
+            Layout::split("a\r\nb\n").0,
+            vec!["a\r".to_string(), "b".to_string()]
+        );
+    }
+}
@@ -1,38 +1,156 @@
-use chrono::{NaiveDateTime, Utc};
-use itertools::Itertools;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{self, BufRead};
+use std::fs;
+use std::io;
+
+pub mod apply;
+mod histogram;
+pub mod ignore;
+pub mod inline;
+mod layout;
+pub mod merge;
+mod moves;
+mod myers;
+mod patience;
+pub mod structural;
+pub mod unified;
+
+pub use apply::{
+    apply, apply_lines, apply_with_layout, content, revert, revert_with_layout, ApplyError,
+};
+pub use ignore::Ignore;
+pub use inline::{inline, Granularity, Segment};
+pub use layout::Layout;
+pub use structural::{structural, KeyChange};
+pub use unified::{unified, unified_with_layout};
+
+///
+/// how the common lines of two states are found. patience and
+/// histogram read better on source code full of repeated lines.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    Myers,
+    Patience,
+    Histogram,
+}
+
+impl Algorithm {
+    /// maps every line of `old` to its partner in `new`, if it has one
+    pub fn matches(&self, old: &[String], new: &[String]) -> Vec<Option<usize>> {
+        match self {
+            Self::Myers => myers::matches(old, new),
+            Self::Patience => patience::matches(old, new),
+            Self::Histogram => histogram::matches(old, new),
+        }
+    }
+}
 
-pub static RFC3339: &str = "%Y-%m-%dT%H:%M:%S%.9f%:z";
+/// lines a block needs at least to be recorded as moved by default
+pub const MIN_MOVED_LINES: usize = 3;
+
+///
+/// what happened to a line: it appeared, disappeared or
+/// its content was replaced. a blob replaces the whole file, which
+/// is how files too large to diff are versioned.
+///
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+    Blob,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Modified => "modified",
+            Self::Blob => "blob",
+        }
+    }
+}
 
+///
+/// a single changed line. `line` is the content before the change,
+/// `changed_line` the content after it, none where the line didn't
+/// exist. a blank line is some empty string.
+///
 #[derive(Serialize, Deserialize, Clone, Debug, Eq)]
 pub struct LineDifference {
     pub path: String,
     pub line_number: usize,
-    pub line: String,
-    pub changed_line: String,
-    pub date_time: String,
+    pub kind: ChangeKind,
+    pub line: Option<String>,
+    pub changed_line: Option<String>,
+    pub date_time: DateTime<Utc>,
+    /// id of the version the change was stored with, unset until stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+    ///
+    /// set if the line is part of a moved block: where a removed line
+    /// went to, where an added line came from
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moved: Option<usize>,
 }
 
 impl LineDifference {
+    ///
+    /// the kind follows from which side has content,
+    /// a change without any is taken as an added blank line
+    ///
     pub fn new(
         path: String,
         line_number: usize,
-        line: String,
-        changed_line: String,
+        line: Option<String>,
+        changed_line: Option<String>,
     ) -> LineDifference {
+        let (kind, changed_line) = match (&line, changed_line) {
+            (None, changed_line) => (ChangeKind::Added, Some(changed_line.unwrap_or_default())),
+            (Some(_), None) => (ChangeKind::Removed, None),
+            (Some(_), changed_line) => (ChangeKind::Modified, changed_line),
+        };
         LineDifference {
             path,
             line_number,
+            kind,
             line,
             changed_line,
-            date_time: Utc::now().to_rfc3339(),
+            date_time: Utc::now(),
+            version: None,
+            moved: None,
         }
     }
 
-    pub fn token() -> LineDifference {
-        LineDifference::new("".to_string(), 0, "".to_string(), "".to_string())
+    pub fn added(path: String, line_number: usize, changed_line: String) -> LineDifference {
+        LineDifference::new(path, line_number, None, Some(changed_line))
+    }
+
+    pub fn removed(path: String, line_number: usize, line: String) -> LineDifference {
+        LineDifference::new(path, line_number, Some(line), None)
+    }
+
+    pub fn modified(
+        path: String,
+        line_number: usize,
+        line: String,
+        changed_line: String,
+    ) -> LineDifference {
+        LineDifference::new(path, line_number, Some(line), Some(changed_line))
+    }
+
+    /// the whole new content of a file, stored in one piece
+    pub fn blob(path: String, content: String) -> LineDifference {
+        LineDifference {
+            kind: ChangeKind::Blob,
+            ..LineDifference::new(path, 0, None, Some(content))
+        }
     }
 }
 
@@ -40,132 +158,171 @@ impl PartialEq for LineDifference {
     fn eq(&self, other: &Self) -> bool {
         self.path.eq(&other.path)
             && self.line_number.eq(&other.line_number)
+            && self.kind.eq(&other.kind)
             && self.line.eq(&other.line)
             && self.changed_line.eq(&other.changed_line)
+            && self.moved.eq(&other.moved)
     }
 }
 
+///
+/// the changes turning the state recorded by `prev_changes` into the
+/// current content of the file at `path`
+///
 pub fn find(
     path: &str,
     prev_changes: &[LineDifference],
+    algorithm: Algorithm,
+    ignore: Ignore,
+    min_moved: usize,
 ) -> Result<Vec<LineDifference>, Box<dyn std::error::Error>> {
-    let prev_changes = &unique_prev_changes(prev_changes);
-    let mut changed_or_added_lines = find_changed_or_added_lines(path, prev_changes)?;
-    let line_count = line_count(path)?;
-
-    if has_removed_lines(prev_changes, line_count) {
-        changed_or_added_lines = [
-            changed_or_added_lines,
-            find_removed_lines(prev_changes, line_count),
-        ]
-        .concat();
-    }
-
-    Ok(changed_or_added_lines)
+    Ok(find_between(
+        path,
+        &replay(prev_changes),
+        &read_lines(path)?,
+        algorithm,
+        ignore,
+        min_moved,
+    ))
 }
 
-fn line_count(path: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let file = File::open(path)?;
-    Ok(io::BufReader::new(&file).lines().count())
+/// the lines of the file at `path`, split as `Layout::split` does
+pub fn read_lines(path: &str) -> io::Result<Vec<String>> {
+    Ok(Layout::split(&fs::read_to_string(path)?).0)
 }
 
-fn has_removed_lines(prev_changes: &[LineDifference], line_count: usize) -> bool {
-    prev_changes.len() > line_count
-}
-
-fn unique_prev_changes(prev_changes: &[LineDifference]) -> Vec<LineDifference> {
-    prev_changes
-        .iter()
-        .sorted_by(|a, b| sort(b.date_time.as_str(), a.date_time.as_str()))
-        .dedup_by(|a, b| a.line_number.eq(&b.line_number))
-        .sorted_by(|a, b| sort(a.date_time.as_str(), b.date_time.as_str()))
-        .cloned()
-        .collect_vec()
-}
+///
+/// the edit script from `old` to `new` around the common lines `algorithm`
+/// finds. line numbers are positions in the file while the changes are applied
+/// one after the other: a removal leaves the following lines where the
+/// removed line was, an addition pushes them down. a removal directly
+/// followed by an addition is a modified line. lines that only differ in
+/// what `ignore` ignores are matched up, but still recorded as modified.
+/// blocks of at least `min_moved` lines that were removed in one place and
+/// added in another are marked as moved, they are removed before and never
+/// paired up with the other lines of their gap.
+///
+pub fn find_between(
+    path: &str,
+    old: &[String],
+    new: &[String],
+    algorithm: Algorithm,
+    ignore: Ignore,
+    min_moved: usize,
+) -> Vec<LineDifference> {
+    let normalized;
+    let (old_keys, new_keys) = if ignore == Ignore::default() {
+        (old, new)
+    } else {
+        let normalize = |lines: &[String]| {
+            lines
+                .iter()
+                .map(|line| ignore.normalize(line))
+                .collect::<Vec<String>>()
+        };
+        normalized = (normalize(old), normalize(new));
+        (&normalized.0[..], &normalized.1[..])
+    };
+    let mut matches = algorithm.matches(old_keys, new_keys);
+    let (moved_old, moved_new) = moves::moves(old_keys, new_keys, &mut matches, min_moved);
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        let next = (i..old.len()).find(|index| matches[*index].is_some());
+        let (end_old, end_new) = match next {
+            Some(index) => (index, matches[index].unwrap()),
+            None => (old.len(), new.len()),
+        };
+        // moved lines leave first, the rest of the gap is paired up in order
+        let mut position = j;
+        (i..end_old).for_each(|index| match moved_old[index] {
+            Some(_) => changes.push(LineDifference {
+                moved: moved_old[index],
+                ..LineDifference::removed(path.to_string(), position, old[index].clone())
+            }),
+            None => position += 1,
+        });
+        let mut kept = (i..end_old).filter(|index| moved_old[*index].is_none());
+        (j..end_new).for_each(|index| {
+            let paired = match moved_new[index] {
+                Some(_) => None,
+                None => kept.next(),
+            };
+            changes.push(match paired {
+                Some(paired) => LineDifference::modified(
+                    path.to_string(),
+                    index,
+                    old[paired].clone(),
+                    new[index].clone(),
+                ),
+                None => LineDifference {
+                    moved: moved_new[index],
+                    ..LineDifference::added(path.to_string(), index, new[index].clone())
+                },
+            })
+        });
+        kept.for_each(|index| {
+            changes.push(LineDifference::removed(
+                path.to_string(),
+                end_new,
+                old[index].clone(),
+            ))
+        });
 
-pub fn sort(date_time_a: &str, date_time_b: &str) -> std::cmp::Ordering {
-    Ord::cmp(
-        &NaiveDateTime::parse_from_str(date_time_a, RFC3339).unwrap(),
-        &NaiveDateTime::parse_from_str(date_time_b, RFC3339).unwrap(),
-    )
-}
+        // the matched line itself stays, unless it differs in ignored ways
+        if next.is_some() && old[end_old] != new[end_new] {
+            changes.push(LineDifference::modified(
+                path.to_string(),
+                end_new,
+                old[end_old].clone(),
+                new[end_new].clone(),
+            ));
+        }
+        i = end_old + 1;
+        j = end_new + 1;
+    }
 
-fn find_removed_lines(
-    prev_changes: &[LineDifference],
-    line_count: usize,
-) -> Vec<LineDifference> {
-    prev_changes
-        .split_at(line_count)
-        .1
-        .iter()
-        .map(|line| {
-            LineDifference::new(
-                line.path.to_string(),
-                line.line_number,
-                line.changed_line.to_string(),
-                "".to_string(),
-            )
-        })
-        .collect()
+    changes
 }
 
-fn find_changed_or_added_lines(
-    path: &str,
-    prev_changes: &[LineDifference],
-) -> Result<Vec<LineDifference>, Box<dyn std::error::Error>> {
-    let file = File::open(path)?;
-    let token = LineDifference::token();
-
-    Ok(io::BufReader::new(file)
-        .lines()
-        .enumerate()
-        .map(|(index, line)| {
-            // TODO: Check
-            if line.is_err() {
-                return token.clone();
+///
+/// applies `changes` in the order they were found to `lines`.
+/// positions past the end are clamped, so a damaged history can't panic.
+///
+pub fn patch(lines: &mut Vec<String>, changes: &[LineDifference]) {
+    changes.iter().for_each(|change| {
+        let changed_line = change.changed_line.clone().unwrap_or_default();
+        match change.kind {
+            ChangeKind::Added => lines.insert(change.line_number.min(lines.len()), changed_line),
+            ChangeKind::Removed if change.line_number < lines.len() => {
+                lines.remove(change.line_number);
             }
-
-            find_changed_or_added_line(prev_changes, index, path, line.unwrap(), &token)
-        })
-        .filter(|e| e.ne(&token))
-        .collect())
+            ChangeKind::Removed => (),
+            ChangeKind::Modified if change.line_number < lines.len() => {
+                lines[change.line_number] = changed_line
+            }
+            ChangeKind::Modified => lines.push(changed_line),
+            ChangeKind::Blob => *lines = Layout::split(&changed_line).0,
+        }
+    });
 }
 
-fn find_changed_or_added_line(
-    prev_changes: &[LineDifference],
-    index: usize,
-    path: &str,
-    line: String,
-    token: &LineDifference,
-) -> LineDifference {
-    let found_change = prev_changes
-        .iter()
-        .find(|found| found.line_number.eq(&index));
-
-    if let Some(found_change) = found_change {
-        if found_change.changed_line.ne(&line) {
-            // changed line
-            return LineDifference::new(
-                path.to_string(),
-                index,
-                found_change.changed_line.clone(),
-                line,
-            );
-        }
-        // unchanged line
-        return token.clone();
-    }
+/// the lines recorded by a log of changes that starts from an empty file
+pub fn replay(changes: &[LineDifference]) -> Vec<String> {
+    let mut lines = vec![];
+    patch(&mut lines, changes);
 
-    // added line
-    LineDifference::new(path.to_string(), index, "".to_string(), line)
+    lines
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
     use std::{
-        fs::{remove_file, OpenOptions},
-        io::Write,
+        fs::{remove_file, File, OpenOptions},
+        io::{BufRead, Write},
     };
 
     fn init(path: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -183,25 +340,15 @@ mod tests {
                 // TODO: Check
                 let line = line.unwrap();
 
-                LineDifference::new(path.to_string(), index, "".to_string(), line)
+                LineDifference::added(path.to_string(), index, line)
             })
             .collect();
 
         Ok([
             res,
             vec![
-                LineDifference::new(
-                    path.to_string(),
-                    4,
-                    "Hello World".to_string(),
-                    "".to_string(),
-                ),
-                LineDifference::new(
-                    path.to_string(),
-                    4,
-                    "".to_string(),
-                    "Hello World".to_string(),
-                ),
+                LineDifference::removed(path.to_string(), 4, "Hello World".to_string()),
+                LineDifference::added(path.to_string(), 4, "Hello World".to_string()),
             ],
         ]
         .concat())
@@ -216,7 +363,8 @@ mod tests {
         init(path).unwrap();
         let changes = read(path).unwrap();
 
-        let new_changes: Vec<LineDifference> = find(path, &changes).unwrap();
+        let new_changes: Vec<LineDifference> =
+            find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
         remove(path).unwrap();
 
         assert_eq!(new_changes, []);
@@ -229,9 +377,9 @@ mod tests {
         let changes = read(path).unwrap();
 
         let mut file = OpenOptions::new().write(true).open(path).unwrap();
-        let mut new_file_content: Vec<String> = unique_prev_changes(&changes)
+        let mut new_file_content: Vec<String> = replay(&changes)
             .iter()
-            .map(|line| line.changed_line.to_string() + "\n")
+            .map(|line| line.clone() + "\n")
             .collect();
         new_file_content[3] = "Hello W0rld\n".to_string();
 
@@ -240,12 +388,13 @@ mod tests {
                 .expect("Couldn't write to file.");
         });
 
-        let new_changes: Vec<LineDifference> = find(path, &changes).unwrap();
+        let new_changes: Vec<LineDifference> =
+            find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
         remove(path).unwrap();
 
         assert_eq!(
             new_changes,
-            vec![LineDifference::new(
+            vec![LineDifference::modified(
                 path.to_string(),
                 3,
                 "Hello World".to_string(),
@@ -262,9 +411,9 @@ mod tests {
 
         let mut file = OpenOptions::new().write(true).open(path).unwrap();
         let new_file_content = [
-            unique_prev_changes(&changes)
+            replay(&changes)
                 .iter()
-                .map(|line| line.clone().changed_line + "\n")
+                .map(|line| line.clone() + "\n")
                 .collect(),
             vec!["Hello World\n".to_string()],
         ]
@@ -275,15 +424,15 @@ mod tests {
                 .expect("Couldn't write to file.");
         });
 
-        let new_changes: Vec<LineDifference> = find(path, &changes).unwrap();
+        let new_changes: Vec<LineDifference> =
+            find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
         remove(path).unwrap();
 
         assert_eq!(
             new_changes,
-            vec![LineDifference::new(
+            vec![LineDifference::added(
                 path.to_string(),
                 5,
-                "".to_string(),
                 "Hello World".to_string()
             )]
         );
@@ -296,25 +445,24 @@ mod tests {
 
         let changes: Vec<LineDifference> = [
             read(path).unwrap(),
-            vec![LineDifference::new(
+            vec![LineDifference::added(
                 path.to_string(),
                 5,
-                "".to_string(),
                 "Hello World".to_string(),
             )],
         ]
         .concat();
 
-        let new_changes: Vec<LineDifference> = find(path, &changes).unwrap();
+        let new_changes: Vec<LineDifference> =
+            find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
         remove(path).unwrap();
 
         assert_eq!(
             new_changes,
-            vec![LineDifference::new(
+            vec![LineDifference::removed(
                 path.to_string(),
                 5,
-                "Hello World".to_string(),
-                "".to_string()
+                "Hello World".to_string()
             )]
         );
     }
@@ -323,15 +471,10 @@ mod tests {
     fn should_compare_only_the_latest_prev_changes() {
         let path = "test5.txt";
 
-        let prev_change = LineDifference::new(
-            path.to_string(),
-            0,
-            "".to_string(),
-            "Hello World".to_string(),
-        );
+        let prev_change = LineDifference::added(path.to_string(), 0, "Hello World".to_string());
         let changes: Vec<LineDifference> = vec![
             prev_change,
-            LineDifference::new(
+            LineDifference::modified(
                 path.to_string(),
                 0,
                 "Hello World".to_string(),
@@ -342,29 +485,259 @@ mod tests {
         file.write_all("Hello World2\nNew Change\n".as_bytes())
             .unwrap();
 
-        let new_changes: Vec<LineDifference> = find(path, &changes).unwrap();
+        let new_changes: Vec<LineDifference> =
+            find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
         remove(path).unwrap();
 
         assert_eq!(
             new_changes,
-            vec![LineDifference::new(
+            vec![LineDifference::added(
                 path.to_string(),
                 1,
-                "".to_string(),
                 "New Change".to_string()
             )]
         );
     }
 
-    #[ignore]
+    #[test]
+    fn differences_between_states() {
+        let path = "test6.txt";
+        let old = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let new = vec!["a".to_string(), "B".to_string()];
+
+        assert_eq!(
+            find_between(path, &old, &new, Algorithm::Myers, Ignore::default(), 0),
+            vec![
+                LineDifference::modified(path.to_string(), 1, "b".to_string(), "B".to_string()),
+                LineDifference::removed(path.to_string(), 2, "c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_lines_are_lines() {
+        let path = "test7.txt";
+        let changes = vec![
+            LineDifference::added(path.to_string(), 0, "a".to_string()),
+            LineDifference::added(path.to_string(), 1, "".to_string()),
+        ];
+        std::fs::write(path, "a\nb\n").unwrap();
+        let filled = find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
+        std::fs::write(path, "a\n").unwrap();
+        let removed = find(path, &changes, Algorithm::Myers, Ignore::default(), 0).unwrap();
+        remove(path).unwrap();
+
+        assert_eq!(
+            filled,
+            vec![LineDifference::modified(
+                path.to_string(),
+                1,
+                "".to_string(),
+                "b".to_string()
+            )]
+        );
+        assert_eq!(removed[0].kind, ChangeKind::Removed);
+        assert_eq!(removed[0].line, Some("".to_string()));
+    }
+
+    #[test]
+    fn insertions_and_deletions_keep_their_positions() {
+        let path = "test8.txt";
+        let to_lines = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect_vec();
+        let old = to_lines(&["a", "b", "c", "d"]);
+        let new = to_lines(&["new", "a", "b", "d", "e"]);
+
+        let changes = find_between(path, &old, &new, Algorithm::Myers, Ignore::default(), 0);
+        let mut patched = old.clone();
+        patch(&mut patched, &changes);
+
+        assert_eq!(
+            changes,
+            vec![
+                LineDifference::added(path.to_string(), 0, "new".to_string()),
+                LineDifference::removed(path.to_string(), 3, "c".to_string()),
+                LineDifference::added(path.to_string(), 4, "e".to_string()),
+            ]
+        );
+        assert_eq!(patched, new);
+        assert_eq!(
+            find_between(path, &new, &old, Algorithm::Myers, Ignore::default(), 0).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn unique_lines_anchor_patience_and_histogram() {
+        let path = "test9.txt";
+        let old = merge::to_lines("fn main() {\n    start();\n}\n}");
+        let new = merge::to_lines("fn main() {\n}\n    start();\n    stop();");
+        let start_removed = |algorithm| {
+            find_between(path, &old, &new, algorithm, Ignore::default(), 0)
+                .iter()
+                .any(|change| change.line == Some("    start();".to_string()))
+        };
+
+        for algorithm in [Algorithm::Myers, Algorithm::Patience, Algorithm::Histogram].iter() {
+            let mut patched = old.clone();
+            patch(
+                &mut patched,
+                &find_between(path, &old, &new, *algorithm, Ignore::default(), 0),
+            );
+            assert_eq!(patched, new);
+        }
+        assert!(start_removed(Algorithm::Myers));
+        assert!(!start_removed(Algorithm::Patience));
+        assert!(!start_removed(Algorithm::Histogram));
+    }
+
+    #[test]
+    fn moved_functions_are_not_new_code() {
+        let path = "test.rs";
+        let old = merge::to_lines("fn a() {\n    1\n}\nfn b() {\n    2\n}\nlet x = 0;");
+        let new = merge::to_lines("fn b() {\n    2\n}\nlet x = 1;\nfn a() {\n    1\n}");
+
+        let changes = find_between(path, &old, &new, Algorithm::Myers, Ignore::default(), 3);
+        let unmoved = find_between(path, &old, &new, Algorithm::Myers, Ignore::default(), 0);
+        let moved = |change: &&LineDifference| change.moved.is_some();
+        let mut lines = old.clone();
+        patch(&mut lines, &changes);
+
+        assert_eq!(lines, new);
+        assert_eq!(
+            changes
+                .iter()
+                .filter(moved)
+                .map(|change| (change.kind, change.moved))
+                .collect_vec(),
+            vec![
+                (ChangeKind::Removed, Some(4)),
+                (ChangeKind::Removed, Some(5)),
+                (ChangeKind::Removed, Some(6)),
+                (ChangeKind::Added, Some(0)),
+                (ChangeKind::Added, Some(1)),
+                (ChangeKind::Added, Some(2)),
+            ]
+        );
+        assert!(changes.contains(&LineDifference::modified(
+            path.to_string(),
+            3,
+            "let x = 0;".to_string(),
+            "let x = 1;".to_string()
+        )));
+        assert!(!unmoved.iter().any(|change| moved(&change)));
+    }
+
+    #[test]
+    fn lines_differing_in_ignored_ways_are_matched_up() {
+        let path = "test10.txt";
+        let old = merge::to_lines("fn a() {\nx\n}");
+        let new = merge::to_lines("fn a() {\n  y\n    x\n}");
+        let ignore = Ignore {
+            all_whitespace: true,
+            ..Ignore::default()
+        };
+
+        let changes = find_between(path, &old, &new, Algorithm::Myers, ignore, 0);
+        let mut patched = old.clone();
+        patch(&mut patched, &changes);
+
+        assert_eq!(
+            changes,
+            vec![
+                LineDifference::added(path.to_string(), 1, "  y".to_string()),
+                LineDifference::modified(path.to_string(), 2, "x".to_string(), "    x".to_string()),
+            ]
+        );
+        assert_eq!(patched, new);
+        assert_eq!(
+            changes
+                .iter()
+                .filter(|change| ignore.is_significant(change))
+                .count(),
+            1
+        );
+    }
+
     #[test]
     fn a_lot_of_lines() {
-        unimplemented!();
+        let path = "test_a_lot_of_lines.txt";
+        let mut lines = (0..100_000).map(|i| format!("line {}", i)).collect_vec();
+        let mut history = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| LineDifference::added(path.to_string(), index, line.clone()))
+            .collect_vec();
+        history.extend((0..1000).map(|index| {
+            LineDifference::modified(
+                path.to_string(),
+                index * 100,
+                lines[index * 100].clone(),
+                lines[index * 100].clone(),
+            )
+        }));
+        lines[10] = "changed".to_string();
+        lines[50_000] = "changed".to_string();
+        lines.insert(99_000, "inserted".to_string());
+        std::fs::write(path, lines.join("\n")).unwrap();
+
+        let changes = find(path, &history, Algorithm::Myers, Ignore::default(), 0).unwrap();
+        remove(path).unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                LineDifference::modified(
+                    path.to_string(),
+                    10,
+                    "line 10".to_string(),
+                    "changed".to_string()
+                ),
+                LineDifference::modified(
+                    path.to_string(),
+                    50_000,
+                    "line 50000".to_string(),
+                    "changed".to_string()
+                ),
+                LineDifference::added(path.to_string(), 99_000, "inserted".to_string()),
+            ]
+        );
     }
 
-    #[ignore]
     #[test]
     fn a_lot_of_differences() {
-        unimplemented!();
+        let old = (0..10_000).map(|i| (i % 97).to_string()).collect_vec();
+        let new = (0..10_000).map(|i| (i * 31 % 89).to_string()).collect_vec();
+        let rewritten = (0..10_000).map(|i| format!("new {}", i)).collect_vec();
+
+        for algorithm in [Algorithm::Myers, Algorithm::Histogram] {
+            let mut lines = old.clone();
+            patch(
+                &mut lines,
+                &find_between(
+                    "test.txt",
+                    &old,
+                    &new,
+                    algorithm,
+                    Ignore::default(),
+                    MIN_MOVED_LINES,
+                ),
+            );
+            assert_eq!(lines, new);
+        }
+        let changes = find_between(
+            "test.txt",
+            &old,
+            &rewritten,
+            Algorithm::Myers,
+            Ignore::default(),
+            0,
+        );
+        let mut lines = old.clone();
+        patch(&mut lines, &changes);
+
+        assert!(changes
+            .iter()
+            .all(|change| change.kind == ChangeKind::Modified));
+        assert_eq!(lines, rewritten);
     }
 }
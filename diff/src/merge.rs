@@ -0,0 +1,150 @@
+use crate::myers::matches;
+use crate::Layout;
+
+pub static CONFLICT_START: &str = "<<<<<<< current";
+pub static CONFLICT_SEPARATOR: &str = "=======";
+pub static CONFLICT_END: &str = ">>>>>>> restored";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub lines: Vec<String>,
+    pub conflicts: usize,
+}
+
+///
+/// line based three-way merge of `current` and `restored`, which both
+/// derive from `base`. conflicting regions are wrapped in
+/// conflict markers and counted.
+///
+pub fn merge3(base: &[String], current: &[String], restored: &[String]) -> Merge {
+    let current_matches = matches(base, current);
+    let restored_matches = matches(base, restored);
+    let mut merge = Merge {
+        lines: vec![],
+        conflicts: 0,
+    };
+    let (mut i_base, mut i_current, mut i_restored) = (0, 0, 0);
+
+    loop {
+        let mut stable = 0;
+        while i_base + stable < base.len()
+            && current_matches[i_base + stable] == Some(i_current + stable)
+            && restored_matches[i_base + stable] == Some(i_restored + stable)
+        {
+            stable += 1;
+        }
+        if stable > 0 {
+            merge
+                .lines
+                .extend_from_slice(&base[i_base..i_base + stable]);
+            i_base += stable;
+            i_current += stable;
+            i_restored += stable;
+            continue;
+        }
+
+        let next = (i_base..base.len())
+            .find(|index| current_matches[*index].is_some() && restored_matches[*index].is_some());
+        let (end_base, end_current, end_restored) = match next {
+            Some(index) => (
+                index,
+                current_matches[index].unwrap(),
+                restored_matches[index].unwrap(),
+            ),
+            None => (base.len(), current.len(), restored.len()),
+        };
+
+        resolve(
+            &mut merge,
+            &base[i_base..end_base],
+            &current[i_current..end_current],
+            &restored[i_restored..end_restored],
+        );
+
+        if next.is_none() {
+            break;
+        }
+        i_base = end_base;
+        i_current = end_current;
+        i_restored = end_restored;
+    }
+
+    merge
+}
+
+fn resolve(merge: &mut Merge, base: &[String], current: &[String], restored: &[String]) {
+    if current == base || current == restored {
+        merge.lines.extend_from_slice(restored);
+    } else if restored == base {
+        merge.lines.extend_from_slice(current);
+    } else {
+        merge.conflicts += 1;
+        merge.lines.push(CONFLICT_START.to_string());
+        merge.lines.extend_from_slice(current);
+        merge.lines.push(CONFLICT_SEPARATOR.to_string());
+        merge.lines.extend_from_slice(restored);
+        merge.lines.push(CONFLICT_END.to_string());
+    }
+}
+
+/// the lines of some content, split as `Layout::split` does
+pub fn to_lines(content: &str) -> Vec<String> {
+    Layout::split(content).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_current_takes_restored() {
+        let base = to_lines("a\nb\nc");
+        let restored = to_lines("a\nc");
+
+        assert_eq!(
+            merge3(&base, &base, &restored),
+            Merge {
+                lines: restored,
+                conflicts: 0
+            }
+        );
+    }
+
+    #[test]
+    fn independent_edits_are_combined() {
+        let base = to_lines("a\nb\nc\nd\ne");
+        let current = to_lines("a\nB\nc\nd\ne");
+        let restored = to_lines("a\nb\nc\nd");
+
+        assert_eq!(
+            merge3(&base, &current, &restored),
+            Merge {
+                lines: to_lines("a\nB\nc\nd"),
+                conflicts: 0
+            }
+        );
+    }
+
+    #[test]
+    fn overlapping_edits_conflict() {
+        let base = to_lines("a\nb\nc");
+        let current = to_lines("a\nx\nc");
+        let restored = to_lines("a\ny\nc");
+
+        let merge = merge3(&base, &current, &restored);
+
+        assert_eq!(merge.conflicts, 1);
+        assert_eq!(
+            merge.lines,
+            vec![
+                "a",
+                CONFLICT_START,
+                "x",
+                CONFLICT_SEPARATOR,
+                "y",
+                CONFLICT_END,
+                "c"
+            ]
+        );
+    }
+}
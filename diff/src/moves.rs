@@ -0,0 +1,131 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// removed places a moved block is searched at, per added line
+const MAX_CANDIDATES: usize = 64;
+
+///
+/// blocks of at least `min_lines` lines that were removed in one place
+/// and added in another. maps every moved line of `old` to its position
+/// in `new` and every moved line of `new` back to its position in `old`.
+/// a `min_lines` of 0 finds no moves at all. matches are first moved
+/// next to their neighbours, so repeated lines like a closing brace
+/// don't tear a moved block apart.
+///
+pub(crate) fn moves(
+    old: &[String],
+    new: &[String],
+    matches: &mut Vec<Option<usize>>,
+    min_lines: usize,
+) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+    let mut moved_old = vec![None; old.len()];
+    let mut moved_new = vec![None; new.len()];
+    if min_lines == 0 {
+        return (moved_old, moved_new);
+    }
+
+    contiguous(new, matches);
+    let mut inverse = invert(matches, new.len());
+    contiguous(old, &mut inverse);
+    *matches = invert(&inverse, old.len());
+
+    let mut matched_new = vec![false; new.len()];
+    matches
+        .iter()
+        .flatten()
+        .for_each(|index| matched_new[*index] = true);
+    let mut removed: HashMap<&String, Vec<usize>> = HashMap::new();
+    (0..old.len())
+        .filter(|index| matches[*index].is_none())
+        .for_each(|index| removed.entry(&old[index]).or_default().push(index));
+
+    let mut j = 0;
+    while j < new.len() {
+        let candidates = match removed.get(&new[j]) {
+            Some(candidates) if !matched_new[j] => candidates,
+            _ => {
+                j += 1;
+                continue;
+            }
+        };
+        let block = candidates
+            .iter()
+            .filter(|i| moved_old[**i].is_none())
+            .take(MAX_CANDIDATES)
+            .map(|i| {
+                let length = (0..)
+                    .take_while(|offset| {
+                        let (x, y) = (i + offset, j + offset);
+                        x < old.len()
+                            && y < new.len()
+                            && matches[x].is_none()
+                            && moved_old[x].is_none()
+                            && !matched_new[y]
+                            && old[x] == new[y]
+                    })
+                    .count();
+                (*i, length)
+            })
+            .max_by_key(|(i, length)| (*length, Reverse(*i)));
+
+        match block {
+            Some((i, length)) if length >= min_lines => {
+                (0..length).for_each(|offset| {
+                    moved_old[i + offset] = Some(j + offset);
+                    moved_new[j + offset] = Some(i + offset);
+                });
+                j += length;
+            }
+            _ => j += 1,
+        }
+    }
+
+    (moved_old, moved_new)
+}
+
+///
+/// rematches a line to the equal line right after the partner of the
+/// line before it, where that one is free
+///
+fn contiguous(to: &[String], matches: &mut [Option<usize>]) {
+    (1..matches.len()).for_each(|index| {
+        if let (Some(previous), Some(partner)) = (matches[index - 1], matches[index]) {
+            if previous + 1 < partner && to[previous + 1] == to[partner] {
+                matches[index] = Some(previous + 1);
+            }
+        }
+    });
+}
+
+/// the matches seen from the other side
+fn invert(matches: &[Option<usize>], len: usize) -> Vec<Option<usize>> {
+    let mut inverse = vec![None; len];
+    matches.iter().enumerate().for_each(|(index, partner)| {
+        if let Some(partner) = partner {
+            inverse[*partner] = Some(index);
+        }
+    });
+
+    inverse
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::to_lines;
+    use crate::myers;
+
+    #[test]
+    fn only_blocks_long_enough_are_moves() {
+        let old = to_lines("fn a() {\nx\n}\nfn b() {\ny\n}\nz");
+        let new = to_lines("fn b() {\ny\n}\nz\nfn a() {\nx\n}\nw");
+        let mut matches = myers::matches(&old, &new);
+
+        let (moved_old, moved_new) = moves(&old, &new, &mut matches.clone(), 3);
+        let (_, too_short) = moves(&old, &new, &mut matches, 4);
+
+        assert_eq!(moved_old[..3], [Some(4), Some(5), Some(6)]);
+        assert_eq!(moved_new[4..], [Some(0), Some(1), Some(2), None]);
+        assert!(too_short.iter().all(Option::is_none));
+    }
+}
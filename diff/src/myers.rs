@@ -0,0 +1,216 @@
+use itertools::Itertools;
+use std::collections::HashSet;
+
+/// edits searched for before a part of both sides is split in the middle
+const COST_LIMIT: isize = 256;
+
+///
+/// longest common subsequence of both sides found with Myers'
+/// O((N+M)D) algorithm in linear space, mapped from every line of
+/// `old` to its partner in `new`. lines only one side has can't be
+/// common and are left out, so a rewritten file costs next to nothing.
+/// past `COST_LIMIT` edits the search gives up on the shortest script,
+/// which keeps large files with many differences near linear.
+///
+pub fn matches(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; old.len()];
+    let (prefix, suffix) = trim(old, new, 0, 0, &mut matches);
+    let (old_rest, new_rest) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let (in_old, in_new): (HashSet<&String>, HashSet<&String>) =
+        (old_rest.iter().collect(), new_rest.iter().collect());
+    let kept_old = (0..old_rest.len())
+        .filter(|index| in_new.contains(&old_rest[*index]))
+        .collect_vec();
+    let kept_new = (0..new_rest.len())
+        .filter(|index| in_old.contains(&new_rest[*index]))
+        .collect_vec();
+
+    let mut kept_matches = vec![None; kept_old.len()];
+    common(
+        &kept_old.iter().map(|index| &old_rest[*index]).collect_vec(),
+        &kept_new.iter().map(|index| &new_rest[*index]).collect_vec(),
+        0,
+        0,
+        &mut kept_matches,
+    );
+    kept_matches
+        .iter()
+        .enumerate()
+        .for_each(|(index, partner)| {
+            matches[prefix + kept_old[index]] = partner.map(|p| prefix + kept_new[p])
+        });
+
+    matches
+}
+
+/// records the common lines of `old` and `new`, which start at `x` and `y`
+fn common<T: PartialEq>(old: &[T], new: &[T], x: usize, y: usize, matches: &mut [Option<usize>]) {
+    let (prefix, suffix) = trim(old, new, x, y, matches);
+    let (old, new) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+
+    let (start_x, start_y, end_x, end_y) = middle_snake(old, new);
+    let (x, y) = (x + prefix, y + prefix);
+    common(&old[..start_x], &new[..start_y], x, y, matches);
+    (0..end_x - start_x).for_each(|index| matches[x + start_x + index] = Some(y + start_y + index));
+    common(&old[end_x..], &new[end_y..], x + end_x, y + end_y, matches);
+}
+
+///
+/// matches the common prefix and suffix of both sides, which start at
+/// `x` and `y`. returns their lengths.
+///
+pub(crate) fn trim<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    x: usize,
+    y: usize,
+    matches: &mut [Option<usize>],
+) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    (0..prefix).for_each(|index| matches[x + index] = Some(y + index));
+    (0..suffix).for_each(|index| {
+        matches[x + old.len() - suffix + index] = Some(y + new.len() - suffix + index)
+    });
+
+    (prefix, suffix)
+}
+
+/// runs Myers on a part of both sides that starts at `x` and `y`
+pub(crate) fn fallback(
+    old: &[String],
+    new: &[String],
+    x: usize,
+    y: usize,
+    matches: &mut [Option<usize>],
+) {
+    self::matches(old, new)
+        .iter()
+        .enumerate()
+        .for_each(|(index, partner)| matches[x + index] = partner.map(|p| y + p));
+}
+
+///
+/// the snake in the middle of a shortest edit script, searched from
+/// both ends at once. it splits the script into two halves of about
+/// the same number of edits. when both searches haven't met after
+/// `COST_LIMIT` edits, or the square root of the length if that's
+/// more, both sides are simply split in half.
+///
+fn middle_snake<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize, usize, usize) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // furthest x reached on every diagonal, forwards and from the end
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+    let limit = COST_LIMIT.max(((n + m) as f64).sqrt() as isize);
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            let c = delta - k;
+            if odd && -d < c && c < d && x + backward[at(c)] >= n {
+                return (start_x as usize, start_y as usize, x as usize, y as usize);
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (start_x, start_y) = (x, y);
+            while x < n && y < m && old[(n - 1 - x) as usize] == new[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            let c = delta - k;
+            if !odd && -d <= c && c <= d && x + forward[at(c)] >= n {
+                return (
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - start_x) as usize,
+                    (m - start_y) as usize,
+                );
+            }
+        }
+
+        if d >= limit {
+            let (x, y) = ((n / 2) as usize, (m / 2) as usize);
+            return (x, y, x, y);
+        }
+    }
+
+    unreachable!("both sides always meet")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::to_lines;
+
+    fn common_lines(old: &str, new: &str) -> Vec<String> {
+        let (old, new) = (to_lines(old), to_lines(new));
+        let matches = matches(&old, &new);
+        assert!(matches
+            .iter()
+            .flatten()
+            .zip(matches.iter().flatten().skip(1))
+            .all(|(a, b)| a < b));
+
+        matches
+            .iter()
+            .enumerate()
+            .filter_map(|(index, partner)| {
+                partner.map(|partner| {
+                    assert_eq!(old[index], new[partner]);
+                    old[index].clone()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_a_longest_common_subsequence() {
+        assert_eq!(
+            common_lines("a\nb\nc\na\nb\nb\na", "c\nb\na\nb\na\nc"),
+            to_lines("b\na\nb\na")
+        );
+        assert_eq!(common_lines("x\ny", "y\nx").len(), 1);
+        assert_eq!(common_lines("", "a"), Vec::<String>::new());
+        assert_eq!(common_lines("a\nb", "a\nb"), to_lines("a\nb"));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::myers;
+use std::collections::HashMap;
+
+///
+/// longest common subsequence found by patience diff: lines that occur
+/// exactly once on both sides anchor the result, the gaps between them
+/// are diffed again. keeps repeated lines like lone braces from pulling
+/// unrelated blocks together. gaps without unique lines fall back to Myers.
+///
+pub fn matches(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; old.len()];
+    common(old, new, 0, 0, &mut matches);
+
+    matches
+}
+
+fn common(old: &[String], new: &[String], x: usize, y: usize, matches: &mut [Option<usize>]) {
+    let (prefix, suffix) = myers::trim(old, new, x, y, matches);
+    let (old, new) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let (x, y) = (x + prefix, y + prefix);
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+
+    let anchors = anchors(old, new);
+    if anchors.is_empty() {
+        return myers::fallback(old, new, x, y, matches);
+    }
+    let (mut i, mut j) = (0, 0);
+    for (anchor_old, anchor_new) in anchors {
+        common(
+            &old[i..anchor_old],
+            &new[j..anchor_new],
+            x + i,
+            y + j,
+            matches,
+        );
+        matches[x + anchor_old] = Some(y + anchor_new);
+        i = anchor_old + 1;
+        j = anchor_new + 1;
+    }
+    common(&old[i..], &new[j..], x + i, y + j, matches);
+}
+
+///
+/// the longest increasing run of lines unique to both sides,
+/// as pairs of their old and new index
+///
+fn anchors(old: &[String], new: &[String]) -> Vec<(usize, usize)> {
+    // occurrences on the old and the new side, with the last index of each
+    let mut occurrences: HashMap<&String, (usize, usize, usize, usize)> = HashMap::new();
+    old.iter().enumerate().for_each(|(index, line)| {
+        let entry = occurrences.entry(line).or_insert((0, 0, 0, 0));
+        entry.0 += 1;
+        entry.2 = index;
+    });
+    new.iter().enumerate().for_each(|(index, line)| {
+        if let Some(entry) = occurrences.get_mut(line) {
+            entry.1 += 1;
+            entry.3 = index;
+        }
+    });
+    let mut unique = occurrences
+        .values()
+        .filter(|(in_old, in_new, _, _)| *in_old == 1 && *in_new == 1)
+        .map(|(_, _, index_old, index_new)| (*index_old, *index_new))
+        .collect::<Vec<_>>();
+    unique.sort_unstable();
+
+    // patience sorting, every pile remembers the top of the pile left of it
+    let mut piles: Vec<usize> = vec![];
+    let mut previous: Vec<Option<usize>> = vec![None; unique.len()];
+    for (index, (_, index_new)) in unique.iter().enumerate() {
+        let pile = piles.partition_point(|top| unique[*top].1 < *index_new);
+        if pile > 0 {
+            previous[index] = Some(piles[pile - 1]);
+        }
+        if pile == piles.len() {
+            piles.push(index);
+        } else {
+            piles[pile] = index;
+        }
+    }
+
+    let mut anchors = vec![];
+    let mut next = piles.last().copied();
+    while let Some(index) = next {
+        anchors.push(unique[index]);
+        next = previous[index];
+    }
+    anchors.reverse();
+
+    anchors
+}
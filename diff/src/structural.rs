@@ -0,0 +1,180 @@
+use crate::ChangeKind;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+///
+/// a value that changed at a key path like `server.port` or
+/// `servers[1].name`. a side is none where the key didn't exist.
+///
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct KeyChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl KeyChange {
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.old, &self.new) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            _ => ChangeKind::Modified,
+        }
+    }
+}
+
+impl fmt::Display for KeyChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let side = |value: &Option<String>| value.clone().unwrap_or_else(|| "< none >".to_string());
+        write!(f, "{}: {} → {}", self.key, side(&self.old), side(&self.new))
+    }
+}
+
+/// whether `path` is a json, toml or yaml file
+pub fn is_structured(path: &str) -> bool {
+    matches!(
+        Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str()),
+        Some("json") | Some("toml") | Some("yaml") | Some("yml")
+    )
+}
+
+///
+/// the key paths whose values differ between two states of a json,
+/// toml or yaml file, in the order of the new state with removed keys
+/// last. reordered keys and reflowed whitespace aren't changes. none if
+/// the file is none of these or a state doesn't parse.
+///
+pub fn structural(path: &str, old: &[String], new: &[String]) -> Option<Vec<KeyChange>> {
+    let (old, new) = (
+        leaves(&parse(path, &old.join("\n"))?),
+        leaves(&parse(path, &new.join("\n"))?),
+    );
+    let old_values: HashMap<&String, &String> =
+        old.iter().map(|(key, value)| (key, value)).collect();
+    let new_keys: HashSet<&String> = new.iter().map(|(key, _)| key).collect();
+
+    Some(
+        new.iter()
+            .filter(|(key, value)| old_values.get(key) != Some(&value))
+            .map(|(key, value)| KeyChange {
+                key: key.clone(),
+                old: old_values.get(key).map(|old| old.to_string()),
+                new: Some(value.clone()),
+            })
+            .chain(
+                old.iter()
+                    .filter(|(key, _)| !new_keys.contains(key))
+                    .map(|(key, value)| KeyChange {
+                        key: key.clone(),
+                        old: Some(value.clone()),
+                        new: None,
+                    }),
+            )
+            .collect(),
+    )
+}
+
+/// the tree of a file, an empty file has none
+fn parse(path: &str, content: &str) -> Option<Value> {
+    if content.trim().is_empty() {
+        return Some(Value::Null);
+    }
+    match Path::new(path).extension()?.to_str()? {
+        "json" => serde_json::from_str(content).ok(),
+        "toml" => Some(from_toml(toml::from_str(content).ok()?)),
+        "yaml" | "yml" => serde_yaml::from_str(content).ok(),
+        _ => None,
+    }
+}
+
+/// toml datetimes become strings instead of their private wrapper
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => Value::from(float),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, from_toml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// every scalar and empty container of a tree with its key path
+fn leaves(value: &Value) -> Vec<(String, String)> {
+    let mut leaves = vec![];
+    collect("", value, &mut leaves);
+
+    leaves
+}
+
+fn collect(key: &str, value: &Value, leaves: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => map.iter().for_each(|(name, value)| {
+            let path = if key.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", key, name)
+            };
+            collect(&path, value, leaves)
+        }),
+        Value::Array(items) if !items.is_empty() => items
+            .iter()
+            .enumerate()
+            .for_each(|(index, value)| collect(&format!("{}[{}]", key, index), value, leaves)),
+        Value::Null if key.is_empty() => (),
+        value => leaves.push((key.to_string(), value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::to_lines;
+
+    #[test]
+    fn keys_are_compared_not_lines() {
+        let toml_old = to_lines("[server]\nhost = \"a\"\nport = 80\n[log]\nlevel = 1");
+        let toml_new =
+            to_lines("[log]\nlevel = 1\n\n[server]\nport = 8080\nhosts = [\"a\", \"b\"]");
+        let json_old = to_lines("{\"a\": {\"b\": [1, 2]}, \"c\": true}");
+        let json_new = to_lines("{\n  \"c\": true,\n  \"a\": {\"b\": [1, 2]}\n}");
+
+        let changes = structural("config.toml", &toml_old, &toml_new).unwrap();
+
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| change.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "server.hosts[0]: < none > → \"a\"",
+                "server.hosts[1]: < none > → \"b\"",
+                "server.port: 80 → 8080",
+                "server.host: \"a\" → < none >",
+            ]
+        );
+        assert_eq!(changes[2].kind(), ChangeKind::Modified);
+        assert_eq!(structural("a.json", &json_old, &json_new), Some(vec![]));
+        assert_eq!(
+            structural("a.yaml", &[], &to_lines("a:\n  b: 1")),
+            Some(vec![KeyChange {
+                key: "a.b".to_string(),
+                old: None,
+                new: Some("1".to_string()),
+            }])
+        );
+        assert_eq!(structural("a.json", &[], &to_lines("{")), None);
+        assert!(!is_structured("main.rs"));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::layout::Layout;
+use crate::myers::matches;
+use itertools::Itertools;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// an edit together with the old and new line index it happens at
+type Op = (Edit, usize, usize);
+
+///
+/// formats the difference between two states of a file as a unified
+/// diff with `context` lines around every change, the way `diff -u`
+/// does. returns an empty string if both states are equal.
+///
+pub fn unified(
+    old_name: &str,
+    new_name: &str,
+    old: &[String],
+    new: &[String],
+    context: usize,
+) -> String {
+    unified_with_layout(
+        old_name,
+        new_name,
+        old,
+        Layout::default(),
+        new,
+        Layout::default(),
+        context,
+    )
+}
+
+///
+/// like `unified`, with the lines ending the way the layouts of both
+/// states say. a missing final newline is marked the way `diff -u` does.
+///
+pub fn unified_with_layout(
+    old_name: &str,
+    new_name: &str,
+    old: &[String],
+    old_layout: Layout,
+    new: &[String],
+    new_layout: Layout,
+    context: usize,
+) -> String {
+    // compared with their endings, so a changed ending changes the line
+    let old = terminated(old, old_layout);
+    let new = terminated(new, new_layout);
+    let ops = ops(&old, &new);
+    let changes = ops
+        .iter()
+        .positions(|(edit, _, _)| *edit != Edit::Keep)
+        .collect_vec();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for change in changes {
+        match hunks.last_mut() {
+            Some((_, end)) if change - *end <= 2 * context + 1 => *end = change,
+            _ => hunks.push((change, change)),
+        }
+    }
+
+    let mut patch = format!("--- {}\n+++ {}\n", old_name, new_name);
+    hunks.into_iter().for_each(|(first, last)| {
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(ops.len());
+        patch.push_str(&hunk(&ops[start..end], &old, &new));
+    });
+
+    patch
+}
+
+/// the lines with their endings, the last one without if the file has none
+fn terminated(lines: &[String], layout: Layout) -> Vec<String> {
+    let newline = if layout.crlf { "\r\n" } else { "\n" };
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            if index + 1 == lines.len() && !layout.final_newline {
+                line.clone()
+            } else {
+                format!("{}{}", line, newline)
+            }
+        })
+        .collect_vec()
+}
+
+fn ops(old: &[String], new: &[String]) -> Vec<Op> {
+    let matches = matches(old, new);
+    let mut ops = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && matches[i] == Some(j) {
+            ops.push((Edit::Keep, i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && matches[i].is_none() {
+            ops.push((Edit::Remove, i, j));
+            i += 1;
+        } else {
+            ops.push((Edit::Add, i, j));
+            j += 1;
+        }
+    }
+
+    ops
+}
+
+fn hunk(ops: &[Op], old: &[String], new: &[String]) -> String {
+    let (_, old_start, new_start) = ops[0];
+    let old_count = ops.iter().filter(|(edit, _, _)| *edit != Edit::Add).count();
+    let new_count = ops
+        .iter()
+        .filter(|(edit, _, _)| *edit != Edit::Remove)
+        .count();
+    // an empty range names the line before it
+    let position = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+
+    let mut hunk = format!(
+        "@@ -{},{} +{},{} @@\n",
+        position(old_start, old_count),
+        old_count,
+        position(new_start, new_count),
+        new_count
+    );
+    ops.iter().for_each(|(edit, i, j)| {
+        let (prefix, line) = match edit {
+            Edit::Keep => (' ', &old[*i]),
+            Edit::Remove => ('-', &old[*i]),
+            Edit::Add => ('+', &new[*j]),
+        };
+        hunk.push(prefix);
+        hunk.push_str(line);
+        if !line.ends_with('\n') {
+            hunk.push_str("\n\\ No newline at end of file\n");
+        }
+    });
+
+    hunk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::to_lines;
+
+    #[test]
+    fn changes_are_surrounded_by_context() {
+        let old = to_lines("1\n2\n3\n4\n5\n6\n7\n8\n9\n10");
+        let new = to_lines("1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11");
+
+        assert_eq!(
+            unified("./a.txt", "./a.txt", &old, &new, 2),
+            "--- ./a.txt\n+++ ./a.txt\n\
+             @@ -1,5 +1,5 @@\n 1\n 2\n-3\n+three\n 4\n 5\n\
+             @@ -9,2 +9,3 @@\n 9\n 10\n+11\n"
+        );
+    }
+
+    #[test]
+    fn created_and_equal_files() {
+        let new = to_lines("a\nb");
+
+        assert_eq!(
+            unified("/dev/null", "./a.txt", &[], &new, 3),
+            "--- /dev/null\n+++ ./a.txt\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+        assert_eq!(unified("./a.txt", "./a.txt", &new, &new, 3), "");
+    }
+
+    #[test]
+    fn line_endings_follow_the_layouts() {
+        let (old, old_layout) = Layout::split("a\r\nb\r\n");
+        let (new, new_layout) = Layout::split("a\r\nc");
+        let (lf, lf_layout) = Layout::split("a\nb\n");
+
+        assert_eq!(
+            unified_with_layout("./a.txt", "./a.txt", &old, old_layout, &new, new_layout, 3),
+            "--- ./a.txt\n+++ ./a.txt\n@@ -1,2 +1,2 @@\n a\r\n-b\r\n+c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            unified_with_layout("./a.txt", "./a.txt", &new, new_layout, &old, old_layout, 3),
+            "--- ./a.txt\n+++ ./a.txt\n@@ -1,2 +1,2 @@\n a\r\n-c\n\\ No newline at end of file\n+b\r\n"
+        );
+        assert_eq!(
+            unified_with_layout("./a.txt", "./a.txt", &old, old_layout, &lf, lf_layout, 3),
+            "--- ./a.txt\n+++ ./a.txt\n@@ -1,2 +1,2 @@\n-a\r\n-b\r\n+a\n+b\n"
+        );
+    }
+}
@@ -10,6 +10,7 @@ pub mod event_handle {
     use store::store::FileVersions;
     use store::store::Store;
     use store::store::TimeFrame;
+    use store::store::{BlameLine, Hit, SearchQuery};
 
     pub struct EventHandle {
         store: Arc<Mutex<Store>>,
@@ -20,9 +21,35 @@ pub mod event_handle {
         pub file_versions_to_ui: Sender<Vec<Option<FileVersions>>>,
         pub on_undo: Receiver<(String, usize)>,
         pub on_redo: Receiver<(String, usize)>,
+        pub on_jump: Receiver<(String, i64)>,
+        pub on_stash: Receiver<(Option<String>, i64, i64)>,
+        pub on_note: Receiver<(String, i64, String)>,
+        pub on_search: Receiver<SearchQuery>,
+        pub hits_to_ui: Sender<Vec<Hit>>,
+        pub on_blame: Receiver<String>,
+        pub blame_to_ui: Sender<(String, Vec<BlameLine>)>,
+        pub on_preview_restore: Receiver<(String, Vec<LineDifference>)>,
+        /// the restored lines, or why the restore was refused
+        pub preview_to_ui: Sender<(String, Result<Vec<String>, String>)>,
+        pub on_restore_changes: Receiver<(String, Vec<LineDifference>)>,
+        pub on_diff_between: Receiver<(String, i64, Option<i64>)>,
+        /// the differences, or why the snapshots couldn't be compared
+        pub diff_to_ui: Sender<(String, Result<Vec<LineDifference>, String>)>,
+        pub error_to_ui: Sender<String>,
+        /// outcomes of requests worth telling, which aren't errors
+        pub status_to_ui: Sender<String>,
         pub on_time_frame_change: Receiver<TimeFrame>,
     }
 
+    fn transmit_error(communication: &EventHandleCommunication, err: Box<dyn std::error::Error>) {
+        communication
+            .error_to_ui
+            .send(err.to_string())
+            .unwrap_or_else(|err| {
+                eprintln!("Could not transmit error to TUI {:?}", err);
+            });
+    }
+
     fn transmit_file_versions(event_handle: &EventHandle) {
         let view = event_handle.store.lock().unwrap().view().unwrap();
 
@@ -66,7 +93,11 @@ pub mod event_handle {
             let store = self.store.clone();
             thread::spawn(move || loop {
                 let (path, count) = communication.on_undo.recv().unwrap();
-                store.lock().unwrap().undo_by(path, count).unwrap();
+                store
+                    .lock()
+                    .unwrap()
+                    .undo_by(path, count)
+                    .unwrap_or_else(|err| transmit_error(&communication, err));
                 transmit_file_versions(&EventHandle {
                     communication: communication.clone(),
                     store: store.clone(),
@@ -79,7 +110,110 @@ pub mod event_handle {
             let store = self.store.clone();
             thread::spawn(move || loop {
                 let (path, count) = communication.on_redo.recv().unwrap();
-                store.lock().unwrap().redo_by(path, count).unwrap();
+                store
+                    .lock()
+                    .unwrap()
+                    .redo_by(path, count)
+                    .unwrap_or_else(|err| transmit_error(&communication, err));
+                transmit_file_versions(&EventHandle {
+                    communication: communication.clone(),
+                    store: store.clone(),
+                });
+            });
+        }
+
+        pub fn on_jump(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let (path, version) = communication.on_jump.recv().unwrap();
+                store
+                    .lock()
+                    .unwrap()
+                    .restore_version(path, version)
+                    .unwrap_or_else(|err| transmit_error(&communication, err));
+                transmit_file_versions(&EventHandle {
+                    communication: communication.clone(),
+                    store: store.clone(),
+                });
+            });
+        }
+
+        pub fn on_note(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let (path, version, text) = communication.on_note.recv().unwrap();
+                store
+                    .lock()
+                    .unwrap()
+                    .set_note(&path, version, &text)
+                    .unwrap_or_else(|err| transmit_error(&communication, err));
+                transmit_file_versions(&EventHandle {
+                    communication: communication.clone(),
+                    store: store.clone(),
+                });
+            });
+        }
+
+        pub fn on_search(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let query = communication.on_search.recv().unwrap();
+                let result = store.lock().unwrap().search(&query);
+                match result {
+                    Ok(hits) => communication.hits_to_ui.send(hits).unwrap_or_else(|err| {
+                        eprintln!("Could not transmit search hits to TUI {:?}", err);
+                    }),
+                    Err(err) => transmit_error(&communication, err),
+                }
+            });
+        }
+
+        pub fn on_blame(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let path = communication.on_blame.recv().unwrap();
+                let result = store.lock().unwrap().blame(&path);
+                match result {
+                    Ok(blame) => communication
+                        .blame_to_ui
+                        .send((path, blame))
+                        .unwrap_or_else(|err| {
+                            eprintln!("Could not transmit blame to TUI {:?}", err);
+                        }),
+                    Err(err) => transmit_error(&communication, err),
+                }
+            });
+        }
+
+        pub fn on_preview_restore(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let (path, changes) = communication.on_preview_restore.recv().unwrap();
+                let result = store.lock().unwrap().preview_restore(&path, &changes);
+                communication
+                    .preview_to_ui
+                    .send((path, result.map_err(|err| err.to_string())))
+                    .unwrap_or_else(|err| {
+                        eprintln!("Could not transmit preview to TUI {:?}", err);
+                    });
+            });
+        }
+
+        pub fn on_restore_changes(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let (path, changes) = communication.on_restore_changes.recv().unwrap();
+                store
+                    .lock()
+                    .unwrap()
+                    .restore_changes(&path, &changes)
+                    .unwrap_or_else(|err| transmit_error(&communication, err));
                 transmit_file_versions(&EventHandle {
                     communication: communication.clone(),
                     store: store.clone(),
@@ -87,6 +221,48 @@ pub mod event_handle {
             });
         }
 
+        pub fn on_diff_between(&mut self) {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let (path, from, to) = communication.on_diff_between.recv().unwrap();
+                let result = store.lock().unwrap().diff_between(&path, from, to);
+                communication
+                    .diff_to_ui
+                    .send((path, result.map_err(|err| err.to_string())))
+                    .unwrap_or_else(|err| {
+                        eprintln!("Could not transmit differences to TUI {:?}", err);
+                    });
+            });
+        }
+
+        ///
+        /// hands stash requests (path, from, to) to `stash`, which lives
+        /// outside of this crate. its outcome is shown in the TUI.
+        ///
+        pub fn on_stash<F>(&mut self, stash: F)
+        where
+            F: Fn(&Store, Option<String>, i64, i64) -> Result<String, Box<dyn std::error::Error>>
+                + Send
+                + 'static,
+        {
+            let communication = self.communication.clone();
+            let store = self.store.clone();
+            thread::spawn(move || loop {
+                let (path, from, to) = communication.on_stash.recv().unwrap();
+                let result = stash(&store.lock().unwrap(), path, from, to);
+                match result {
+                    Ok(entry) => communication
+                        .status_to_ui
+                        .send(entry)
+                        .unwrap_or_else(|err| {
+                            eprintln!("Could not transmit stash entry to TUI {:?}", err);
+                        }),
+                    Err(err) => transmit_error(&communication, err),
+                }
+            });
+        }
+
         pub fn handle(&mut self, event: DebouncedEvent) -> Result<(), Box<dyn std::error::Error>> {
             let path = self.to_path(&event)?;
             if path.is_none() {
@@ -137,8 +313,7 @@ pub mod event_handle {
 
             let mut store = self.store.lock().unwrap();
             store.create_new_file_entry(path)?;
-            let changes = store.get_file_changes::<LineDifference>(path);
-            let changes = diff::find(path, &changes)?;
+            let changes = store.find_changes(path)?;
             let stored = store.store_changes(path, &changes);
             let _view = store.view()?;
             self.communication.file_versions_to_ui.send(_view)?;
@@ -152,15 +327,9 @@ pub mod event_handle {
 
             let mut store = self.store.lock().unwrap();
 
-            let changes = store.get_file_changes::<LineDifference>(path);
-            let changes: Vec<LineDifference> = changes.iter().map(|change| {
-                LineDifference::new(
-                    path.to_string(),
-                    change.line_number,
-                    change.changed_line.to_string(),
-                    "".to_string(),
-                )
-            }).collect();
+            let (algorithm, ignore) = (store.diff_algorithm(path), store.ignore(path));
+            let changes =
+                diff::find_between(path, store.current_lines(path), &[], algorithm, ignore, 0);
             let stored = store.store_changes(path, &changes);
             let _view = store.view()?;
             self.communication.file_versions_to_ui.send(_view)?;
@@ -49,6 +49,16 @@ impl FileWatch {
         Ok(())
     }
 
+    ///
+    /// keeps the event handle serving the ui without watching anything,
+    /// refreshing it periodically until quit is requested
+    ///
+    pub fn idle(&mut self, refresh_rate: Duration) {
+        while let Err(flume::RecvTimeoutError::Timeout) = self.on_quit.recv_timeout(refresh_rate) {
+            self.event_handle.init_file_versions();
+        }
+    }
+
     fn watch(&mut self, dir: &str) -> Result<(), notify::Error> {
         self.watch_dog.watch(dir, RecursiveMode::Recursive)
     }
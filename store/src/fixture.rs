@@ -0,0 +1,42 @@
+use crate::store::Store;
+use std::fs;
+
+///
+/// a watched directory `name` and its store `name.db` for tests. both
+/// are removed when the fixture is dropped, even after a failed assert.
+/// declare it before the store, so the store is dropped and dumped first.
+///
+pub struct Fixture {
+    pub dir: String,
+    pub store_path: String,
+}
+
+impl Fixture {
+    pub fn new(name: &str) -> Fixture {
+        let fixture = Fixture {
+            dir: name.to_string(),
+            store_path: format!("{}.db", name),
+        };
+        // leftovers of a run that was killed
+        fixture.remove();
+        fs::create_dir_all(&fixture.dir).unwrap();
+
+        fixture
+    }
+
+    /// a new store watching the directory
+    pub fn store(&self) -> Store {
+        Store::new(&self.store_path, &self.dir, vec![], vec![]).unwrap()
+    }
+
+    fn remove(&self) {
+        let _ = fs::remove_dir_all(&self.dir);
+        let _ = fs::remove_file(&self.store_path);
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
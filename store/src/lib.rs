@@ -1,31 +1,213 @@
 extern crate simple_error;
 
+#[cfg(test)]
+mod fixture;
+
 pub mod store {
+    mod blame;
+    mod fsck;
+    mod notes;
+    mod search;
+    mod states;
+    mod tags;
+    mod version_tree;
+
+    pub use self::blame::BlameLine;
+    pub use self::fsck::{Issue, Problem};
+    pub use self::notes::Note;
+    use self::search::SearchIndex;
+    pub use self::search::{Hit, Occurrence, SearchQuery};
+    use self::states::States;
+    pub use self::states::DEFAULT_CACHE_LIMIT;
+    pub use self::tags::Tag;
+    pub use self::version_tree::{VersionNode, VersionTree};
     use chrono::NaiveDate;
     use chrono::NaiveDateTime;
-    use chrono::Utc;
-    use diff::LineDifference;
+    use chrono::{DateTime, Utc};
+    use diff::{Algorithm, ChangeKind, Ignore, KeyChange, Layout, LineDifference};
     use itertools::Itertools;
     use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
     use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
     use std::error;
+    use std::fmt;
+    use std::fs;
     use std::fs::File;
-    use std::io::Write;
-    use std::io::{self, BufRead};
+    use std::io;
+    use std::io::{BufRead, BufReader, Write};
+    use std::path::Path;
     use walkdir::{DirEntry, WalkDir};
 
     static FILE_VERSION_STACK: &str = "FILE_VERSION_STACK";
     static FILE_VERSION_MARKER: &str = "FILE_VERSION_MARKER";
+    static FILE_VERSION_TREE: &str = "FILE_VERSION_TREE";
+    static VERSION_TAGS: &str = "VERSION_TAGS";
+    static VERSION_NOTES: &str = "VERSION_NOTES";
+    // the search index used to be saved, only read to drop it
+    static SEARCH_INDEX: &str = "SEARCH_INDEX";
+    static LAST_VERSION_ID: &str = "LAST_VERSION_ID";
+    static CHANGE_FORMAT: &str = "CHANGE_FORMAT";
+
+    /// changes carry a kind, optional lines and a typed timestamp
+    const TYPED_CHANGES: u32 = 2;
+    /// line numbers are positions while applying the changes in order
+    const POSITIONAL_CHANGES: u32 = 3;
+
+    /// lists holding bookkeeping instead of the changes of a file
+    fn is_meta_list(name: &str) -> bool {
+        [
+            FILE_VERSION_STACK,
+            FILE_VERSION_MARKER,
+            FILE_VERSION_TREE,
+            VERSION_TAGS,
+            VERSION_NOTES,
+        ]
+        .contains(&name)
+    }
     pub struct Store {
         db: PickleDb,
+        store_path: String,
+        read_only: bool,
         pub time_frame: TimeFrame,
+        pub conflict_strategy: ConflictStrategy,
+        pub diff_algorithms: DiffAlgorithms,
+        pub ignore_rules: IgnoreRules,
+        pub blob_limit: u64,
+        pub min_moved_lines: usize,
+        states: States,
+        search_index: SearchIndex,
+    }
+
+    /// size in bytes above which a file is stored whole instead of diffed
+    pub const DEFAULT_BLOB_LIMIT: u64 = 4 * 1024 * 1024;
+
+    ///
+    /// decides what happens when a file changed on disk since the
+    /// version an undo or redo starts from
+    ///
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ConflictStrategy {
+        /// refuse to touch the file if the merge has conflicts
+        #[default]
+        Refuse,
+        /// write the merge with conflict markers around every conflict
+        Markers,
+    }
+
+    ///
+    /// the diff algorithm changes are found with, chosen by the
+    /// extension of a file
+    ///
+    #[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+    pub struct DiffAlgorithms {
+        #[serde(default)]
+        pub default: Algorithm,
+        /// keyed by extension without the dot, e.g. "rs"
+        #[serde(default)]
+        pub extensions: HashMap<String, Algorithm>,
+        /// json, toml and yaml files are also compared by their keys
+        #[serde(default)]
+        pub structural: bool,
+    }
+
+    impl DiffAlgorithms {
+        pub fn of(&self, path: &str) -> Algorithm {
+            Path::new(path)
+                .extension()
+                .and_then(|extension| self.extensions.get(extension.to_str()?))
+                .copied()
+                .unwrap_or(self.default)
+        }
+    }
+
+    ///
+    /// what to ignore when diffing, for every file unless a path pattern
+    /// matches. the last matching pattern replaces the global settings.
+    ///
+    #[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+    pub struct IgnoreRules {
+        #[serde(flatten)]
+        pub ignore: Ignore,
+        /// hide versions whose changes are all ignored, they are still
+        /// recorded so undo knows what is on disk
+        #[serde(default)]
+        pub skip_versions: bool,
+        #[serde(default)]
+        pub paths: Vec<PathIgnore>,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Deserialize)]
+    pub struct PathIgnore {
+        /// a glob, relative patterns match any suffix of a path
+        pub pattern: String,
+        #[serde(flatten)]
+        pub ignore: Ignore,
+    }
+
+    impl IgnoreRules {
+        pub fn of(&self, path: &str) -> Ignore {
+            self.paths
+                .iter()
+                .rev()
+                .find(|rule| {
+                    [rule.pattern.clone(), format!("**/{}", rule.pattern)]
+                        .iter()
+                        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+                        .any(|pattern| pattern.matches(path))
+                })
+                .map_or(self.ignore, |rule| rule.ignore)
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct ConflictError {
+        pub path: String,
+        pub conflicts: usize,
+    }
+
+    impl fmt::Display for ConflictError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "{} changed on disk, restoring it would cause {} conflict(s)",
+                self.path, self.conflicts
+            )
+        }
     }
 
+    impl error::Error for ConflictError {}
+
     #[derive(Clone, Debug)]
     pub struct Version {
+        pub id: i64,
         pub datetime: NaiveDateTime,
         pub changes: Vec<LineDifference>,
+        pub note: Option<String>,
+        /// the changed keys, if the file is compared by its structure
+        pub structure: Option<Vec<KeyChange>>,
+    }
+
+    ///
+    /// content of a file right after one of its versions was stored
+    ///
+    #[derive(Clone, Debug)]
+    pub struct Snapshot {
+        pub path: String,
+        pub id: i64,
+        pub datetime: NaiveDateTime,
+        pub lines: Vec<String>,
+        pub layout: Layout,
+        /// whether the version removed the file rather than emptying it
+        pub removed: bool,
+    }
+
+    impl Snapshot {
+        /// the content of the file byte for byte, none if it was removed
+        pub fn content(&self) -> Option<String> {
+            (!self.removed).then(|| self.layout.join(&self.lines))
+        }
     }
 
     #[derive(Clone, Debug)]
@@ -33,18 +215,65 @@ pub mod store {
         pub path: String,
         pub versions: Vec<Version>,
         pub hits_of_codes: Vec<HitsOfCode>,
+        pub version_tree: Option<VersionTree>,
+        /// tags pointing at versions of this file
+        pub tags: Vec<(i64, Tag)>,
     }
 
     #[derive(Serialize, Deserialize, Clone)]
     struct VersionStack {
         path: String,
+        #[serde(default)]
+        versions: Vec<StoredVersion>,
+        // versions used to be keyed by the second they were stored in,
+        // only read to migrate old stores
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
         timestamps: Vec<i64>,
     }
 
+    impl VersionStack {
+        /// ids of the stored versions, oldest first
+        fn ids(&self) -> Vec<i64> {
+            self.versions.iter().map(|version| version.id).collect_vec()
+        }
+
+        /// ids of the versions that aren't hidden, the ones of the tree
+        fn shown_ids(&self) -> Vec<i64> {
+            self.versions
+                .iter()
+                .filter(|version| !version.hidden)
+                .map(|version| version.id)
+                .collect_vec()
+        }
+    }
+
+    ///
+    /// a version of a file: its id, unique and increasing in the order
+    /// versions are stored, the time it was stored at and the layout of
+    /// its lines, so it is restored byte for byte. a removed file is told
+    /// apart from an empty one. a hidden version only holds ignored changes,
+    /// it is kept to follow the disk but not shown nor part of the tree.
+    ///
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    struct StoredVersion {
+        id: i64,
+        datetime: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Layout::is_default")]
+        layout: Layout,
+        #[serde(default, skip_serializing_if = "is_false")]
+        removed: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        hidden: bool,
+    }
+
+    fn is_false(value: &bool) -> bool {
+        !value
+    }
+
+    // superseded by the version tree, only read to migrate old stores
     #[derive(Serialize, Deserialize)]
     struct VersionMarker {
         path: String,
-        // Test
         timestamp_marker: usize,
     }
 
@@ -54,6 +283,7 @@ pub mod store {
         HOUR,
         DAY,
         WEEK,
+        ALL,
     }
 
     #[derive(Clone, Debug)]
@@ -69,6 +299,7 @@ pub mod store {
                 Self::HOUR => 60 * Self::MINUTE.value(),
                 Self::DAY => 24 * Self::HOUR.value(),
                 Self::WEEK => 7 * Self::DAY.value(),
+                Self::ALL => i64::MAX,
             }
         }
     }
@@ -92,16 +323,555 @@ pub mod store {
     }
 
     fn create_stack(db: &mut PickleDb) -> Result<(), pickledb::error::Error> {
+        db.set(CHANGE_FORMAT, &POSITIONAL_CHANGES)?;
+        db.lcreate(FILE_VERSION_STACK)?;
+        db.lcreate(FILE_VERSION_TREE).map(|_| ())
+    }
+
+    // the shape of a change before it had a kind: empty strings stood
+    // for missing lines and the time was an RFC3339 string
+    #[derive(Serialize, Deserialize)]
+    struct LegacyLineDifference {
+        path: String,
+        line_number: usize,
+        line: String,
+        changed_line: String,
+        date_time: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<i64>,
+    }
+
+    impl LegacyLineDifference {
+        /// none if the time can't be parsed, fsck deals with those
+        fn migrate(&self) -> Option<LineDifference> {
+            let date_time = DateTime::parse_from_rfc3339(&self.date_time).ok()?;
+            let non_empty = |line: &str| Some(line.to_string()).filter(|line| !line.is_empty());
+            let line = non_empty(&self.line);
+            let changed_line = match &line {
+                // a removal only ever had an old line
+                Some(_) => non_empty(&self.changed_line),
+                None => Some(self.changed_line.clone()),
+            };
+
+            Some(LineDifference {
+                date_time: date_time.with_timezone(&Utc),
+                version: self.version,
+                ..LineDifference::new(self.path.clone(), self.line_number, line, changed_line)
+            })
+        }
+    }
+
+    ///
+    /// stores written before changes had a kind are converted in place.
+    /// entries that can't be converted are kept as they are.
+    ///
+    fn migrate_line_differences(db: &mut PickleDb) -> Result<(), Box<dyn error::Error>> {
+        if db.get::<u32>(CHANGE_FORMAT).unwrap_or(0) >= TYPED_CHANGES {
+            return Ok(());
+        }
+
+        let change_lists = db
+            .get_all()
+            .into_iter()
+            .filter(|name| !is_meta_list(name) && db.lexists(name))
+            .collect_vec();
+        for name in change_lists {
+            let changes = db
+                .liter(&name)
+                .filter_map(|change| change.get_item::<LegacyLineDifference>())
+                .collect_vec();
+            db.lrem_list(&name)?;
+            db.lcreate(&name)?;
+            changes.iter().for_each(|change| {
+                match change.migrate() {
+                    Some(migrated) => db.ladd(&name, &migrated),
+                    None => db.ladd(&name, change),
+                };
+            });
+        }
+        db.set(CHANGE_FORMAT, &TYPED_CHANGES)?;
+
+        Ok(())
+    }
+
+    ///
+    /// stores written before the version tree existed only know a linear
+    /// marker per file. their stacks are turned into linear trees whose
+    /// current node is the one the marker pointed at.
+    ///
+    fn migrate_version_markers(db: &mut PickleDb) -> Result<(), Box<dyn error::Error>> {
+        if db.lexists(FILE_VERSION_TREE) {
+            return Ok(());
+        }
+        db.lcreate(FILE_VERSION_TREE)?;
+
+        let version_stacks: Vec<VersionStack> = db
+            .liter(FILE_VERSION_STACK)
+            .map(|version_stack| version_stack.get_item().unwrap())
+            .collect_vec();
+        let version_markers: Vec<VersionMarker> = if db.lexists(FILE_VERSION_MARKER) {
+            db.liter(FILE_VERSION_MARKER)
+                .map(|version_marker| version_marker.get_item().unwrap())
+                .collect_vec()
+        } else {
+            vec![]
+        };
+
+        version_stacks.into_iter().for_each(|version_stack| {
+            let mut version_tree =
+                VersionTree::linear(version_stack.path.clone(), &version_stack.timestamps);
+            let marker = version_markers
+                .iter()
+                .find(|version_marker| version_marker.path.eq(&version_stack.path));
+            if let Some(marker) = marker {
+                let timestamps = version_stack.timestamps.iter().sorted().collect_vec();
+                let position = marker.timestamp_marker.clamp(1, timestamps.len().max(1)) - 1;
+                if let Some(timestamp) = timestamps.get(position) {
+                    version_tree.current = **timestamp;
+                }
+            }
+            db.ladd(FILE_VERSION_TREE, &version_tree);
+        });
+
+        if db.lexists(FILE_VERSION_MARKER) {
+            db.rem(FILE_VERSION_MARKER)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// changes used to overwrite the line with their number. the content
+    /// after every version is rebuilt that way and recorded again as a
+    /// positional diff of the content before it.
+    ///
+    fn migrate_positional_changes(db: &mut PickleDb) -> Result<(), Box<dyn error::Error>> {
+        if db.get::<u32>(CHANGE_FORMAT).unwrap_or(0) >= POSITIONAL_CHANGES {
+            return Ok(());
+        }
+
+        let change_lists = db
+            .get_all()
+            .into_iter()
+            .filter(|name| !is_meta_list(name) && db.lexists(name))
+            .collect_vec();
+        for name in change_lists {
+            let (changes, unreadable): (Vec<_>, Vec<_>) = db
+                .liter(&name)
+                .map(|item| {
+                    (
+                        item.get_item::<LineDifference>(),
+                        item.get_item::<LegacyLineDifference>(),
+                    )
+                })
+                .partition(|(change, _)| change.is_some());
+            let changes = changes
+                .into_iter()
+                .filter_map(|(change, _)| change)
+                .sorted_by_key(|change| change.date_time)
+                .collect_vec();
+
+            let mut lines: BTreeMap<usize, String> = BTreeMap::new();
+            let mut migrated = vec![];
+            for (_, version) in &changes.iter().group_by(|change| change.version) {
+                let version = version.collect_vec();
+                let before = overwritten_lines(&lines);
+                version
+                    .iter()
+                    .for_each(|change| match &change.changed_line {
+                        Some(changed_line) => {
+                            lines.insert(change.line_number, changed_line.clone());
+                        }
+                        None => {
+                            lines.remove(&change.line_number);
+                        }
+                    });
+                let after = overwritten_lines(&lines);
+                migrated.extend(
+                    diff::find_between(
+                        &name,
+                        &before,
+                        &after,
+                        Algorithm::default(),
+                        Ignore::default(),
+                        0,
+                    )
+                    .into_iter()
+                    .map(|change| LineDifference {
+                        date_time: version[0].date_time,
+                        version: version[0].version,
+                        ..change
+                    }),
+                );
+            }
+
+            db.lrem_list(&name)?;
+            db.lcreate(&name)?;
+            db.lextend(&name, &migrated);
+            unreadable
+                .iter()
+                .filter_map(|(_, legacy)| legacy.as_ref())
+                .for_each(|legacy| {
+                    db.ladd(&name, legacy);
+                });
+        }
+        db.set(CHANGE_FORMAT, &POSITIONAL_CHANGES)?;
+
+        Ok(())
+    }
+
+    fn overwritten_lines(lines: &BTreeMap<usize, String>) -> Vec<String> {
+        match lines.keys().max() {
+            Some(last) => (0..=*last)
+                .map(|index| lines.get(&index).cloned().unwrap_or_default())
+                .collect_vec(),
+            None => vec![],
+        }
+    }
+
+    ///
+    /// stores written before versions had ids keyed them by the second
+    /// they were stored in. those seconds become the ids, so trees, tags
+    /// and notes keep pointing at the same versions, and every change is
+    /// linked to the version whose time window it fell into.
+    ///
+    fn migrate_version_ids(db: &mut PickleDb) -> Result<(), Box<dyn error::Error>> {
+        let version_stacks: Vec<VersionStack> = db
+            .liter(FILE_VERSION_STACK)
+            .filter_map(|version_stack| version_stack.get_item())
+            .collect_vec();
+        if !version_stacks
+            .iter()
+            .any(|version_stack| version_stack.versions.is_empty())
+        {
+            return Ok(());
+        }
+
+        let mut last_id = db.get::<i64>(LAST_VERSION_ID).unwrap_or(0);
+        let mut migrated = vec![];
+        for mut version_stack in version_stacks {
+            if version_stack.versions.is_empty() {
+                let timestamps = version_stack
+                    .timestamps
+                    .iter()
+                    .copied()
+                    .sorted()
+                    .collect_vec();
+                let changes = db
+                    .liter(&version_stack.path)
+                    .filter_map(|change| change.get_item::<LineDifference>())
+                    .map(|change| LineDifference {
+                        version: change
+                            .version
+                            .or_else(|| legacy_version_of(&timestamps, &change)),
+                        ..change
+                    })
+                    .collect_vec();
+                if db.lexists(&version_stack.path) {
+                    db.lrem_list(&version_stack.path)?;
+                    db.lcreate(&version_stack.path)?;
+                    db.lextend(&version_stack.path, &changes);
+                }
+
+                version_stack.versions = timestamps
+                    .iter()
+                    .map(|timestamp| StoredVersion {
+                        id: *timestamp,
+                        datetime: DateTime::<Utc>::from_utc(
+                            NaiveDateTime::from_timestamp(*timestamp, 0),
+                            Utc,
+                        ),
+                        layout: Layout::default(),
+                        removed: false,
+                        hidden: false,
+                    })
+                    .collect_vec();
+                version_stack.timestamps = vec![];
+                last_id = last_id.max(timestamps.last().copied().unwrap_or(0));
+            }
+            migrated.push(version_stack);
+        }
+        // old entries don't serialize the way they were read,
+        // so the list is rewritten instead of updated in place
+        db.lrem_list(FILE_VERSION_STACK)?;
         db.lcreate(FILE_VERSION_STACK)?;
-        db.lcreate(FILE_VERSION_MARKER).map(|_| ())
+        db.lextend(FILE_VERSION_STACK, &migrated);
+        db.set(LAST_VERSION_ID, &last_id)?;
+
+        // tags and notes are rewritten in their new shape, a file tag
+        // points at the version its old timestamp keyed
+        if db.lexists(VERSION_TAGS) {
+            let tags = db
+                .liter(VERSION_TAGS)
+                .filter_map(|tag| tag.get_item::<Tag>())
+                .map(|tag| Tag {
+                    version: tag.version.or(tag.path.as_ref().map(|_| tag.timestamp)),
+                    ..tag
+                })
+                .collect_vec();
+            db.lrem_list(VERSION_TAGS)?;
+            db.lcreate(VERSION_TAGS)?;
+            db.lextend(VERSION_TAGS, &tags);
+        }
+        if db.lexists(VERSION_NOTES) {
+            let notes = db
+                .liter(VERSION_NOTES)
+                .filter_map(|note| note.get_item::<Note>())
+                .collect_vec();
+            db.lrem_list(VERSION_NOTES)?;
+            db.lcreate(VERSION_NOTES)?;
+            db.lextend(VERSION_NOTES, &notes);
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// the version an unlinked change belonged to in stores keyed by
+    /// seconds: the first one stored at or after it. `timestamps` has
+    /// to be sorted.
+    ///
+    fn legacy_version_of(timestamps: &[i64], change: &LineDifference) -> Option<i64> {
+        let timestamp = change.date_time.timestamp();
+        let position = timestamps.partition_point(|version| *version < timestamp);
+
+        timestamps.get(position).or(timestamps.last()).copied()
+    }
+
+    /// hands out the id of the next version
+    fn next_version_id(db: &mut PickleDb) -> Result<i64, Box<dyn error::Error>> {
+        let id = db.get::<i64>(LAST_VERSION_ID).unwrap_or(0) + 1;
+        db.set(LAST_VERSION_ID, &id)?;
+
+        Ok(id)
+    }
+
+    /// links changes to the version they are stored with
+    fn versioned(changes: &[LineDifference], id: i64) -> Vec<LineDifference> {
+        changes
+            .iter()
+            .map(|change| LineDifference {
+                version: Some(id),
+                ..change.clone()
+            })
+            .collect_vec()
+    }
+
+    ///
+    /// a line while a version is split up: its content in the full version
+    /// and without the selected changes, none where it isn't part of it
+    ///
+    struct SplitLine {
+        full: Option<String>,
+        kept: Option<String>,
+    }
+
+    ///
+    /// the changes of a version that aren't `selected`, in the order they
+    /// were found, placed to apply to `before` without the selected ones.
+    /// the version is replayed once, keeping the lines selected changes
+    /// removed and leaving out the lines they added, so changes next to a
+    /// moved block still land where they belong. every selected change has
+    /// to be one of `changes`.
+    ///
+    fn unselected(
+        before: &[String],
+        changes: Vec<LineDifference>,
+        selected: &[LineDifference],
+    ) -> Result<Vec<LineDifference>, Box<dyn error::Error>> {
+        let mut selected = selected.to_vec();
+        let mut lines = before
+            .iter()
+            .map(|line| SplitLine {
+                full: Some(line.clone()),
+                kept: Some(line.clone()),
+            })
+            .collect_vec();
+        let mut kept = vec![];
+        for change in changes {
+            let is_selected = match selected.iter().position(|other| *other == change) {
+                Some(index) => {
+                    selected.swap_remove(index);
+                    true
+                }
+                None => false,
+            };
+            if change.kind == ChangeKind::Blob {
+                if !is_selected {
+                    kept.push(change);
+                }
+                continue;
+            }
+            let full = lines
+                .iter()
+                .positions(|line| line.full.is_some())
+                .collect_vec();
+            let index = match change.kind {
+                ChangeKind::Added if change.line_number == full.len() => Some(lines.len()),
+                ChangeKind::Added => full.get(change.line_number).copied(),
+                _ => full
+                    .get(change.line_number)
+                    .copied()
+                    .filter(|&index| lines[index].full == change.line),
+            }
+            .ok_or("the changes don't fit the content of their version")?;
+            let line_number = lines[..index]
+                .iter()
+                .filter(|line| line.kept.is_some())
+                .count();
+
+            match change.kind {
+                ChangeKind::Added => {
+                    let line = change.changed_line.clone();
+                    lines.insert(
+                        index,
+                        SplitLine {
+                            full: line.clone(),
+                            kept: line.filter(|_| !is_selected),
+                        },
+                    );
+                    if !is_selected {
+                        kept.push(LineDifference {
+                            line_number,
+                            ..change
+                        });
+                    }
+                }
+                ChangeKind::Removed if is_selected => lines[index].full = None,
+                ChangeKind::Removed => {
+                    let line = lines.remove(index).kept;
+                    if line.is_some() {
+                        kept.push(LineDifference {
+                            line_number,
+                            line,
+                            ..change
+                        });
+                    }
+                }
+                _ => {
+                    lines[index].full = change.changed_line.clone();
+                    if is_selected || lines[index].kept.is_none() {
+                        continue;
+                    }
+                    let line = lines[index]
+                        .kept
+                        .replace(change.changed_line.clone().unwrap_or_default());
+                    kept.push(LineDifference {
+                        line_number,
+                        line,
+                        ..change
+                    });
+                }
+            }
+        }
+        if !selected.is_empty() {
+            return Err("a selected change isn't part of its version".into());
+        }
+
+        Ok(kept)
+    }
+
+    /// the lines of a file on disk and their layout, none if it was removed
+    fn on_disk(path: &str) -> Result<(Vec<String>, Layout), Box<dyn error::Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Layout::split(&content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok((vec![], Layout::default())),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    ///
+    /// whether the file on disk holds `lines`, split the way `Layout::split`
+    /// does. it is read line by line, so a large file is never held whole.
+    ///
+    fn holds_lines(path: &str, lines: &[String]) -> io::Result<bool> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut line = vec![];
+        // lines are compared as they are and without a "\r" before their
+        // "\n", which one counts is only known at the end
+        let (mut raw, mut stripped, mut crlf) = (true, true, true);
+        let (mut count, mut ended) = (0, 0);
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            let expected = lines.get(count).map(String::as_bytes);
+            count += 1;
+            if line.ends_with(b"\n") {
+                line.pop();
+                ended += 1;
+                crlf = crlf && line.ends_with(b"\r");
+                stripped = stripped && expected == Some(line.strip_suffix(b"\r").unwrap_or(&line));
+            } else {
+                stripped = stripped && expected == Some(&line[..]);
+            }
+            raw = raw && expected == Some(&line[..]);
+            if !raw && !stripped {
+                return Ok(false);
+            }
+        }
+
+        Ok(count == lines.len() && if crlf && ended > 0 { stripped } else { raw })
     }
 
+    ///
+    /// content of a file rebuilt by replaying its changes
+    /// on top of an empty file
+    ///
+    #[derive(Default)]
+    struct Replay {
+        lines: Vec<String>,
+    }
+
+    impl Replay {
+        ///
+        /// applies the changes of a version, given newest first. changes
+        /// that don't fit are refused, so a damaged history is never
+        /// written back to disk.
+        ///
+        fn apply(&mut self, changes: &[LineDifference]) -> Result<(), diff::ApplyError> {
+            diff::apply_lines(
+                &mut self.lines,
+                &changes.iter().rev().cloned().collect_vec(),
+            )
+        }
+
+        fn lines(&self) -> Vec<String> {
+            self.lines.clone()
+        }
+    }
+
+    /// the content before and after a version, and its changes newest first
+    type Replayed = (Vec<String>, Vec<String>, Vec<LineDifference>);
+
+    fn dated(changes: Vec<LineDifference>, timestamp: i64) -> Vec<LineDifference> {
+        let date_time = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(timestamp, 0), Utc);
+        changes
+            .into_iter()
+            .map(|change| LineDifference {
+                date_time,
+                ..change
+            })
+            .collect_vec()
+    }
+
+    ///
+    /// changed lines per day. a moved line counts once, where it was
+    /// added, instead of as a removal and an addition.
+    ///
     fn hits_of_codes(file_versions: Vec<Version>) -> Vec<HitsOfCode> {
         file_versions
             .iter()
             .map(|version| HitsOfCode {
                 date: version.datetime.date(),
-                hits: version.changes.len() as u64,
+                hits: version
+                    .changes
+                    .iter()
+                    .filter(|change| {
+                        !(change.kind == ChangeKind::Removed && change.moved.is_some())
+                    })
+                    .count() as u64,
             })
             .collect_vec()
             .into_iter()
@@ -135,9 +905,10 @@ pub mod store {
                     let path = entry.path();
                     let path = path.to_str().unwrap_or("couldn't find path");
 
-                    init_file_version_stack(path.to_string(), db);
-                    init_file_version_marker(path.to_string(), db);
-                    init_file_changes(path.to_string(), db)
+                    let (lines, layout) = on_disk(path)?;
+                    let id = init_file_version_stack(path.to_string(), layout, false, db)?;
+                    init_file_version_tree(path.to_string(), id, db);
+                    init_file_changes(path.to_string(), id, &lines, db)
                 },
             )
             .find(|e| e.is_err())
@@ -164,35 +935,51 @@ pub mod store {
                 .all(|p| !entry.path().parent().unwrap().ends_with(p))
     }
 
-    fn init_file_version_stack(path: String, db: &mut PickleDb) {
-        let timestamps = vec![Utc::now().naive_utc().timestamp()];
-        let version_stack = VersionStack { path, timestamps };
+    fn init_file_version_stack(
+        path: String,
+        layout: Layout,
+        removed: bool,
+        db: &mut PickleDb,
+    ) -> Result<i64, Box<dyn error::Error>> {
+        let id = next_version_id(db)?;
+        let version_stack = VersionStack {
+            path,
+            versions: vec![StoredVersion {
+                id,
+                datetime: Utc::now(),
+                layout,
+                removed,
+                hidden: false,
+            }],
+            timestamps: vec![],
+        };
 
         db.ladd(FILE_VERSION_STACK, &version_stack);
+        Ok(id)
     }
 
-    fn init_file_version_marker(path: String, db: &mut PickleDb) {
-        let version_marker = VersionMarker {
-            path,
-            timestamp_marker: 1,
-        };
-
-        db.ladd(FILE_VERSION_MARKER, &version_marker);
+    fn init_file_version_tree(path: String, id: i64, db: &mut PickleDb) {
+        db.ladd(FILE_VERSION_TREE, &VersionTree::new(path, id));
     }
 
-    fn init_file_changes(path: String, db: &mut PickleDb) -> Result<(), Box<dyn error::Error>> {
+    fn init_file_changes(
+        path: String,
+        id: i64,
+        lines: &[String],
+        db: &mut PickleDb,
+    ) -> Result<(), Box<dyn error::Error>> {
         db.lcreate(path.as_str())?;
-        let file = File::open(path.clone())?;
-        io::BufReader::new(file)
-            .lines()
+        lines
+            .iter()
             .enumerate()
             .map(|(index, line)| {
-                db.ladd(
-                    path.as_str(),
-                    &LineDifference::new(path.clone(), index, "".to_string(), line.unwrap()),
-                )
-                .map(|_| ())
-                .ok_or_else(|| "couldn't add line difference".into())
+                let change = LineDifference {
+                    version: Some(id),
+                    ..LineDifference::added(path.clone(), index, line.clone())
+                };
+                db.ladd(path.as_str(), &change)
+                    .map(|_| ())
+                    .ok_or_else(|| "couldn't add line difference".into())
             })
             .find(|e| e.is_err())
             .unwrap_or(Ok(()))
@@ -207,6 +994,16 @@ pub mod store {
         .map_err(|err| err.into())
     }
 
+    fn load_read_only(store_path: &str) -> Result<PickleDb, Box<dyn error::Error>> {
+        let mut db = PickleDb::load_read_only(store_path, SerializationMethod::Yaml)?;
+        migrate_version_markers(&mut db)?;
+        migrate_line_differences(&mut db)?;
+        migrate_version_ids(&mut db)?;
+        migrate_positional_changes(&mut db)?;
+
+        Ok(db)
+    }
+
     impl Store {
         pub fn new(
             store_path: &str,
@@ -214,206 +1011,791 @@ pub mod store {
             excluded_files: Vec<String>,
             excluded_paths: Vec<String>,
         ) -> Result<Store, Box<dyn error::Error>> {
-            let mut db = load(store_path);
-            if db.is_err() {
-                db = init(store_path, watch_path, excluded_files, excluded_paths);
+            // a store that exists but can't be loaded is left alone for fsck
+            if !Path::new(store_path).exists() {
+                init(store_path, watch_path, excluded_files, excluded_paths)?;
+            }
+
+            Store::open(store_path)
+        }
+
+        /// opens an existing store without initializing a missing one
+        pub fn open(store_path: &str) -> Result<Store, Box<dyn error::Error>> {
+            let mut db = load(store_path)?;
+            migrate_version_markers(&mut db)?;
+            migrate_line_differences(&mut db)?;
+            migrate_version_ids(&mut db)?;
+            migrate_positional_changes(&mut db)?;
+            if db.exists(SEARCH_INDEX) {
+                db.rem(SEARCH_INDEX)?;
             }
-            let db = db?;
 
             Ok(Store {
                 db,
+                store_path: store_path.to_string(),
+                read_only: false,
+                time_frame: TimeFrame::HOUR,
+                conflict_strategy: ConflictStrategy::default(),
+                diff_algorithms: DiffAlgorithms::default(),
+                ignore_rules: IgnoreRules::default(),
+                blob_limit: DEFAULT_BLOB_LIMIT,
+                min_moved_lines: diff::MIN_MOVED_LINES,
+                states: States::default(),
+                search_index: SearchIndex::default(),
+            })
+        }
+
+        ///
+        /// attaches to a store owned by another instance for browsing.
+        /// nothing is ever written, every view reloads the store from disk.
+        ///
+        pub fn open_read_only(store_path: &str) -> Result<Store, Box<dyn error::Error>> {
+            Ok(Store {
+                db: load_read_only(store_path)?,
+                store_path: store_path.to_string(),
+                read_only: true,
                 time_frame: TimeFrame::HOUR,
+                conflict_strategy: ConflictStrategy::default(),
+                diff_algorithms: DiffAlgorithms::default(),
+                ignore_rules: IgnoreRules::default(),
+                blob_limit: DEFAULT_BLOB_LIMIT,
+                min_moved_lines: diff::MIN_MOVED_LINES,
+                states: States::default(),
+                search_index: SearchIndex::default(),
             })
         }
 
+        pub fn is_read_only(&self) -> bool {
+            self.read_only
+        }
+
+        fn ensure_writable(&self) -> Result<(), Box<dyn error::Error>> {
+            if self.read_only {
+                return Err(format!("{} is opened read-only", self.store_path).into());
+            }
+            Ok(())
+        }
+
         pub fn store_changes(
             &mut self,
             path: &str,
             changes: &[LineDifference],
         ) -> Result<(), Box<dyn error::Error>> {
-            self.db.lextend(path, changes);
-            let version_stack = self.db.liter(FILE_VERSION_STACK).find(|version_stack| {
-                let version_stack: VersionStack = version_stack.get_item().unwrap();
-                version_stack.path.eq(path)
-            });
-            if let Some(version_stack) = version_stack {
-                let now = Utc::now().naive_utc().timestamp();
-                let mut version_stack: VersionStack = version_stack.get_item().unwrap();
-                let version_marker: VersionMarker =
-                    self.get_version_marker(path.to_string()).unwrap();
-
-                self.db.lrem_value(FILE_VERSION_STACK, &version_stack)?;
-                version_stack.timestamps.push(now);
-                self.db.ladd(FILE_VERSION_STACK, &version_stack);
+            self.ensure_writable()?;
+            let version_tree = self.get_version_tree(path);
+            // only the line endings, the final newline or whether the file
+            // exists may have changed
+            let layout = on_disk(path)?.1;
+            let removed = !Path::new(path).exists();
+            let relaid = version_tree.is_some() && (layout, removed) != self.recorded(path);
+            if changes.is_empty() && version_tree.is_some() && !relaid {
+                return Ok(());
+            }
+            let ignore = self.ignore(path);
+            let hidden = self.ignore_rules.skip_versions
+                && !relaid
+                && !changes.iter().any(|change| ignore.is_significant(change));
 
-                self.increment_version_marker_by(version_marker, 1);
+            if let Some(mut version_tree) = version_tree {
+                let id = self.push_version(path, Utc::now(), layout, removed, hidden)?;
+                self.db.lextend(path, &versioned(changes, id));
+                if !hidden {
+                    version_tree.add(id);
+                    self.set_version_tree(version_tree)?;
+                }
             } else {
-                init_file_version_stack(path.to_string(), &mut self.db);
-                init_file_version_marker(path.to_string(), &mut self.db);
+                let id = init_file_version_stack(path.to_string(), layout, removed, &mut self.db)?;
+                self.db.lextend(path, &versioned(changes, id));
+                init_file_version_tree(path.to_string(), id, &mut self.db);
             }
 
             Ok(())
         }
 
+        ///
+        /// adds a version stored at `datetime` whose lines are laid out as
+        /// `layout` to the stack, or one that removed the file. returns its id.
+        ///
+        fn push_version(
+            &mut self,
+            path: &str,
+            datetime: DateTime<Utc>,
+            layout: Layout,
+            removed: bool,
+            hidden: bool,
+        ) -> Result<i64, Box<dyn error::Error>> {
+            let mut version_stack = self
+                .get_version_stack(path)
+                .ok_or("couldn't find version stack")?;
+            let id = next_version_id(&mut self.db)?;
+
+            self.db.lrem_value(FILE_VERSION_STACK, &version_stack)?;
+            version_stack.versions.push(StoredVersion {
+                id,
+                datetime,
+                layout,
+                removed,
+                hidden,
+            });
+            self.db.ladd(FILE_VERSION_STACK, &version_stack);
+
+            Ok(id)
+        }
+
+        ///
+        /// the version the file is expected to hold on disk: the current one
+        /// or the newest of the hidden versions stored right after it
+        ///
+        fn on_disk_version(&self, version_tree: &VersionTree) -> i64 {
+            self.stored_versions(&version_tree.path)
+                .unwrap_or_default()
+                .into_iter()
+                .skip_while(|version| version.id != version_tree.current)
+                .take_while(|version| version.id == version_tree.current || version.hidden)
+                .last()
+                .map_or(version_tree.current, |version| version.id)
+        }
+
+        /// the layout of the version `id` of `path`
+        fn layout_of(&self, path: &str, id: i64) -> Layout {
+            self.stored_versions(path)
+                .and_then(|versions| versions.into_iter().find(|version| version.id == id))
+                .map(|version| version.layout)
+                .unwrap_or_default()
+        }
+
+        ///
+        /// the layout of the newest version of `path`, the one on record,
+        /// and whether that version removed the file
+        ///
+        fn recorded(&self, path: &str) -> (Layout, bool) {
+            self.stored_versions(path)
+                .and_then(|versions| versions.last().copied())
+                .map(|version| (version.layout, version.removed))
+                .unwrap_or_default()
+        }
+
         pub fn change_time_frame(&mut self, time_frame: TimeFrame) {
             self.time_frame = time_frame;
         }
 
-        pub fn undo_by(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
-            self.undo(path, count)
+        pub fn change_conflict_strategy(&mut self, conflict_strategy: ConflictStrategy) {
+            self.conflict_strategy = conflict_strategy;
         }
 
-        pub fn redo_by(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
-            self.redo(path, count)
+        pub fn change_diff_algorithms(&mut self, diff_algorithms: DiffAlgorithms) {
+            self.diff_algorithms = diff_algorithms;
         }
 
-        pub fn view(&mut self) -> Result<Vec<Option<FileVersions>>, Box<dyn error::Error>> {
-            let now = Utc::now().naive_utc();
+        /// the algorithm changes of `path` are found with
+        pub fn diff_algorithm(&self, path: &str) -> Algorithm {
+            self.diff_algorithms.of(path)
+        }
 
-            Ok(self
-                .db
-                .liter(FILE_VERSION_STACK)
-                .map(|version_stack| {
-                    let version_stack: VersionStack = version_stack.get_item().unwrap();
-                    let timestamps = version_stack
-                        .timestamps
-                        .iter()
-                        .filter(|timestamp| {
-                            now.timestamp() - self.time_frame.value() < *<&i64>::clone(timestamp)
-                        })
-                        .copied()
-                        .collect_vec();
+        pub fn change_ignore_rules(&mut self, ignore_rules: IgnoreRules) {
+            self.ignore_rules = ignore_rules;
+        }
 
-                    if timestamps.is_empty() {
-                        return None;
+        /// what is ignored when diffing and viewing `path`
+        pub fn ignore(&self, path: &str) -> Ignore {
+            self.ignore_rules.of(path)
+        }
+
+        pub fn change_blob_limit(&mut self, blob_limit: u64) {
+            self.blob_limit = blob_limit;
+        }
+
+        /// bytes the cached contents of all files may take together
+        pub fn change_cache_limit(&mut self, cache_limit: u64) {
+            self.states.set_limit(cache_limit);
+        }
+
+        /// blocks with fewer lines aren't recorded as moved, 0 records no moves
+        pub fn change_min_moved_lines(&mut self, min_moved_lines: usize) {
+            self.min_moved_lines = min_moved_lines;
+        }
+
+        pub fn undo_by(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
+            self.undo(path, count)
+        }
+
+        pub fn redo_by(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
+            self.redo(path, count)
+        }
+
+        /// restores the newest version recorded at or before the given time
+        pub fn jump_to(
+            &mut self,
+            path: String,
+            timestamp: i64,
+        ) -> Result<(), Box<dyn error::Error>> {
+            let target = self
+                .nearest_version(&path, timestamp)
+                .ok_or("no version recorded before the given time")?;
+
+            self.restore_version(path, target)
+        }
+
+        /// restores the version with the given id
+        pub fn restore_version(
+            &mut self,
+            path: String,
+            id: i64,
+        ) -> Result<(), Box<dyn error::Error>> {
+            let version_tree = self
+                .get_version_tree(&path)
+                .ok_or("couldn't find version tree")?;
+            if !version_tree.contains(id) {
+                return Err(format!("{} has no version {} to restore", path, id).into());
+            }
+
+            self.checkout(version_tree, id)
+        }
+
+        ///
+        /// takes back only the `selected` changes of one version, keeping
+        /// its other changes and everything edited since. the result is
+        /// written to disk and recorded as a new version. conflicts with
+        /// later edits are handled as for an undo.
+        ///
+        pub fn restore_changes(
+            &mut self,
+            path: &str,
+            selected: &[LineDifference],
+        ) -> Result<(), Box<dyn error::Error>> {
+            self.ensure_writable()?;
+            let lines = self.preview_restore(path, selected)?;
+            let layout = on_disk(path)?.1;
+            File::create(path)?.write_all(layout.join(&lines).as_bytes())?;
+
+            let changes = self.find_changes(path)?;
+            self.store_changes(path, &changes)
+        }
+
+        /// the content `restore_changes` would write, without writing it
+        pub fn preview_restore(
+            &self,
+            path: &str,
+            selected: &[LineDifference],
+        ) -> Result<Vec<String>, Box<dyn error::Error>> {
+            let id = match selected
+                .iter()
+                .map(|change| change.version)
+                .dedup()
+                .exactly_one()
+            {
+                Ok(Some(id)) => id,
+                _ => return Err("select changes of one stored version".into()),
+            };
+            let (before, after, changes) = self.replay_to(path, id)?;
+
+            let kept = unselected(&before, changes.into_iter().rev().collect_vec(), selected)?;
+            let mut restored = before;
+            diff::apply_lines(&mut restored, &kept)?;
+
+            self.merge_with_disk(path, &after, restored)
+        }
+
+        ///
+        /// the changes turning version `from` of `path` into version `to`,
+        /// or into the file on disk if `to` is none. the versions may be
+        /// far apart or on different branches of the tree.
+        ///
+        pub fn diff_between(
+            &self,
+            path: &str,
+            from: i64,
+            to: Option<i64>,
+        ) -> Result<Vec<LineDifference>, Box<dyn error::Error>> {
+            let (_, old, _) = self.replay_to(path, from)?;
+            let new = match to {
+                Some(to) => self.replay_to(path, to)?.1,
+                None => on_disk(path)?.0,
+            };
+
+            Ok(diff::find_between(
+                path,
+                &old,
+                &new,
+                self.diff_algorithm(path),
+                self.ignore(path),
+                self.min_moved_lines,
+            ))
+        }
+
+        ///
+        /// the content of `path` before and after the version `id`, and the
+        /// changes of that version, newest first. versions are replayed in
+        /// the order they were stored, whichever branch they are on.
+        ///
+        fn replay_to(&self, path: &str, id: i64) -> Result<Replayed, Box<dyn error::Error>> {
+            let stored_versions = self
+                .stored_versions(path)
+                .ok_or("couldn't find version stack")?;
+            let mut replay = Replay::default();
+
+            for version in self.get_versions(path, &stored_versions).into_iter().rev() {
+                let before = replay.lines();
+                replay.apply(&version.changes)?;
+                if version.id == id {
+                    return Ok((before, replay.lines(), version.changes));
+                }
+            }
+
+            Err(format!("{} has no version {}", path, id).into())
+        }
+
+        ///
+        /// id of the newest version in the tree of `path` stored at or
+        /// before `timestamp`, given in seconds
+        ///
+        pub fn nearest_version(&self, path: &str, timestamp: i64) -> Option<i64> {
+            let version_tree = self.get_version_tree(path)?;
+
+            self.stored_versions(path)?
+                .into_iter()
+                .rev()
+                .filter(|version| version_tree.contains(version.id))
+                .find(|version| version.datetime.timestamp() <= timestamp)
+                .map(|version| version.id)
+        }
+
+        /// when the version with the given id was stored
+        pub fn version_datetime(&self, path: &str, id: i64) -> Option<NaiveDateTime> {
+            self.stored_versions(path)?
+                .into_iter()
+                .find(|version| version.id == id)
+                .map(|version| version.datetime.naive_utc())
+        }
+
+        pub fn version_tree(&self, path: &str) -> Option<VersionTree> {
+            self.get_version_tree(path)
+        }
+
+        ///
+        /// the content of every file after each of its versions, in the
+        /// order they were stored. only versions `keep` accepts by their id
+        /// and time are returned, but all of them are replayed.
+        ///
+        pub fn snapshots(
+            &self,
+            keep: impl Fn(i64, NaiveDateTime) -> bool,
+        ) -> Result<Vec<Snapshot>, Box<dyn error::Error>> {
+            let mut snapshots = vec![];
+            for version_stack in self.db.liter(FILE_VERSION_STACK) {
+                let version_stack = version_stack.get_item::<VersionStack>().unwrap();
+                let path = version_stack.path.clone();
+                let stored: HashMap<i64, StoredVersion> = version_stack
+                    .versions
+                    .iter()
+                    .map(|version| (version.id, *version))
+                    .collect();
+                let mut replay = Replay::default();
+
+                for version in self
+                    .get_versions(&path, &version_stack.versions)
+                    .into_iter()
+                    .rev()
+                {
+                    replay.apply(&version.changes)?;
+                    if keep(version.id, version.datetime) {
+                        snapshots.push(Snapshot {
+                            path: path.clone(),
+                            id: version.id,
+                            datetime: version.datetime,
+                            lines: replay.lines(),
+                            layout: stored[&version.id].layout,
+                            removed: stored[&version.id].removed,
+                        });
                     }
-                    Some(VersionStack {
-                        timestamps,
-                        path: version_stack.path,
-                    })
-                })
+                }
+            }
+
+            Ok(snapshots
+                .into_iter()
+                .sorted_by(|a, b| (a.datetime, &a.path, a.id).cmp(&(b.datetime, &b.path, b.id)))
+                .collect_vec())
+        }
+
+        /// paths of every file with a version stack
+        pub fn paths(&self) -> Vec<String> {
+            self.db
+                .liter(FILE_VERSION_STACK)
+                .map(|version_stack| version_stack.get_item::<VersionStack>().unwrap().path)
+                .collect_vec()
+        }
+
+        ///
+        /// seeds the history of a file with versions recorded before its
+        /// first version, e.g. commits of a git repository. `history` holds
+        /// the timestamp and full content of each version, only entries
+        /// older than the current first version are imported.
+        /// imported versions get the next free ids like any new version,
+        /// ids tell the order versions were stored in and the imported ones
+        /// count as new for the mirror. lists order versions by their time.
+        /// returns the number of imported versions.
+        ///
+        pub fn import_history(
+            &mut self,
+            path: &str,
+            history: &[(i64, Vec<String>)],
+        ) -> Result<usize, Box<dyn error::Error>> {
+            self.ensure_writable()?;
+            let mut version_tree = self
+                .get_version_tree(path)
+                .ok_or("couldn't find version tree")?;
+            let mut version_stack = self
+                .get_version_stack(path)
+                .ok_or("couldn't find version stack")?;
+            let root = version_tree.root();
+            let root_datetime = version_stack
+                .versions
+                .iter()
+                .find(|version| version.id == root)
+                .map(|version| version.datetime)
+                .ok_or("couldn't find the first version")?;
+            let baseline = self.content_of(&version_tree, root)?;
+            let history = history
+                .iter()
+                .filter(|(timestamp, _)| *timestamp < root_datetime.timestamp())
+                .sorted_by_key(|(timestamp, _)| *timestamp)
+                .collect_vec();
+            if history.is_empty() {
+                return Ok(0);
+            }
+
+            let mut previous: Vec<String> = vec![];
+            let mut changes = vec![];
+            let mut imported = vec![];
+            for (timestamp, lines) in &history {
+                let id = next_version_id(&mut self.db)?;
+                changes.extend(versioned(
+                    &dated(
+                        diff::find_between(
+                            path,
+                            &previous,
+                            lines,
+                            self.diff_algorithm(path),
+                            self.ignore(path),
+                            self.min_moved_lines,
+                        ),
+                        *timestamp,
+                    ),
+                    id,
+                ));
+                imported.push(StoredVersion {
+                    id,
+                    datetime: DateTime::<Utc>::from_utc(
+                        NaiveDateTime::from_timestamp(*timestamp, 0),
+                        Utc,
+                    ),
+                    layout: Layout::default(),
+                    removed: false,
+                    hidden: false,
+                });
+                previous = lines.clone();
+            }
+            // the first version now starts from the last imported one
+            changes.extend(versioned(
+                &dated(
+                    diff::find_between(
+                        path,
+                        &previous,
+                        &baseline,
+                        self.diff_algorithm(path),
+                        self.ignore(path),
+                        self.min_moved_lines,
+                    ),
+                    root_datetime.timestamp(),
+                ),
+                root,
+            ));
+            changes.extend(
+                self.get_file_changes::<LineDifference>(path)
+                    .into_iter()
+                    .filter(|change| change.version != Some(root)),
+            );
+
+            self.db.lrem_list(path)?;
+            self.db.lcreate(path)?;
+            self.db.lextend(path, &changes);
+            self.states.forget(path);
+            self.search_index.forget(path);
+            self.db.lrem_value(FILE_VERSION_STACK, &version_stack)?;
+            version_stack.versions = [imported.clone(), version_stack.versions].concat();
+            self.db.ladd(FILE_VERSION_STACK, &version_stack);
+            version_tree.graft(&imported.iter().map(|version| version.id).collect_vec());
+            self.set_version_tree(version_tree)?;
+
+            Ok(imported.len())
+        }
+
+        pub fn view(&mut self) -> Result<Vec<Option<FileVersions>>, Box<dyn error::Error>> {
+            if self.read_only {
+                self.db = load_read_only(&self.store_path)?;
+                self.states.clear();
+                self.search_index = SearchIndex::default();
+            }
+            let now = Utc::now().naive_utc();
+
+            Ok(self
+                .db
+                .liter(FILE_VERSION_STACK)
                 .map(|version_stack| -> Option<FileVersions> {
-                    version_stack.as_ref()?;
-                    let mut version_stack = version_stack.unwrap();
-                    let path = version_stack.clone().path;
-                    version_stack.timestamps.push(0);
-                    let versions = self.get_versions(version_stack);
+                    let version_stack: VersionStack = version_stack.get_item().unwrap();
+                    let mut structures =
+                        self.structures(&version_stack.path, &version_stack.versions);
+                    let stored_versions = version_stack
+                        .versions
+                        .into_iter()
+                        .filter(|version| {
+                            !version.hidden
+                                && now.timestamp().saturating_sub(self.time_frame.value())
+                                    < version.datetime.timestamp()
+                        })
+                        .collect_vec();
+                    if stored_versions.is_empty() {
+                        return None;
+                    }
+
+                    let path = version_stack.path;
+                    let ignore = self.ignore(&path);
+                    let versions = self
+                        .get_versions(&path, &stored_versions)
+                        .into_iter()
+                        .map(|version| Version {
+                            changes: version
+                                .changes
+                                .into_iter()
+                                .filter(|change| ignore.is_significant(change))
+                                .collect_vec(),
+                            structure: structures.remove(&version.id),
+                            ..version
+                        })
+                        .collect_vec();
                     let hits_of_codes = hits_of_codes(versions.clone());
+                    let version_tree = self.get_version_tree(&path);
+                    let tags = self.tags_of(&path);
 
                     Some(FileVersions {
                         path,
                         versions,
                         hits_of_codes,
+                        version_tree,
+                        tags,
                     })
                 })
                 .collect_vec())
         }
 
-        fn get_versions(&self, version_stack: VersionStack) -> Vec<Version> {
-            if version_stack.timestamps.len() == 1 {
-                return version_stack
-                    .timestamps
-                    .iter()
-                    .map(|timestamp| {
-                        let changes: Vec<LineDifference> = self
-                            .get_file_changes::<LineDifference>(version_stack.path.as_str())
-                            .into_iter()
-                            .sorted_by(|a, b| {
-                                diff::sort(b.date_time.as_str(), a.date_time.as_str())
-                            })
-                            .filter(|e| {
-                                let diff_timestamp = NaiveDateTime::parse_from_str(
-                                    e.date_time.as_str(),
-                                    diff::RFC3339,
-                                )
-                                .unwrap()
-                                .timestamp();
-
-                                diff_timestamp >= *timestamp
-                            })
-                            .collect_vec();
-
-                        Version {
-                            datetime: NaiveDateTime::from_timestamp(*timestamp, 0),
-                            changes,
-                        }
-                    })
-                    .collect_vec();
+        ///
+        /// the changed keys of every version of a json, toml or yaml file
+        /// by version id, for files compared by their structure. versions
+        /// whose content doesn't parse have none.
+        ///
+        fn structures(
+            &self,
+            path: &str,
+            stored_versions: &[StoredVersion],
+        ) -> HashMap<i64, Vec<KeyChange>> {
+            if !self.diff_algorithms.structural || !diff::structural::is_structured(path) {
+                return HashMap::new();
             }
+            let mut replay = Replay::default();
 
-            version_stack
-                .timestamps
-                .iter()
-                .sorted_by(|a, b| Ord::cmp(b, a))
-                .collect_vec()
-                .windows(2)
-                .map(|diff_pairs| {
-                    let changes = self
-                        .get_file_changes::<LineDifference>(version_stack.path.as_str())
-                        .into_iter()
-                        .sorted_by(|a, b| diff::sort(b.date_time.as_str(), a.date_time.as_str()))
-                        .filter(|e| {
-                            let diff_timestamp =
-                                NaiveDateTime::parse_from_str(e.date_time.as_str(), diff::RFC3339)
-                                    .unwrap()
-                                    .timestamp();
-
-                            diff_timestamp <= *diff_pairs[0]
-                                && diff_timestamp > *diff_pairs[1]
-                        })
-                        .collect_vec();
+            self.get_versions(path, stored_versions)
+                .into_iter()
+                .rev()
+                .filter_map(|version| {
+                    let before = replay.lines();
+                    replay.apply(&version.changes).ok()?;
+                    Some((
+                        version.id,
+                        diff::structural(path, &before, &replay.lines())?,
+                    ))
+                })
+                .collect()
+        }
 
-                    Version {
-                        datetime: NaiveDateTime::from_timestamp(*diff_pairs[0], 0),
-                        changes,
+        ///
+        /// the given versions of a file with the changes linked to them,
+        /// newest first. the changes of a version are newest first, too.
+        ///
+        fn get_versions(&self, path: &str, stored_versions: &[StoredVersion]) -> Vec<Version> {
+            let mut changes: HashMap<i64, Vec<LineDifference>> = HashMap::new();
+            self.get_file_changes::<LineDifference>(path)
+                .into_iter()
+                .rev()
+                .for_each(|change| {
+                    if let Some(version) = change.version {
+                        changes.entry(version).or_default().push(change);
                     }
+                });
+
+            stored_versions
+                .iter()
+                .sorted_by(|a, b| (b.datetime, b.id).cmp(&(a.datetime, a.id)))
+                .map(|stored_version| Version {
+                    id: stored_version.id,
+                    datetime: stored_version.datetime.naive_utc(),
+                    changes: changes.remove(&stored_version.id).unwrap_or_default(),
+                    note: self.note(path, stored_version.id),
+                    structure: None,
                 })
                 .collect_vec()
         }
 
-        fn get_version_marker(&self, path: String) -> Option<VersionMarker> {
+        fn get_version_stack(&self, path: &str) -> Option<VersionStack> {
             self.db
-                .liter(FILE_VERSION_MARKER)
-                .find(|version_marker| {
-                    let version_marker: VersionMarker = version_marker.get_item().unwrap();
-                    version_marker.path.eq(&path)
-                })
-                .map(|version_marker| version_marker.get_item().unwrap())
+                .liter(FILE_VERSION_STACK)
+                .map(|version_stack| version_stack.get_item().unwrap())
+                .find(|version_stack: &VersionStack| version_stack.path.eq(path))
         }
 
-        fn undo(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
-            let versions = self.peek_versions(path.clone(), count, true);
-            let version_marker: VersionMarker = self.get_version_marker(path).unwrap();
-
-            self.decrement_version_marker_by(version_marker, count);
-            self.undo_versions(versions)
+        /// the versions of a file, oldest first
+        fn stored_versions(&self, path: &str) -> Option<Vec<StoredVersion>> {
+            self.get_version_stack(path).map(|version_stack| {
+                version_stack
+                    .versions
+                    .into_iter()
+                    .sorted_by(|a, b| (a.datetime, a.id).cmp(&(b.datetime, b.id)))
+                    .collect_vec()
+            })
         }
 
-        fn decrement_version_marker_by(&mut self, mut version_marker: VersionMarker, count: usize) {
+        fn get_version_tree(&self, path: &str) -> Option<VersionTree> {
             self.db
-                .lrem_value(FILE_VERSION_MARKER, &version_marker)
-                .unwrap();
-            version_marker.timestamp_marker -= count;
-            self.db.ladd(FILE_VERSION_MARKER, &version_marker);
+                .liter(FILE_VERSION_TREE)
+                .map(|version_tree| version_tree.get_item().unwrap())
+                .find(|version_tree: &VersionTree| version_tree.path.eq(path))
         }
 
-        fn increment_version_marker_by(&mut self, mut version_marker: VersionMarker, count: usize) {
-            self.db
-                .lrem_value(FILE_VERSION_MARKER, &version_marker)
-                .unwrap();
-            version_marker.timestamp_marker += count;
-            self.db.ladd(FILE_VERSION_MARKER, &version_marker);
+        fn set_version_tree(
+            &mut self,
+            version_tree: VersionTree,
+        ) -> Result<(), Box<dyn error::Error>> {
+            if let Some(previous) = self.get_version_tree(&version_tree.path) {
+                self.db.lrem_value(FILE_VERSION_TREE, &previous)?;
+            }
+            self.db.ladd(FILE_VERSION_TREE, &version_tree);
+
+            Ok(())
+        }
+
+        fn undo(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
+            let version_tree = self
+                .get_version_tree(&path)
+                .ok_or("couldn't find version tree")?;
+            let target = version_tree.undo_target(count);
+
+            self.checkout(version_tree, target)
         }
 
         fn redo(&mut self, path: String, count: usize) -> Result<(), Box<dyn error::Error>> {
-            let versions = self.peek_versions(path.clone(), count, true);
-            let version_marker: VersionMarker = self.get_version_marker(path).unwrap();
+            let version_tree = self
+                .get_version_tree(&path)
+                .ok_or("couldn't find version tree")?;
+            let target = version_tree.redo_target(count);
 
-            self.increment_version_marker_by(version_marker, count);
-            self.undo_versions(versions)
+            self.checkout(version_tree, target)
+        }
+
+        ///
+        /// writes the content of the target node back to disk and moves the
+        /// tree onto it. the write itself is recorded right away as a plain
+        /// version outside of the tree, so the following file event finds
+        /// nothing new and the next edit branches off the target.
+        ///
+        /// if the file no longer matches the current node, the edits made
+        /// on disk meanwhile are merged with the target instead of being
+        /// overwritten.
+        ///
+        fn checkout(
+            &mut self,
+            version_tree: VersionTree,
+            target: i64,
+        ) -> Result<(), Box<dyn error::Error>> {
+            self.ensure_writable()?;
+            if target == version_tree.current {
+                return Ok(());
+            }
+            let lines = self.checkout_lines(&version_tree, target)?;
+
+            self.write_checkout(version_tree, target, &lines)
+        }
+
+        /// the content checking out `target` writes, merged with the edits on disk
+        fn checkout_lines(
+            &self,
+            version_tree: &VersionTree,
+            target: i64,
+        ) -> Result<Vec<String>, Box<dyn error::Error>> {
+            let base = self.content_of(version_tree, self.on_disk_version(version_tree))?;
+            let restored = self.content_of(version_tree, target)?;
+
+            self.merge_with_disk(&version_tree.path, &base, restored)
+        }
+
+        /// writes the content `checkout_lines` gave and makes `target` current
+        fn write_checkout(
+            &mut self,
+            mut version_tree: VersionTree,
+            target: i64,
+            lines: &[String],
+        ) -> Result<(), Box<dyn error::Error>> {
+            let path = version_tree.path.clone();
+            let layout = self.layout_of(&path, target);
+
+            File::create(&path)?.write_all(layout.join(lines).as_bytes())?;
+
+            let changes = self.find_changes(&path)?;
+            if !changes.is_empty() || (layout, false) != self.recorded(&path) {
+                let id = self.push_version(&path, Utc::now(), layout, false, false)?;
+                self.db.lextend(&path, &versioned(&changes, id));
+            }
+
+            version_tree.current = target;
+            self.set_version_tree(version_tree)
+        }
+
+        fn merge_with_disk(
+            &self,
+            path: &str,
+            base: &[String],
+            restored: Vec<String>,
+        ) -> Result<Vec<String>, Box<dyn error::Error>> {
+            let current = on_disk(path)?.0;
+            if current == base {
+                return Ok(restored);
+            }
+
+            let merge = diff::merge::merge3(base, &current, &restored);
+            if merge.conflicts > 0 && self.conflict_strategy == ConflictStrategy::Refuse {
+                return Err(ConflictError {
+                    path: path.to_string(),
+                    conflicts: merge.conflicts,
+                }
+                .into());
+            }
+
+            Ok(merge.lines)
+        }
+
+        ///
+        /// the content right after the version `target` was stored. the
+        /// versions are replayed strictly in the order they were stored,
+        /// since each one only fits the content its predecessor left.
+        ///
+        fn content_of(
+            &self,
+            version_tree: &VersionTree,
+            target: i64,
+        ) -> Result<Vec<String>, Box<dyn error::Error>> {
+            Ok(self.replay_to(&version_tree.path, target)?.1)
         }
 
         pub fn create_new_file_entry(&mut self, path: &str) -> Result<(), Box<dyn error::Error>> {
+            self.ensure_writable()?;
             if !self.db.lexists(path) {
                 self.db.lcreate(path)?;
             }
@@ -421,6 +1803,61 @@ pub mod store {
             Ok(())
         }
 
+        ///
+        /// the changes turning the recorded state of `path` into the file on
+        /// disk. files larger than the blob limit aren't diffed, they are
+        /// compared line by line while they are read and only read whole
+        /// if they changed, to be stored as a blob.
+        ///
+        pub fn find_changes(
+            &mut self,
+            path: &str,
+        ) -> Result<Vec<LineDifference>, Box<dyn error::Error>> {
+            let (algorithm, ignore) = (self.diff_algorithm(path), self.ignore(path));
+            let (blob_limit, min_moved) = (self.blob_limit, self.min_moved_lines);
+            let previous = self.current_lines(path);
+
+            if fs::metadata(path)?.len() > blob_limit {
+                if holds_lines(path, previous)? {
+                    return Ok(vec![]);
+                }
+                let content = fs::read_to_string(path)?;
+                return Ok(vec![LineDifference::blob(path.to_string(), content)]);
+            }
+
+            Ok(diff::find_between(
+                path,
+                previous,
+                &diff::read_lines(path)?,
+                algorithm,
+                ignore,
+                min_moved,
+            ))
+        }
+
+        ///
+        /// the content of `path` as recorded. it is cached and only the
+        /// changes stored since the last call are patched onto it.
+        ///
+        pub fn current_lines(&mut self, path: &str) -> &[String] {
+            let length = self.db.llen(path);
+            let (indexed, mut lines) = match self.states.take(path) {
+                Some((indexed, lines)) if indexed <= length => (indexed, lines),
+                _ => (0, vec![]),
+            };
+            if indexed < length {
+                let changes = self
+                    .db
+                    .liter(path)
+                    .skip(indexed)
+                    .filter_map(|change| change.get_item::<LineDifference>())
+                    .collect_vec();
+                diff::patch(&mut lines, &changes);
+            }
+
+            self.states.insert(path, length, lines)
+        }
+
         pub fn get_file_changes<T: DeserializeOwned + std::fmt::Debug>(
             &self,
             path: &str,
@@ -430,80 +1867,703 @@ pub mod store {
                 .map(|e| e.get_item::<T>().unwrap())
                 .collect()
         }
+    }
+}
 
-        fn peek_versions(&mut self, path: String, count: usize, previous: bool) -> Vec<Version> {
-            let version_marker = self.get_version_marker(path.clone()).unwrap();
-            let pos;
-            if previous {
-                pos = version_marker.timestamp_marker - count;
-            } else {
-                pos = version_marker.timestamp_marker;
-            }
+#[cfg(test)]
+mod tests {
+    use super::fixture::Fixture;
+    use super::store::{
+        ConflictError, ConflictStrategy, DiffAlgorithms, IgnoreRules, PathIgnore, Store, TimeFrame,
+        Version,
+    };
+    use chrono::NaiveDateTime;
+    use diff::{Algorithm, ChangeKind, Ignore, KeyChange, LineDifference};
+    use itertools::Itertools;
+    use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
+    use serde::Serialize;
+    use std::fs;
 
-            let mut version_stack = self
-                .db
-                .liter(FILE_VERSION_STACK)
-                .map(|version_stack| version_stack.get_item().unwrap())
-                .find(|version_stack: &VersionStack| version_stack.path.eq(&path))
-                .unwrap();
+    fn versions(store: &mut Store, path: &str) -> Vec<Version> {
+        store.change_time_frame(TimeFrame::ALL);
+        store
+            .view()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .find(|file_versions| file_versions.path.eq(path))
+            .map(|file_versions| file_versions.versions)
+            .unwrap_or_default()
+    }
 
-            version_stack.timestamps = version_stack
-                .timestamps
-                .into_iter()
-                .skip(pos)
-                .take(count)
-                .collect_vec();
+    fn write(store: &mut Store, path: &str, content: &str) {
+        fs::write(path, content).unwrap();
+        let changes = diff::find(
+            path,
+            &store.get_file_changes::<LineDifference>(path),
+            Algorithm::Myers,
+            Ignore::default(),
+            0,
+        )
+        .unwrap();
+        store.store_changes(path, &changes).unwrap();
+    }
 
-            self.get_versions(
-                self.db
-                    .liter(FILE_VERSION_STACK)
-                    .map(|version_stack| version_stack.get_item().unwrap())
-                    .find(|version_stack: &VersionStack| version_stack.path.eq(&path))
-                    .unwrap(),
-            )
-        }
+    #[test]
+    fn undo_and_edit_opens_a_branch() {
+        let fixture = Fixture::new("test_undo_tree");
+        let path = "test_undo_tree/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "a\nb\n");
+        write(&mut store, path, "a\nb\nc\n");
+        let undone_from = store.version_tree(path).unwrap().current;
+        store.undo_by(path.to_string(), 2).unwrap();
+        let restored = fs::read_to_string(path).unwrap();
+        write(&mut store, path, "x\n");
+        let version_tree = store.version_tree(path).unwrap();
+
+        assert_eq!(restored, "a\n");
+        let root = version_tree.root();
+        assert_eq!(version_tree.children(root).len(), 2);
+        assert!(version_tree.contains(undone_from));
+        assert_eq!(version_tree.parent(version_tree.current), Some(root));
+    }
+
+    #[test]
+    fn undo_merges_edits_made_on_disk_meanwhile() {
+        let fixture = Fixture::new("test_undo_merge");
+        let path = "test_undo_merge/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "a\nb\nc\n");
+        fs::write(path, "A\nb\nc\n").unwrap();
+        store.undo_by(path.to_string(), 1).unwrap();
+        let merged = fs::read_to_string(path).unwrap();
+
+        assert_eq!(merged, "A\nb\n");
+    }
+
+    #[test]
+    fn ignored_edits_are_neither_versioned_nor_shown() {
+        let fixture = Fixture::new("test_ignore");
+        let path = "test_ignore/file.rs";
+        fs::write(path, "fn a() {\nx\n}\n").unwrap();
+        let mut store = fixture.store();
+        let whitespace = Ignore {
+            all_whitespace: true,
+            ..Ignore::default()
+        };
+        store.change_ignore_rules(IgnoreRules {
+            skip_versions: true,
+            paths: vec![PathIgnore {
+                pattern: "*.rs".to_string(),
+                ignore: whitespace,
+            }],
+            ..IgnoreRules::default()
+        });
+
+        write(&mut store, path, "fn a() {\n    x\n}\n");
+        let skipped = versions(&mut store, path).len();
+        write(&mut store, path, "fn a() {\n        x\n    y\n}\n");
+        let shown = versions(&mut store, path)[0].changes.clone();
+        let other = store.ignore("test_ignore/file.txt");
+
+        assert_eq!(skipped, 1);
+        assert_eq!(
+            shown,
+            vec![LineDifference::added(
+                path.to_string(),
+                2,
+                "    y".to_string()
+            )]
+        );
+        assert_eq!(other, Ignore::default());
+    }
+
+    #[test]
+    fn undo_follows_the_disk_past_skipped_versions() {
+        let fixture = Fixture::new("test_undo_skipped");
+        let path = "test_undo_skipped/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        store.change_ignore_rules(IgnoreRules {
+            ignore: Ignore {
+                whitespace_change: true,
+                ..Ignore::default()
+            },
+            skip_versions: true,
+            ..IgnoreRules::default()
+        });
+
+        write(&mut store, path, "a\nb\n");
+        write(&mut store, path, "a\nb \n");
+        let shown = versions(&mut store, path).len();
+        store.undo_by(path.to_string(), 1).unwrap();
+        let undone = fs::read_to_string(path).unwrap();
+        store.redo_by(path.to_string(), 1).unwrap();
+        let redone = fs::read_to_string(path).unwrap();
+
+        assert_eq!(store.conflict_strategy, ConflictStrategy::Refuse);
+        assert_eq!(shown, 2);
+        assert_eq!(undone, "a\n");
+        assert_eq!(redone, "a\nb\n");
+    }
+
+    #[test]
+    fn inserted_lines_are_undone_in_place() {
+        let fixture = Fixture::new("test_undo_insert");
+        let path = "test_undo_insert/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "new\na\nb\n");
+        let inserted = versions(&mut store, path)[0].changes.clone();
+        store.undo_by(path.to_string(), 1).unwrap();
+        let undone = fs::read_to_string(path).unwrap();
+        store.redo_by(path.to_string(), 1).unwrap();
+        let redone = fs::read_to_string(path).unwrap();
+
+        assert_eq!(
+            inserted,
+            vec![LineDifference::added(
+                path.to_string(),
+                0,
+                "new".to_string()
+            )]
+        );
+        assert_eq!(undone, "a\nb\n");
+        assert_eq!(redone, "new\na\nb\n");
+    }
+
+    #[test]
+    fn undo_restores_line_endings_byte_for_byte() {
+        let fixture = Fixture::new("test_undo_crlf");
+        let path = "test_undo_crlf/config.json";
+        let original = "{\r\n  \"port\": 80,\r\n  \"host\": \"a\"\r\n}";
+        fs::write(path, original).unwrap();
+        let mut store = fixture.store();
+
+        write(
+            &mut store,
+            path,
+            "{\r\n  \"port\": 8080,\r\n  \"host\": \"a\"\r\n}",
+        );
+        write(
+            &mut store,
+            path,
+            "{\n  \"port\": 8080,\n  \"host\": \"a\"\n}\n",
+        );
+        let relaid = versions(&mut store, path).len();
+        store.undo_by(path.to_string(), 1).unwrap();
+        let edited = fs::read(path).unwrap();
+        store.undo_by(path.to_string(), 1).unwrap();
+        let undone = fs::read(path).unwrap();
+        store.redo_by(path.to_string(), 2).unwrap();
 
-        fn undo_versions(&self, versions: Vec<Version>) -> Result<(), Box<dyn error::Error>> {
-            versions.iter().for_each(|version| {
-                // TODO: propagate error
-                self.undo_changes(&version.changes).unwrap()
+        assert_eq!(relaid, 3);
+        assert_eq!(
+            edited,
+            b"{\r\n  \"port\": 8080,\r\n  \"host\": \"a\"\r\n}".to_vec()
+        );
+        assert_eq!(undone, original.as_bytes().to_vec());
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "{\n  \"port\": 8080,\n  \"host\": \"a\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn undo_refuses_a_history_that_doesnt_fit() {
+        let fixture = Fixture::new("test_undo_damaged");
+        let path = "test_undo_damaged/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let mut store = fixture.store();
+        let missing = LineDifference::removed(path.to_string(), 5, "x".to_string());
+        store.store_changes(path, &[missing]).unwrap();
+        write(&mut store, path, "a\n");
+
+        let refused = store.undo_by(path.to_string(), 1).unwrap_err();
+
+        assert!(refused.downcast_ref::<diff::ApplyError>().is_some());
+        assert_eq!(fs::read_to_string(path).unwrap(), "a\n");
+    }
+
+    #[test]
+    fn files_above_the_blob_limit_are_compared_by_their_lines() {
+        let fixture = Fixture::new("test_blob_lines");
+        let contents = [
+            "a\r\nb\r\n",
+            "a\nb",
+            "a\r\nb",
+            "a\r\nb\n",
+            "\n\n",
+            "a\r",
+            "",
+        ];
+        let path = |index: usize| format!("test_blob_lines/{}.txt", index);
+        contents.iter().enumerate().for_each(|(index, content)| {
+            fs::write(path(index), content).unwrap();
+        });
+        let mut store = fixture.store();
+        store.change_blob_limit(0);
+
+        let unchanged = (0..contents.len())
+            .map(|index| store.find_changes(&path(index)).unwrap())
+            .collect_vec();
+        let changed = contents
+            .iter()
+            .enumerate()
+            .map(|(index, content)| {
+                let content = format!("{}x\r\n", content);
+                fs::write(path(index), &content).unwrap();
+                (store.find_changes(&path(index)).unwrap(), content)
+            })
+            .collect_vec();
+
+        assert!(unchanged.iter().all(|changes| changes.is_empty()));
+        changed
+            .into_iter()
+            .enumerate()
+            .for_each(|(index, (changes, content))| {
+                assert_eq!(changes, vec![LineDifference::blob(path(index), content)]);
             });
-            Ok(())
-        }
+    }
 
-        fn undo_changes(&self, changes: &[LineDifference]) -> Result<(), Box<dyn error::Error>> {
-            let path = changes.first().unwrap().path.clone();
-            let file = File::open(path.clone())?;
-            let lines = io::BufReader::new(file).lines().collect_vec();
-            let mut redone_lines: Vec<String> = vec![];
+    #[test]
+    fn files_above_the_blob_limit_are_stored_whole() {
+        let fixture = Fixture::new("test_blob");
+        let path = "test_blob/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let mut store = fixture.store();
+        store.change_blob_limit(8);
 
-            if lines.is_empty() {
-                redone_lines.push(
-                    changes
-                        .iter()
-                        .find(|l| l.line_number.eq(&0))
-                        .unwrap()
-                        .line
-                        .clone(),
-                );
-            } else {
-                redone_lines = lines
-                    .iter()
-                    .map(|l| l.as_ref().unwrap())
-                    .enumerate()
-                    .map(|(index, line)| {
-                        let found = changes.iter().find(|l| l.line_number.eq(&index));
-                        if let Some(found) = found {
-                            return found.line.clone();
-                        }
-                        line.clone()
-                    })
-                    .collect();
-            }
+        fs::write(path, "a\nb\nc\nd\ne\n").unwrap();
+        let blob = store.find_changes(path).unwrap();
+        store.store_changes(path, &blob).unwrap();
+        let unchanged = store.find_changes(path).unwrap();
+        let blame = store.blame(path).unwrap();
+        store.change_blob_limit(1024);
+        fs::write(path, "a\nb\nC\nd\ne\n").unwrap();
+        let modified = store.find_changes(path).unwrap();
+        store.store_changes(path, &modified).unwrap();
+        store.undo_by(path.to_string(), 2).unwrap();
+        let undone = fs::read_to_string(path).unwrap();
+        let current = store.current_lines(path).to_vec();
+
+        assert_eq!(
+            blob,
+            vec![LineDifference::blob(
+                path.to_string(),
+                "a\nb\nc\nd\ne\n".to_string()
+            )]
+        );
+        assert_eq!(blob[0].kind, ChangeKind::Blob);
+        assert!(unchanged.is_empty());
+        assert_eq!(
+            blame.iter().map(|line| line.rewrites).collect_vec(),
+            vec![0; 5]
+        );
+        assert_eq!(
+            modified,
+            vec![LineDifference::modified(
+                path.to_string(),
+                2,
+                "c".to_string(),
+                "C".to_string()
+            )]
+        );
+        assert_eq!(undone, "a\nb\n");
+        assert_eq!(current, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn moved_lines_are_hit_once() {
+        let fixture = Fixture::new("test_moved");
+        let path = "test_moved/file.rs";
+        fs::write(path, "fn a() {\n    1\n}\nfn b() {\n    2\n}\n").unwrap();
+        let mut store = fixture.store();
+
+        fs::write(path, "fn b() {\n    2\n}\nfn a() {\n    1\n}\n").unwrap();
+        let changes = store.find_changes(path).unwrap();
+        store.store_changes(path, &changes).unwrap();
+        store.change_time_frame(TimeFrame::ALL);
+        let hits: u64 = store
+            .view()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .flat_map(|file_versions| file_versions.hits_of_codes)
+            .map(|hits_of_code| hits_of_code.hits)
+            .sum();
+
+        assert_eq!(changes.len(), 6);
+        assert!(changes.iter().all(|change| change.moved.is_some()));
+        assert_eq!(hits, 6 + 3);
+    }
+
+    #[test]
+    fn structured_files_are_viewed_by_their_keys() {
+        let fixture = Fixture::new("test_structural");
+        let path = "test_structural/config.json";
+        fs::write(path, "{\"server\": {\"port\": 80, \"host\": \"a\"}}\n").unwrap();
+        let mut store = fixture.store();
+        store.change_diff_algorithms(DiffAlgorithms {
+            structural: true,
+            ..DiffAlgorithms::default()
+        });
+
+        write(
+            &mut store,
+            path,
+            "{\n  \"server\": {\n    \"host\": \"a\",\n    \"port\": 8080\n  }\n}\n",
+        );
+        let versions = versions(&mut store, path);
+        store.undo_by(path.to_string(), 1).unwrap();
+        let undone = fs::read_to_string(path).unwrap();
+
+        assert_eq!(
+            versions[0].structure,
+            Some(vec![KeyChange {
+                key: "server.port".to_string(),
+                old: Some("80".to_string()),
+                new: Some("8080".to_string()),
+            }])
+        );
+        assert_eq!(versions[1].structure.as_ref().map(Vec::len), Some(2));
+        assert_eq!(undone, "{\"server\": {\"port\": 80, \"host\": \"a\"}}\n");
+    }
+
+    #[test]
+    fn conflicting_undo_is_refused_or_marked() {
+        let fixture = Fixture::new("test_undo_conflict");
+        let path = "test_undo_conflict/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "a\nb\n");
+        fs::write(path, "a\nB\n").unwrap();
+        let refused = store.undo_by(path.to_string(), 1).unwrap_err();
+        let untouched = fs::read_to_string(path).unwrap();
+        store.change_conflict_strategy(ConflictStrategy::Markers);
+        store.undo_by(path.to_string(), 1).unwrap();
+        let marked = fs::read_to_string(path).unwrap();
+
+        assert!(refused.downcast_ref::<ConflictError>().is_some());
+        assert_eq!(untouched, "a\nB\n");
+        assert_eq!(marked, "a\n<<<<<<< current\nB\n=======\n>>>>>>> restored\n");
+    }
+
+    #[test]
+    fn selected_changes_are_restored_alone() {
+        let fixture = Fixture::new("test_restore_changes");
+        let path = "test_restore_changes/file.txt";
+        fs::write(path, "a\nb\nc\nd\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "A\nb\nc\nx\nd\n");
+        write(&mut store, path, "A\nb\nc\nx\nd\ne\n");
+        let edited = versions(&mut store, path)[1].changes.clone();
+        let added = edited
+            .iter()
+            .filter(|change| change.kind == ChangeKind::Added)
+            .cloned()
+            .collect_vec();
+        let preview = store.preview_restore(path, &added).unwrap();
+        let untouched = fs::read_to_string(path).unwrap();
+        store.restore_changes(path, &added).unwrap();
+        let restored = fs::read_to_string(path).unwrap();
+        let restored_versions = versions(&mut store, path).len();
+        fs::write(path, "A\nb\nC\nd\ne\n").unwrap();
+        let modified = edited
+            .iter()
+            .filter(|change| change.kind == ChangeKind::Modified)
+            .cloned()
+            .collect_vec();
+        let merged = store.preview_restore(path, &modified).unwrap();
+        fs::write(path, "B\nb\nC\nd\ne\n").unwrap();
+        let refused = store.restore_changes(path, &modified).unwrap_err();
+
+        assert_eq!(edited.len(), 2);
+        assert_eq!(preview, vec!["A", "b", "c", "d", "e"]);
+        assert_eq!(untouched, "A\nb\nc\nx\nd\ne\n");
+        assert_eq!(restored, "A\nb\nc\nd\ne\n");
+        assert_eq!(restored_versions, 4);
+        assert_eq!(merged, vec!["a", "b", "C", "d", "e"]);
+        assert!(refused.downcast_ref::<ConflictError>().is_some());
+    }
+
+    #[test]
+    fn moved_blocks_are_restored_apart_from_their_neighbours() {
+        let fixture = Fixture::new("test_restore_moved");
+        let path = "test_restore_moved/file.txt";
+        fs::write(path, "k\nM1\nM2\nM3\na\nb\nc\nd\n").unwrap();
+        let mut store = fixture.store();
+        fs::write(path, "K\na\nb\nc\nd\nM1\nM2\nM3\n").unwrap();
+        let changes = store.find_changes(path).unwrap();
+        store.store_changes(path, &changes).unwrap();
+
+        let edited = versions(&mut store, path)[0].changes.clone();
+        let (moved, modified): (Vec<_>, Vec<_>) = edited
+            .into_iter()
+            .partition(|change| change.moved.is_some());
+        let preview = |selected: &[LineDifference]| store.preview_restore(path, selected).unwrap();
+
+        assert_eq!(moved.len(), 6);
+        assert_eq!(
+            preview(&moved),
+            vec!["K", "M1", "M2", "M3", "a", "b", "c", "d"]
+        );
+        assert_eq!(
+            preview(&modified),
+            vec!["k", "a", "b", "c", "d", "M1", "M2", "M3"]
+        );
+        assert_eq!(
+            preview(&[moved.clone(), modified.clone()].concat()),
+            vec!["k", "M1", "M2", "M3", "a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn any_two_versions_or_the_working_copy_are_compared() {
+        let fixture = Fixture::new("test_diff_between");
+        let path = "test_diff_between/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "a\nB\n");
+        write(&mut store, path, "a\nB\nc\n");
+        fs::write(path, "B\nc\n").unwrap();
+        let ids = versions(&mut store, path)
+            .iter()
+            .map(|version| version.id)
+            .collect_vec();
+        let between = store.diff_between(path, ids[2], Some(ids[0])).unwrap();
+        let backwards = store.diff_between(path, ids[0], Some(ids[2])).unwrap();
+        let working_copy = store.diff_between(path, ids[0], None).unwrap();
+        let unknown = store.diff_between(path, -1, None);
 
-            let mut file = File::create(path)?;
-            file.write_all(redone_lines.join("\n").as_bytes())
-                .map_err(|err| err.into())
+        assert_eq!(
+            between,
+            vec![
+                LineDifference::modified(path.to_string(), 1, "b".to_string(), "B".to_string()),
+                LineDifference::added(path.to_string(), 2, "c".to_string()),
+            ]
+        );
+        assert_eq!(backwards.len(), 2);
+        assert_eq!(
+            working_copy,
+            vec![LineDifference::removed(
+                path.to_string(),
+                0,
+                "a".to_string()
+            )]
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn read_only_store_refuses_writes() {
+        let fixture = Fixture::new("test_read_only");
+        let path = "test_read_only/file.txt";
+        fs::write(path, "a\n").unwrap();
+        drop(fixture.store());
+
+        let mut store = Store::open_read_only(&fixture.store_path).unwrap();
+        let viewed = store.view().unwrap().len();
+        let stored = store.store_changes(path, &[]);
+
+        assert_eq!(viewed, 1);
+        assert!(stored.is_err());
+    }
+
+    #[test]
+    fn imported_history_precedes_the_baseline() {
+        let fixture = Fixture::new("test_import");
+        let path = "test_import/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        let lines = |content: &str| diff::merge::to_lines(content);
+
+        let baseline_id = store.version_tree(path).unwrap().current;
+
+        let imported = store
+            .import_history(path, &[(100, lines("x\ny\nz")), (200, lines("a\ny\nz"))])
+            .unwrap();
+        let version_tree = store.version_tree(path).unwrap();
+        let ids = store
+            .snapshots(|_, _| true)
+            .unwrap()
+            .iter()
+            .map(|snapshot| snapshot.id)
+            .collect_vec();
+        let root = store.version_datetime(path, version_tree.root());
+        store.undo_by(path.to_string(), 1).unwrap();
+        let previous = fs::read_to_string(path).unwrap();
+        store.undo_by(path.to_string(), 1).unwrap();
+        let first = fs::read_to_string(path).unwrap();
+        store.redo_by(path.to_string(), 2).unwrap();
+        let baseline = fs::read_to_string(path).unwrap();
+        let pending = diff::find(
+            path,
+            &store.get_file_changes::<LineDifference>(path),
+            Algorithm::Myers,
+            Ignore::default(),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(imported, 2);
+        assert_eq!(ids.len(), 3);
+        assert!(ids[0] > baseline_id && ids[1] > ids[0]);
+        assert_eq!(ids[2], baseline_id);
+        assert_eq!(version_tree.root(), ids[0]);
+        assert_eq!(root, Some(NaiveDateTime::from_timestamp(100, 0)));
+        assert_eq!(previous, "a\ny\nz\n");
+        assert_eq!(first, "x\ny\nz\n");
+        assert_eq!(baseline, "a\n");
+        assert_eq!(pending, vec![]);
+    }
+
+    #[test]
+    fn snapshots_replay_every_version() {
+        let fixture = Fixture::new("test_snapshots");
+        let path = "test_snapshots/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        let lines = |content: &str| diff::merge::to_lines(content);
+        store
+            .import_history(path, &[(100, lines("x\ny")), (200, lines("x"))])
+            .unwrap();
+
+        let all = store.snapshots(|_, _| true).unwrap();
+        let newer = store
+            .snapshots(|_, datetime| datetime.timestamp() > 100)
+            .unwrap();
+
+        let contents: Vec<Vec<String>> =
+            all.iter().map(|snapshot| snapshot.lines.clone()).collect();
+        assert_eq!(contents, vec![lines("x\ny"), lines("x"), lines("a")]);
+        assert_eq!(newer.len(), 2);
+    }
+
+    #[test]
+    fn versions_stored_within_a_second_stay_apart() {
+        let fixture = Fixture::new("test_same_second");
+        let path = "test_same_second/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+
+        write(&mut store, path, "a\nb\n");
+        write(&mut store, path, "a\nb\nc\n");
+        let version_tree = store.version_tree(path).unwrap();
+        let versions = versions(&mut store, path);
+
+        let ids = versions.iter().map(|version| version.id).collect_vec();
+        assert_eq!(ids, vec![3, 2, 1]);
+        assert_eq!(version_tree.ancestry(3), vec![1, 2, 3]);
+        assert_eq!(versions[0].changes[0].changed_line, Some("c".to_string()));
+        assert_eq!(versions[1].changes[0].changed_line, Some("b".to_string()));
+    }
+
+    #[test]
+    fn stores_keyed_by_seconds_are_migrated() {
+        #[derive(Serialize)]
+        struct LegacyStack {
+            path: String,
+            timestamps: Vec<i64>,
         }
+        #[derive(Serialize)]
+        struct LegacyNote {
+            path: String,
+            timestamp: i64,
+            text: String,
+        }
+        #[derive(Serialize)]
+        struct LegacyChange {
+            path: String,
+            line_number: usize,
+            line: String,
+            changed_line: String,
+            date_time: String,
+        }
+        let fixture = Fixture::new("test_legacy");
+        let path = "test_legacy/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let change = |line_number, line: &str, changed_line: &str, date_time: &str| LegacyChange {
+            path: path.to_string(),
+            line_number,
+            line: line.to_string(),
+            changed_line: changed_line.to_string(),
+            date_time: date_time.to_string(),
+        };
+        let mut db = PickleDb::new(
+            &fixture.store_path,
+            PickleDbDumpPolicy::AutoDump,
+            SerializationMethod::Yaml,
+        );
+        db.lcreate("FILE_VERSION_STACK").unwrap();
+        db.ladd(
+            "FILE_VERSION_STACK",
+            &LegacyStack {
+                path: path.to_string(),
+                timestamps: vec![100, 200],
+            },
+        );
+        db.lcreate(path).unwrap();
+        db.lextend(
+            path,
+            &[
+                change(0, "", "a", "1970-01-01T00:01:39.500000000+00:00"),
+                change(1, "", "b", "1970-01-01T00:01:39.500000000+00:00"),
+                change(2, "", "c", "1970-01-01T00:01:39.500000000+00:00"),
+                change(2, "c", "", "1970-01-01T00:03:20.000000000+00:00"),
+            ],
+        );
+        db.lcreate("VERSION_NOTES").unwrap();
+        db.ladd(
+            "VERSION_NOTES",
+            &LegacyNote {
+                path: path.to_string(),
+                timestamp: 200,
+                text: "second".to_string(),
+            },
+        );
+        drop(db);
+
+        let mut store = Store::open(&fixture.store_path).unwrap();
+        let migrated = versions(&mut store, path);
+        let note = store.note(path, 200);
+        drop(store);
+        let mut store = Store::open(&fixture.store_path).unwrap();
+        write(&mut store, path, "a\nb\nc\n");
+        let next = store.version_tree(path).unwrap().current;
+        let reopened = versions(&mut store, path).len();
+
+        let linked = migrated
+            .iter()
+            .map(|version| {
+                let kinds = version
+                    .changes
+                    .iter()
+                    .map(|change| change.kind)
+                    .collect_vec();
+                (version.id, kinds)
+            })
+            .collect_vec();
+        assert_eq!(
+            linked,
+            vec![
+                (200, vec![ChangeKind::Removed]),
+                (100, vec![ChangeKind::Added; 3])
+            ]
+        );
+        assert_eq!(migrated[0].changes[0].changed_line, None);
+        assert_eq!(migrated[1].datetime, NaiveDateTime::from_timestamp(100, 0));
+        assert_eq!(note, Some("second".to_string()));
+        assert_eq!(next, 201);
+        assert_eq!(reopened, 3);
     }
 }
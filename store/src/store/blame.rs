@@ -0,0 +1,143 @@
+use super::Store;
+use diff::{ChangeKind, LineDifference};
+use itertools::Itertools;
+use std::error;
+
+///
+/// a line of the current file with the version that last
+/// changed it and how often it was changed after being added
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlameLine {
+    pub line_number: usize,
+    pub line: String,
+    pub version: i64,
+    /// when the version was stored, in seconds
+    pub timestamp: i64,
+    pub rewrites: usize,
+}
+
+impl Store {
+    ///
+    /// blames every line of the current state of `path` by patching
+    /// its changes in the order they were recorded
+    ///
+    pub fn blame(&self, path: &str) -> Result<Vec<BlameLine>, Box<dyn error::Error>> {
+        let versions = self
+            .stored_versions(path)
+            .ok_or_else(|| format!("{} has no versions", path))?;
+        let mut lines: Vec<BlameLine> = vec![];
+
+        for change in self.get_file_changes::<LineDifference>(path) {
+            let version = match versions
+                .iter()
+                .find(|version| Some(version.id) == change.version)
+            {
+                Some(version) => version,
+                None => continue,
+            };
+            let blamed = |line: String, rewrites| BlameLine {
+                line_number: change.line_number,
+                line,
+                version: version.id,
+                timestamp: version.datetime.timestamp(),
+                rewrites,
+            };
+            let position = change.line_number.min(lines.len());
+            match (change.kind, change.changed_line.clone()) {
+                (ChangeKind::Removed, _) | (_, None) => {
+                    if position < lines.len() {
+                        lines.remove(position);
+                    }
+                }
+                (ChangeKind::Added, Some(changed_line)) => {
+                    lines.insert(position, blamed(changed_line, 0))
+                }
+                (ChangeKind::Modified, Some(changed_line)) => match lines.get_mut(position) {
+                    Some(line) => *line = blamed(changed_line, line.rewrites + 1),
+                    None => lines.push(blamed(changed_line, 0)),
+                },
+                // a line left where it was keeps its blame
+                (ChangeKind::Blob, Some(content)) => {
+                    lines = diff::merge::to_lines(&content)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, line)| match lines.get(index) {
+                            Some(kept) if kept.line == line => kept.clone(),
+                            Some(kept) => blamed(line, kept.rewrites + 1),
+                            None => blamed(line, 0),
+                        })
+                        .collect_vec()
+                }
+            }
+        }
+
+        Ok(lines
+            .into_iter()
+            .enumerate()
+            .map(|(line_number, line)| BlameLine {
+                line_number,
+                ..line
+            })
+            .collect_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use std::fs;
+
+    #[test]
+    fn lines_are_blamed_on_their_last_change() {
+        let fixture = Fixture::new("test_blame");
+        let path = "test_blame/file.txt";
+        fs::write(path, "a\nb\nc\n").unwrap();
+        let mut store = fixture.store();
+        let first = store.version_tree(path).unwrap().current;
+
+        let change = |line_number, line: &str, changed_line: &str| {
+            LineDifference::modified(
+                path.to_string(),
+                line_number,
+                line.to_string(),
+                changed_line.to_string(),
+            )
+        };
+        let removed = LineDifference::removed(path.to_string(), 2, "c".to_string());
+
+        store
+            .store_changes(path, &[change(1, "b", "B"), removed])
+            .unwrap();
+        store.store_changes(path, &[change(1, "B", "BB")]).unwrap();
+        let third = store.version_tree(path).unwrap().current;
+        let inserted = LineDifference::added(path.to_string(), 0, "new".to_string());
+        store.store_changes(path, &[inserted]).unwrap();
+        let fourth = store.version_tree(path).unwrap().current;
+
+        let blame = store.blame(path).unwrap();
+        let unknown = store.blame("test_blame/missing.txt").is_err();
+
+        let summary = blame
+            .iter()
+            .map(|line| {
+                (
+                    line.line_number,
+                    line.line.as_str(),
+                    line.version,
+                    line.rewrites,
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            summary,
+            vec![
+                (0, "new", fourth, 0),
+                (1, "a", first, 0),
+                (2, "BB", third, 2)
+            ]
+        );
+        assert!(unknown);
+    }
+}
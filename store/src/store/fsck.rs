@@ -0,0 +1,572 @@
+use super::{
+    is_meta_list, Store, VersionStack, VersionTree, FILE_VERSION_STACK, FILE_VERSION_TREE,
+    VERSION_TAGS,
+};
+use diff::LineDifference;
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// an entry of the list couldn't be deserialized
+    UnreadableEntry(String),
+    /// a change list without a version stack
+    OrphanedChanges,
+    /// a version stack without a change list
+    MissingChanges,
+    /// a version stack without a version tree
+    MissingVersionTree,
+    /// a version tree without a version stack
+    OrphanedVersionTree,
+    /// a tree node whose version isn't on the stack
+    UnknownVersion,
+    /// the current node of a tree isn't part of it
+    DetachedCurrent,
+    /// a tag pointing at a version that doesn't exist
+    DanglingTag(String),
+    /// a version whose changes can't be replayed, the history ends before it
+    UnreplayableVersion,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub path: String,
+    pub version: Option<i64>,
+    pub issue: Issue,
+    pub repaired: bool,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match &self.issue {
+            Issue::UnreadableEntry(list) => format!("unreadable entry in {}", list),
+            Issue::OrphanedChanges => "changes without version stack".to_string(),
+            Issue::MissingChanges => "version stack without changes".to_string(),
+            Issue::MissingVersionTree => "version stack without version tree".to_string(),
+            Issue::OrphanedVersionTree => "version tree without version stack".to_string(),
+            Issue::UnknownVersion => "version tree node missing on the stack".to_string(),
+            Issue::DetachedCurrent => "current version is not part of the tree".to_string(),
+            Issue::DanglingTag(name) => format!("tag {:?} points at a missing version", name),
+            Issue::UnreplayableVersion => {
+                "version can't be replayed, the history has to end before it".to_string()
+            }
+        };
+        write!(f, "{}", self.path)?;
+        if let Some(version) = self.version {
+            write!(f, " @ {}", version)?;
+        }
+        write!(f, ": {}", description)?;
+        if self.repaired {
+            write!(f, " (repaired)")?;
+        }
+        Ok(())
+    }
+}
+
+impl Problem {
+    fn new(path: &str, version: Option<i64>, issue: Issue, repaired: bool) -> Problem {
+        Problem {
+            path: path.to_string(),
+            version,
+            issue,
+            repaired,
+        }
+    }
+}
+
+impl Store {
+    ///
+    /// checks the invariants between version stacks, version trees and
+    /// change lists. with `repair` problems are fixed by dropping orphans,
+    /// rebuilding trees from their stacks, ending a history before its
+    /// first version that can't be replayed or re-baselining the file
+    /// from disk. pinned versions are kept, and tags pointing at a missing
+    /// version are only deleted with `drop_dangling_tags`.
+    ///
+    pub fn fsck(
+        &mut self,
+        repair: bool,
+        drop_dangling_tags: bool,
+    ) -> Result<Vec<Problem>, Box<dyn error::Error>> {
+        if repair {
+            self.ensure_writable()?;
+        }
+        let mut problems = vec![];
+        let mut rebaseline = vec![];
+
+        let mut version_stacks: Vec<VersionStack> =
+            self.check_list(FILE_VERSION_STACK, repair, &mut problems)?;
+        let mut version_trees: Vec<VersionTree> =
+            self.check_list(FILE_VERSION_TREE, repair, &mut problems)?;
+
+        let change_lists = self
+            .db
+            .get_all()
+            .into_iter()
+            .filter(|name| !is_meta_list(name) && self.db.lexists(name))
+            .sorted()
+            .collect_vec();
+        let mut truncated = false;
+        for path in &change_lists {
+            let found = problems.len();
+            let version_stack = version_stacks.iter().find(|stack| stack.path.eq(path));
+            let entries: Vec<Option<LineDifference>> = self
+                .db
+                .liter(path)
+                .map(|change| change.get_item())
+                .collect();
+            let dropped = version_stack
+                .map(|version_stack| unreplayable(version_stack, &entries))
+                .unwrap_or_default();
+            // pinned versions are never dropped, the file is left as it is
+            let pinned = if repair { self.pinned(path) } else { vec![] };
+            let fixable = repair && !dropped.iter().any(|version| pinned.contains(version));
+            let _: Vec<LineDifference> = self.check_list(path, fixable, &mut problems)?;
+
+            if version_stack.is_none() {
+                problems.push(Problem::new(path, None, Issue::OrphanedChanges, repair));
+                if repair {
+                    self.db.lrem_list(path)?;
+                }
+                continue;
+            }
+            if let Some(first) = dropped.first() {
+                problems.push(Problem::new(
+                    path,
+                    Some(*first),
+                    Issue::UnreplayableVersion,
+                    fixable,
+                ));
+                if fixable {
+                    self.truncate(path, &dropped)?;
+                    truncated = true;
+                }
+            }
+            if problems.len() > found && fixable {
+                rebaseline.push(path.clone());
+            }
+        }
+        if truncated {
+            version_stacks = self.check_list(FILE_VERSION_STACK, false, &mut vec![])?;
+            version_trees = self.check_list(FILE_VERSION_TREE, false, &mut vec![])?;
+        }
+
+        for version_stack in &version_stacks {
+            let path = version_stack.path.as_str();
+            if !change_lists.iter().any(|list| list.eq(path)) {
+                problems.push(Problem::new(path, None, Issue::MissingChanges, repair));
+                if repair {
+                    self.create_new_file_entry(path)?;
+                }
+                rebaseline.push(path.to_string());
+            }
+            if !version_trees.iter().any(|tree| tree.path.eq(path)) {
+                problems.push(Problem::new(path, None, Issue::MissingVersionTree, repair));
+                if repair {
+                    self.set_version_tree(VersionTree::linear(
+                        path.to_string(),
+                        &version_stack.shown_ids(),
+                    ))?;
+                }
+            }
+        }
+
+        for tag in self.tags() {
+            let path = match &tag.path {
+                Some(path) => path,
+                None => continue,
+            };
+            let version = tag.version.unwrap_or(tag.timestamp);
+            let exists = version_stacks
+                .iter()
+                .any(|stack| stack.path.eq(path) && stack.ids().contains(&version));
+            if !exists {
+                problems.push(Problem::new(
+                    path,
+                    Some(version),
+                    Issue::DanglingTag(tag.name.clone()),
+                    repair && drop_dangling_tags,
+                ));
+                if repair && drop_dangling_tags {
+                    self.db.lrem_value(VERSION_TAGS, &tag)?;
+                }
+            }
+        }
+
+        for version_tree in version_trees {
+            let path = version_tree.path.clone();
+            let version_stack = version_stacks.iter().find(|stack| stack.path.eq(&path));
+            let version_stack = match version_stack {
+                Some(version_stack) => version_stack,
+                None => {
+                    problems.push(Problem::new(
+                        &path,
+                        None,
+                        Issue::OrphanedVersionTree,
+                        repair,
+                    ));
+                    if repair {
+                        self.db.lrem_value(FILE_VERSION_TREE, &version_tree)?;
+                    }
+                    continue;
+                }
+            };
+
+            let ids = version_stack.ids();
+            let pinned = if repair { self.pinned(&path) } else { vec![] };
+            let mut repaired = version_tree.clone();
+            version_tree
+                .nodes
+                .iter()
+                .filter(|node| !ids.contains(&node.version))
+                .for_each(|node| {
+                    // a tag still points at it, only untagging it lets it go
+                    let kept = pinned.contains(&node.version);
+                    problems.push(Problem::new(
+                        &path,
+                        Some(node.version),
+                        Issue::UnknownVersion,
+                        repair && !kept,
+                    ));
+                    if !kept {
+                        repaired.remove(node.version);
+                    }
+                });
+            if !repaired.contains(repaired.current) {
+                problems.push(Problem::new(
+                    &path,
+                    Some(version_tree.current),
+                    Issue::DetachedCurrent,
+                    repair,
+                ));
+                let newest = repaired.nodes.iter().map(|node| node.version).max();
+                repaired.current = newest.unwrap_or(version_tree.current);
+            }
+            if repair && repaired != version_tree {
+                self.set_version_tree(repaired)?;
+            }
+        }
+
+        if repair {
+            rebaseline
+                .iter()
+                .unique()
+                .filter(|path| Path::new(path).is_file())
+                .map(|path| self.rebaseline(path))
+                .find(|result| result.is_err())
+                .unwrap_or(Ok(()))?;
+        }
+
+        Ok(problems)
+    }
+
+    ///
+    /// records the file as it is on disk right now as a new version,
+    /// so the stored state matches the disk again
+    ///
+    pub fn rebaseline(&mut self, path: &str) -> Result<(), Box<dyn error::Error>> {
+        self.create_new_file_entry(path)?;
+        let changes = self.find_changes(path)?;
+
+        self.store_changes(path, &changes)
+    }
+
+    ///
+    /// drops versions of a file with their changes, the newest version
+    /// left becomes the current one. a file without any version left is
+    /// forgotten, so re-baselining records it as new.
+    ///
+    fn truncate(&mut self, path: &str, dropped: &[i64]) -> Result<(), Box<dyn error::Error>> {
+        let kept = self
+            .db
+            .liter(path)
+            .filter_map(|change| change.get_item::<LineDifference>())
+            .filter(|change| !matches!(change.version, Some(version) if dropped.contains(&version)))
+            .collect_vec();
+        self.rewrite_list(path, &kept)?;
+
+        if let Some(mut version_stack) = self.get_version_stack(path) {
+            self.db.lrem_value(FILE_VERSION_STACK, &version_stack)?;
+            version_stack
+                .versions
+                .retain(|version| !dropped.contains(&version.id));
+            if version_stack.versions.is_empty() {
+                self.db.lrem_list(path)?;
+            } else {
+                self.db.ladd(FILE_VERSION_STACK, &version_stack);
+            }
+        }
+        if let Some(mut version_tree) = self.get_version_tree(path) {
+            self.db.lrem_value(FILE_VERSION_TREE, &version_tree)?;
+            dropped
+                .iter()
+                .for_each(|version| version_tree.remove(*version));
+            if !version_tree.contains(version_tree.current) {
+                let newest = version_tree.nodes.iter().map(|node| node.version).max();
+                version_tree.current = newest.unwrap_or(version_tree.current);
+            }
+            if !version_tree.nodes.is_empty() && self.get_version_stack(path).is_some() {
+                self.db.ladd(FILE_VERSION_TREE, &version_tree);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_list<T: DeserializeOwned + Serialize>(
+        &mut self,
+        name: &str,
+        repair: bool,
+        problems: &mut Vec<Problem>,
+    ) -> Result<Vec<T>, Box<dyn error::Error>> {
+        let items: Vec<Option<T>> = self.db.liter(name).map(|item| item.get_item()).collect();
+        let unreadable = items.iter().filter(|item| item.is_none()).count();
+        let items = items.into_iter().flatten().collect_vec();
+
+        (0..unreadable).for_each(|_| {
+            problems.push(Problem::new(
+                name,
+                None,
+                Issue::UnreadableEntry(name.to_string()),
+                repair,
+            ))
+        });
+        if repair && unreadable > 0 {
+            self.rewrite_list(name, &items)?;
+        }
+
+        Ok(items)
+    }
+
+    fn rewrite_list<T: Serialize>(
+        &mut self,
+        name: &str,
+        items: &[T],
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.db.lrem_list(name)?;
+        self.db.lcreate(name)?;
+        self.db.lextend(name, items);
+        self.states.forget(name);
+        self.search_index.forget(name);
+
+        Ok(())
+    }
+}
+
+///
+/// the versions of a file from the first one that can't be replayed on,
+/// in the order they are replayed: its changes don't fit the content
+/// before it, or an unreadable entry may have belonged to it. that is the
+/// version of the entry before it, the entry itself could be one of its
+/// changes. every later version only fits what that one left.
+///
+fn unreplayable(version_stack: &VersionStack, entries: &[Option<LineDifference>]) -> Vec<i64> {
+    let suspect = entries.iter().position(Option::is_none).map(|hole| {
+        entries[..hole]
+            .iter()
+            .rev()
+            .flatten()
+            .find_map(|change| change.version)
+    });
+    let mut changes: HashMap<i64, Vec<LineDifference>> = HashMap::new();
+    entries.iter().flatten().for_each(|change| {
+        if let Some(version) = change.version {
+            changes.entry(version).or_default().push(change.clone());
+        }
+    });
+    let versions = version_stack
+        .versions
+        .iter()
+        .sorted_by(|a, b| (a.datetime, a.id).cmp(&(b.datetime, b.id)))
+        .map(|version| version.id)
+        .collect_vec();
+
+    let mut lines = vec![];
+    let first = versions.iter().enumerate().position(|(index, version)| {
+        let changes = changes.remove(version).unwrap_or_default();
+        suspect == Some(Some(*version))
+            || (suspect == Some(None) && index == 0)
+            || diff::apply_lines(&mut lines, &changes).is_err()
+    });
+
+    first.map_or_else(Vec::new, |first| versions[first..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use crate::store::Tag;
+    use std::fs;
+
+    #[test]
+    fn finds_and_repairs_broken_entries() {
+        let fixture = Fixture::new("test_fsck");
+        let path = "test_fsck/file.txt";
+        fs::write(path, "a\nb\n").unwrap();
+        let mut store = fixture.store();
+        fs::write(path, "a\nb\nc\n").unwrap();
+        store.rebaseline(path).unwrap();
+
+        store.db.ladd(path, &"not a change");
+        store.db.lcreate("test_fsck/gone.txt").unwrap();
+        let mut version_tree = store.version_tree(path).unwrap();
+        version_tree.add(1000);
+        store.set_version_tree(version_tree).unwrap();
+
+        let found = store.fsck(false, false).unwrap();
+        let repaired = store.fsck(true, false).unwrap();
+        let rechecked = store.fsck(false, false).unwrap();
+
+        let issues = found.into_iter().map(|problem| problem.issue).collect_vec();
+        assert_eq!(
+            issues,
+            vec![
+                Issue::UnreadableEntry(path.to_string()),
+                Issue::UnreplayableVersion,
+                Issue::OrphanedChanges,
+                Issue::UnknownVersion,
+                Issue::DetachedCurrent,
+            ]
+        );
+        assert!(repaired.iter().all(|problem| problem.repaired));
+        assert_eq!(rechecked, vec![]);
+    }
+
+    #[test]
+    fn histories_end_before_the_first_unreplayable_version() {
+        #[derive(Serialize)]
+        struct UnparsableChange {
+            path: String,
+            line_number: usize,
+            kind: String,
+            changed_line: String,
+            date_time: String,
+            version: i64,
+        }
+        let fixture = Fixture::new("test_fsck_replay");
+        let path = "test_fsck_replay/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        let first = store.version_tree(path).unwrap().current;
+        for content in ["a\nb\nc\n", "a\nb\nc\nd\n", "a\nb\nc\nd\ne\n"].iter() {
+            fs::write(path, content).unwrap();
+            store.rebaseline(path).unwrap();
+        }
+        let version_tree = store.version_tree(path).unwrap();
+        let broken = version_tree.ancestry(version_tree.current)[1];
+        // the second change of the second version gets a date that can't be read
+        let changes = store.get_file_changes::<LineDifference>(path);
+        let position = changes
+            .iter()
+            .positions(|change| change.version == Some(broken))
+            .nth(1)
+            .unwrap();
+        store.db.lrem_list(path).unwrap();
+        store.db.lcreate(path).unwrap();
+        store.db.lextend(path, &changes[..position]);
+        store.db.ladd(
+            path,
+            &UnparsableChange {
+                path: path.to_string(),
+                line_number: 2,
+                kind: "Added".to_string(),
+                changed_line: "c".to_string(),
+                date_time: "yesterday".to_string(),
+                version: broken,
+            },
+        );
+        store.db.lextend(path, &changes[position + 1..]);
+
+        let found = store.fsck(false, false).unwrap();
+        let repaired = store.fsck(true, false).unwrap();
+        let contents = store
+            .snapshots(|_, _| true)
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.content().unwrap())
+            .collect_vec();
+        let version_tree = store.version_tree(path).unwrap();
+
+        assert_eq!(
+            found,
+            repaired
+                .iter()
+                .map(|problem| Problem {
+                    repaired: false,
+                    ..problem.clone()
+                })
+                .collect_vec()
+        );
+        assert_eq!(
+            repaired,
+            vec![
+                Problem::new(path, None, Issue::UnreadableEntry(path.to_string()), true),
+                Problem::new(path, Some(broken), Issue::UnreplayableVersion, true),
+            ]
+        );
+        assert_eq!(contents, vec!["a\n", "a\nb\nc\nd\ne\n"]);
+        assert_eq!(version_tree.ancestry(version_tree.current)[0], first);
+        assert_eq!(version_tree.nodes.len(), 2);
+        assert_eq!(store.fsck(false, false).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn tags_and_pinned_versions_survive_repairs() {
+        let fixture = Fixture::new("test_fsck_tags");
+        let path = "test_fsck_tags/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        let mut version_tree = store.version_tree(path).unwrap();
+        version_tree.add(1000);
+        version_tree.current = version_tree.root();
+        store.set_version_tree(version_tree).unwrap();
+        let tag = Tag {
+            name: "lost".to_string(),
+            path: Some(path.to_string()),
+            timestamp: 0,
+            version: Some(1000),
+        };
+        store.db.lcreate(VERSION_TAGS).unwrap();
+        store.db.ladd(VERSION_TAGS, &tag);
+
+        let kept = store.fsck(true, false).unwrap();
+        let tags = store.tags();
+        let pinned = store.version_tree(path).unwrap().contains(1000);
+        let dropped = store.fsck(true, true).unwrap();
+
+        assert_eq!(
+            kept,
+            vec![
+                Problem::new(
+                    path,
+                    Some(1000),
+                    Issue::DanglingTag("lost".to_string()),
+                    false
+                ),
+                Problem::new(path, Some(1000), Issue::UnknownVersion, false),
+            ]
+        );
+        assert_eq!(tags, vec![tag]);
+        assert!(pinned);
+        assert_eq!(
+            dropped,
+            vec![
+                Problem::new(
+                    path,
+                    Some(1000),
+                    Issue::DanglingTag("lost".to_string()),
+                    true
+                ),
+                Problem::new(path, Some(1000), Issue::UnknownVersion, true),
+            ]
+        );
+        assert_eq!(store.tags(), vec![]);
+        assert_eq!(store.fsck(false, false).unwrap(), vec![]);
+    }
+}
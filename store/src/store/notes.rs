@@ -0,0 +1,114 @@
+use super::{Store, VERSION_NOTES};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::error;
+
+///
+/// free-form text attached to a version of a file
+///
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Note {
+    pub path: String,
+    /// id of the version, older stores called it timestamp
+    #[serde(alias = "timestamp")]
+    pub version: i64,
+    pub text: String,
+}
+
+impl Store {
+    ///
+    /// attaches `text` to the version `version` of `path`, replacing
+    /// an existing note. empty text removes the note.
+    ///
+    pub fn set_note(
+        &mut self,
+        path: &str,
+        version: i64,
+        text: &str,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.ensure_writable()?;
+        if self.version_datetime(path, version).is_none() {
+            return Err(format!("{} has no version {}", path, version).into());
+        }
+
+        if !self.db.lexists(VERSION_NOTES) {
+            self.db.lcreate(VERSION_NOTES)?;
+        }
+        if let Some(previous) = self.find_note(path, version) {
+            self.db.lrem_value(VERSION_NOTES, &previous)?;
+        }
+        let text = text.trim_end();
+        if !text.is_empty() {
+            let note = Note {
+                path: path.to_string(),
+                version,
+                text: text.to_string(),
+            };
+            self.db.ladd(VERSION_NOTES, &note);
+        }
+
+        Ok(())
+    }
+
+    /// every note, oldest version first
+    pub fn notes(&self) -> Vec<Note> {
+        if !self.db.lexists(VERSION_NOTES) {
+            return vec![];
+        }
+        self.db
+            .liter(VERSION_NOTES)
+            .filter_map(|note| note.get_item::<Note>())
+            .sorted_by(|a, b| (a.version, &a.path).cmp(&(b.version, &b.path)))
+            .collect_vec()
+    }
+
+    pub fn note(&self, path: &str, version: i64) -> Option<String> {
+        self.find_note(path, version).map(|note| note.text)
+    }
+
+    /// notes containing `query`, ignoring case
+    pub fn search_notes(&self, query: &str) -> Vec<Note> {
+        let query = query.to_lowercase();
+        self.notes()
+            .into_iter()
+            .filter(|note| note.text.to_lowercase().contains(&query))
+            .collect_vec()
+    }
+
+    fn find_note(&self, path: &str, version: i64) -> Option<Note> {
+        self.notes()
+            .into_iter()
+            .find(|note| note.path.eq(path) && note.version == version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixture::Fixture;
+    use std::fs;
+
+    #[test]
+    fn notes_are_replaced_removed_and_searchable() {
+        let fixture = Fixture::new("test_notes");
+        let path = "test_notes/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        let version = store.version_tree(path).unwrap().current;
+
+        store.set_note(path, version, "first draft").unwrap();
+        store
+            .set_note(path, version, "Demo for the client\nworks offline\n")
+            .unwrap();
+        let unknown = store.set_note(path, version + 1, "nope").is_err();
+        let note = store.note(path, version);
+        let found = store.search_notes("OFFLINE").len();
+        let missing = store.search_notes("draft").len();
+        store.set_note(path, version, "").unwrap();
+        let removed = store.notes().is_empty();
+
+        assert!(unknown);
+        assert_eq!(note, Some("Demo for the client\nworks offline".to_string()));
+        assert_eq!((found, missing), (1, 0));
+        assert!(removed);
+    }
+}
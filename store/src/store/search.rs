@@ -0,0 +1,356 @@
+use super::Store;
+use chrono::NaiveDateTime;
+use diff::{ChangeKind, LineDifference};
+use glob::Pattern;
+use itertools::Itertools;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// treat `pattern` as a regular expression instead of literal text
+    pub regex: bool,
+    /// only search files whose path matches, relative patterns match any suffix
+    pub glob: Option<String>,
+    pub from: i64,
+    pub to: i64,
+}
+
+impl SearchQuery {
+    pub fn literal(pattern: &str) -> SearchQuery {
+        SearchQuery {
+            pattern: pattern.to_string(),
+            regex: false,
+            glob: None,
+            from: i64::MIN,
+            to: i64::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Occurrence {
+    Appeared,
+    Disappeared,
+}
+
+///
+/// a version in which a matching line appeared or disappeared
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub path: String,
+    pub version: i64,
+    pub datetime: NaiveDateTime,
+    pub line_number: usize,
+    pub line: String,
+    pub occurrence: Occurrence,
+}
+
+///
+/// maps every three characters in a row of the stored lines to the
+/// positions of the changes holding them, per file. change lists only
+/// grow, so the number of changes indexed per file is enough to pick up
+/// new ones; a rewritten list is forgotten and indexed anew. the index
+/// lives in memory only and is built by the first literal search.
+///
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    files: HashMap<String, FileIndex>,
+}
+
+#[derive(Default)]
+struct FileIndex {
+    indexed: usize,
+    /// positions in increasing order
+    trigrams: HashMap<Trigram, Vec<usize>>,
+}
+
+type Trigram = (char, char, char);
+
+impl SearchIndex {
+    /// drops the index of a file whose changes were rewritten
+    pub(crate) fn forget(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+}
+
+impl FileIndex {
+    ///
+    /// positions of the changes holding every trigram, `None` if there
+    /// are no trigrams to look up
+    ///
+    fn candidates(&self, trigrams: &[Trigram]) -> Option<Vec<usize>> {
+        let mut postings = trigrams
+            .iter()
+            .map(|trigram| self.trigrams.get(trigram).map_or(&[][..], Vec::as_slice))
+            .sorted_by_key(|positions| positions.len());
+        let shortest = postings.next()?;
+
+        Some(
+            shortest
+                .iter()
+                .copied()
+                .filter(|position| {
+                    postings
+                        .clone()
+                        .all(|positions| positions.binary_search(position).is_ok())
+                })
+                .collect_vec(),
+        )
+    }
+}
+
+enum Matcher {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Matcher::Literal(pattern) => line.contains(pattern.as_str()),
+            Matcher::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+impl Store {
+    ///
+    /// finds every version where a line matching the query appeared or
+    /// disappeared, newest first. literal queries only look at the changes
+    /// the index names, regular expressions look at every change.
+    ///
+    pub fn search(&mut self, query: &SearchQuery) -> Result<Vec<Hit>, Box<dyn error::Error>> {
+        let matcher = if query.regex {
+            Matcher::Regex(Regex::new(&query.pattern)?)
+        } else {
+            Matcher::Literal(query.pattern.clone())
+        };
+        let globs = match &query.glob {
+            Some(glob) => vec![Pattern::new(glob)?, Pattern::new(&format!("**/{}", glob))?],
+            None => vec![],
+        };
+        let paths = self
+            .paths()
+            .into_iter()
+            .filter(|path| globs.is_empty() || globs.iter().any(|glob| glob.matches(path)))
+            .collect_vec();
+        let trigrams = match &matcher {
+            Matcher::Literal(pattern) => {
+                self.refresh_search_index(&paths);
+                trigrams(pattern).into_iter().collect_vec()
+            }
+            Matcher::Regex(_) => vec![],
+        };
+
+        Ok(paths
+            .iter()
+            .flat_map(|path| {
+                let candidates = self
+                    .search_index
+                    .files
+                    .get(path)
+                    .and_then(|file_index| file_index.candidates(&trigrams));
+                self.search_file(path, &matcher, candidates)
+            })
+            .filter(|hit| (query.from..=query.to).contains(&hit.datetime.timestamp()))
+            .sorted_by(|a, b| {
+                (b.datetime, b.version, &a.path, a.line_number).cmp(&(
+                    a.datetime,
+                    a.version,
+                    &b.path,
+                    b.line_number,
+                ))
+            })
+            .collect_vec())
+    }
+
+    ///
+    /// hits among the changes of a file at `positions`, or among all of
+    /// them without positions
+    ///
+    fn search_file(
+        &self,
+        path: &str,
+        matcher: &Matcher,
+        positions: Option<Vec<usize>>,
+    ) -> Vec<Hit> {
+        let versions = match self.stored_versions(path) {
+            Some(versions) => versions,
+            None => return vec![],
+        };
+        let changes = match positions {
+            Some(positions) => positions
+                .into_iter()
+                .filter_map(|position| self.db.lget::<LineDifference>(path, position))
+                .collect_vec(),
+            None => self.get_file_changes::<LineDifference>(path),
+        };
+
+        // a blob holds the whole file, not a line
+        changes
+            .into_iter()
+            .filter(|change| change.kind != ChangeKind::Blob)
+            .filter_map(|change| {
+                let matches = |line: &Option<String>| {
+                    line.as_deref()
+                        .filter(|line| matcher.is_match(line))
+                        .is_some()
+                };
+                let (line, occurrence) =
+                    match (matches(&change.line), matches(&change.changed_line)) {
+                        (false, true) => (change.changed_line.clone()?, Occurrence::Appeared),
+                        (true, false) => (change.line.clone()?, Occurrence::Disappeared),
+                        _ => return None,
+                    };
+                let version = versions
+                    .iter()
+                    .find(|version| Some(version.id) == change.version && !version.hidden)?;
+                Some(Hit {
+                    path: path.to_string(),
+                    version: version.id,
+                    datetime: version.datetime.naive_utc(),
+                    line_number: change.line_number,
+                    line,
+                    occurrence,
+                })
+            })
+            .collect_vec()
+    }
+
+    /// indexes the changes of `paths` stored since the last search
+    fn refresh_search_index(&mut self, paths: &[String]) {
+        for path in paths {
+            let length = self.db.llen(path);
+            let file_index = self.search_index.files.entry(path.clone()).or_default();
+            if file_index.indexed > length {
+                *file_index = FileIndex::default();
+            }
+            let changes = self
+                .db
+                .liter(path)
+                .skip(file_index.indexed)
+                .filter_map(|change| change.get_item::<LineDifference>());
+            for (position, change) in (file_index.indexed..).zip(changes) {
+                if change.kind == ChangeKind::Blob {
+                    continue;
+                }
+                change
+                    .line
+                    .iter()
+                    .chain(change.changed_line.iter())
+                    .flat_map(|line| trigrams(line))
+                    .for_each(|trigram| {
+                        let positions = file_index.trigrams.entry(trigram).or_default();
+                        if positions.last() != Some(&position) {
+                            positions.push(position);
+                        }
+                    });
+            }
+            file_index.indexed = length;
+        }
+    }
+}
+
+/// every three characters in a row of a line, each once
+fn trigrams(line: &str) -> BTreeSet<Trigram> {
+    line.chars().tuple_windows().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use std::fs;
+
+    #[test]
+    fn finds_appeared_and_disappeared_lines() {
+        let fixture = Fixture::new("test_search");
+        let path = "test_search/file.rs";
+        let other = "test_search/other.txt";
+        fs::write(path, "fn parse_config() {\n}\n").unwrap();
+        fs::write(other, "parse_config is mentioned here\n").unwrap();
+        let mut store = fixture.store();
+        let first = store.version_tree(path).unwrap().current;
+
+        fs::write(path, "fn load() {\n}\n").unwrap();
+        store.rebaseline(path).unwrap();
+        let second = store.version_tree(path).unwrap().current;
+
+        let hits = store
+            .search(&SearchQuery {
+                glob: Some("*.rs".to_string()),
+                ..SearchQuery::literal("parse_conf")
+            })
+            .unwrap();
+        let regex_hits = store
+            .search(&SearchQuery {
+                regex: true,
+                ..SearchQuery::literal(r"^fn lo\w+\(\)")
+            })
+            .unwrap()
+            .into_iter()
+            .map(|hit| (hit.version, hit.line))
+            .collect_vec();
+        let unknown = store.search(&SearchQuery::literal("nowhere")).unwrap();
+
+        let summary = hits
+            .iter()
+            .map(|hit| (hit.version, hit.occurrence))
+            .collect_vec();
+        assert_eq!(
+            summary,
+            vec![
+                (second, Occurrence::Disappeared),
+                (first, Occurrence::Appeared)
+            ]
+        );
+        assert_eq!(regex_hits, vec![(second, "fn load() {".to_string())]);
+        assert_eq!(unknown, vec![]);
+    }
+
+    #[test]
+    fn files_stored_whole_arent_searched() {
+        let fixture = Fixture::new("test_search_blob");
+        let path = "test_search_blob/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        store.change_blob_limit(8);
+
+        fs::write(path, "a\nneedle\nb\n").unwrap();
+        let blob = store.find_changes(path).unwrap();
+        store.store_changes(path, &blob).unwrap();
+        let hits = store.search(&SearchQuery::literal("needle")).unwrap();
+
+        assert_eq!(blob[0].kind, ChangeKind::Blob);
+        assert_eq!(hits, vec![]);
+    }
+
+    #[test]
+    fn the_index_narrows_the_changes_and_isnt_saved() {
+        let fixture = Fixture::new("test_search_index");
+        let path = "test_search_index/file.txt";
+        fs::write(path, "one\ntwo\n").unwrap();
+        let mut store = fixture.store();
+        fs::write(path, "one\ntwo\nthree\n").unwrap();
+        let changes = store.find_changes(path).unwrap();
+        store.store_changes(path, &changes).unwrap();
+        let saved = fs::read(&fixture.store_path).unwrap();
+
+        let hits = store.search(&SearchQuery::literal("thre")).unwrap();
+        let file_index = &store.search_index.files[path];
+        let narrowed = file_index.candidates(&trigrams("thre").into_iter().collect_vec());
+        let unnarrowed = file_index.candidates(&trigrams("o").into_iter().collect_vec());
+
+        assert_eq!(
+            hits.iter().map(|hit| hit.line.as_str()).collect_vec(),
+            vec!["three"]
+        );
+        assert_eq!(narrowed, Some(vec![2]));
+        assert_eq!(unnarrowed, None);
+        assert_eq!(fs::read(&fixture.store_path).unwrap(), saved);
+    }
+}
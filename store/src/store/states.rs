@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+/// bytes the cached states of all files may take together by default
+pub const DEFAULT_CACHE_LIMIT: u64 = 64 * 1024 * 1024;
+
+///
+/// the replayed content of files and how many of their changes it covers.
+/// once all of them take more than `limit` bytes the least recently used
+/// are dropped, they are replayed again when needed.
+///
+pub(crate) struct States {
+    limit: u64,
+    size: u64,
+    used: u64,
+    files: HashMap<String, State>,
+}
+
+struct State {
+    indexed: usize,
+    lines: Vec<String>,
+    size: u64,
+    used: u64,
+}
+
+impl Default for States {
+    fn default() -> Self {
+        States {
+            limit: DEFAULT_CACHE_LIMIT,
+            size: 0,
+            used: 0,
+            files: HashMap::new(),
+        }
+    }
+}
+
+impl States {
+    /// takes the state of a file out of the cache, to patch it
+    pub(crate) fn take(&mut self, path: &str) -> Option<(usize, Vec<String>)> {
+        let state = self.files.remove(path)?;
+        self.size -= state.size;
+
+        Some((state.indexed, state.lines))
+    }
+
+    ///
+    /// caches the state of a file as the most recently used one. it stays
+    /// even if it alone is larger than the limit, until another one is added.
+    ///
+    pub(crate) fn insert(&mut self, path: &str, indexed: usize, lines: Vec<String>) -> &[String] {
+        self.forget(path);
+        let size = lines.iter().map(|line| line.len() as u64 + 1).sum();
+        self.used += 1;
+        self.size += size;
+        self.files.insert(
+            path.to_string(),
+            State {
+                indexed,
+                lines,
+                size,
+                used: self.used,
+            },
+        );
+        self.evict(path);
+
+        &self.files[path].lines
+    }
+
+    /// drops the state of a file whose changes were rewritten
+    pub(crate) fn forget(&mut self, path: &str) {
+        self.take(path);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.files.clear();
+        self.size = 0;
+    }
+
+    pub(crate) fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+        self.evict("");
+    }
+
+    /// drops the least recently used states but `keep` until they fit the limit
+    fn evict(&mut self, keep: &str) {
+        while self.size > self.limit {
+            let oldest = self
+                .files
+                .iter()
+                .filter(|(path, _)| path.as_str() != keep)
+                .min_by_key(|(_, state)| state.used)
+                .map(|(path, _)| path.clone());
+            match oldest {
+                Some(oldest) => self.forget(&oldest),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> Vec<String> {
+        vec!["abc".to_string(); count]
+    }
+
+    #[test]
+    fn least_recently_used_states_are_dropped() {
+        let mut states = States::default();
+        states.set_limit(40);
+        states.insert("a", 1, lines(4));
+        states.insert("b", 1, lines(4));
+        let a = states.take("a");
+        states.insert("a", a.unwrap().0, lines(4));
+        states.insert("c", 1, lines(4));
+        let (a, b, c) = (states.take("a"), states.take("b"), states.take("c"));
+        states.insert("big", 1, lines(20));
+
+        assert!(a.is_some());
+        assert!(b.is_none());
+        assert!(c.is_some());
+        assert_eq!(states.take("big").map(|(_, lines)| lines.len()), Some(20));
+        assert_eq!(states.size, 0);
+    }
+}
@@ -0,0 +1,269 @@
+use super::{Store, VERSION_TAGS};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::error;
+
+///
+/// a name for a version of one file, or with `path` unset for the
+/// moment `timestamp` across the whole tree. tagged versions are pinned.
+///
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub path: Option<String>,
+    /// the tagged moment, for a file tag when its version was stored
+    pub timestamp: i64,
+    /// id of the version a file tag points at. for a whole-tree tag the
+    /// newest id stored by then, later versions of the same second are after it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+}
+
+impl Store {
+    ///
+    /// tags the newest version of `path` recorded at or before `timestamp`,
+    /// or the moment `timestamp` of every file if no path is given
+    ///
+    pub fn tag(
+        &mut self,
+        name: &str,
+        path: Option<&str>,
+        timestamp: i64,
+    ) -> Result<Tag, Box<dyn error::Error>> {
+        self.ensure_writable()?;
+        if name.trim().is_empty() {
+            return Err("tags need a name".into());
+        }
+        if self.tags().iter().any(|tag| tag.name.eq(name)) {
+            return Err(format!("tag {:?} exists already", name).into());
+        }
+        let (timestamp, version) = match path {
+            Some(path) => {
+                let version = self
+                    .nearest_version(path, timestamp)
+                    .ok_or_else(|| format!("{} has no version before the given time", path))?;
+                let datetime = self
+                    .version_datetime(path, version)
+                    .ok_or("couldn't find the tagged version")?;
+                (datetime.timestamp(), Some(version))
+            }
+            None => {
+                let newest = self
+                    .paths()
+                    .iter()
+                    .filter_map(|path| self.stored_versions(path))
+                    .flatten()
+                    .filter(|version| version.datetime.timestamp() <= timestamp)
+                    .map(|version| version.id)
+                    .max();
+                (timestamp, newest)
+            }
+        };
+
+        let tag = Tag {
+            name: name.to_string(),
+            path: path.map(|path| path.to_string()),
+            timestamp,
+            version,
+        };
+        if !self.db.lexists(VERSION_TAGS) {
+            self.db.lcreate(VERSION_TAGS)?;
+        }
+        self.db.ladd(VERSION_TAGS, &tag);
+
+        Ok(tag)
+    }
+
+    pub fn untag(&mut self, name: &str) -> Result<(), Box<dyn error::Error>> {
+        self.ensure_writable()?;
+        let tag = self
+            .find_tag(name)
+            .ok_or_else(|| format!("no tag named {:?}", name))?;
+        self.db.lrem_value(VERSION_TAGS, &tag)?;
+
+        Ok(())
+    }
+
+    /// every tag, oldest first
+    pub fn tags(&self) -> Vec<Tag> {
+        if !self.db.lexists(VERSION_TAGS) {
+            return vec![];
+        }
+        self.db
+            .liter(VERSION_TAGS)
+            .filter_map(|tag| tag.get_item::<Tag>())
+            .sorted_by_key(|tag| tag.timestamp)
+            .collect_vec()
+    }
+
+    pub fn find_tag(&self, name: &str) -> Option<Tag> {
+        self.tags().into_iter().find(|tag| tag.name.eq(name))
+    }
+
+    ///
+    /// the tags of a file with the version of it they point at,
+    /// whole-tree tags resolve to the file's version at that moment
+    ///
+    pub fn tags_of(&self, path: &str) -> Vec<(i64, Tag)> {
+        self.tags()
+            .into_iter()
+            .filter_map(|tag| match (&tag.path, tag.version) {
+                (Some(tagged), Some(version)) if tagged.eq(path) => Some((version, tag)),
+                (Some(_), _) => None,
+                (None, _) => self
+                    .moment_version(path, &tag)
+                    .map(|version| (version, tag)),
+            })
+            .collect_vec()
+    }
+
+    ///
+    /// versions of a file that are tagged. whatever drops versions,
+    /// like fsck repairing a store, has to keep these.
+    ///
+    pub fn pinned(&self, path: &str) -> Vec<i64> {
+        self.tags_of(path)
+            .into_iter()
+            .map(|(version, _)| version)
+            .unique()
+            .sorted()
+            .collect_vec()
+    }
+
+    /// the version of `path` in its tree a whole-tree tag stands for
+    fn moment_version(&self, path: &str, tag: &Tag) -> Option<i64> {
+        let version_tree = self.get_version_tree(path)?;
+
+        self.stored_versions(path)?
+            .into_iter()
+            .rev()
+            .filter(|version| version_tree.contains(version.id))
+            .filter(|version| version.datetime.timestamp() <= tag.timestamp)
+            .find(|version| version.id <= tag.version.unwrap_or(i64::MAX))
+            .map(|version| version.id)
+    }
+
+    ///
+    /// restores the tagged version, or for a whole-tree tag every file
+    /// as it was at that moment. every file is merged with its edits on
+    /// disk before any is written, if one can't be restored none is.
+    /// returns the restored paths.
+    ///
+    pub fn restore_tag(&mut self, name: &str) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let tag = self
+            .find_tag(name)
+            .ok_or_else(|| format!("no tag named {:?}", name))?;
+        if let (Some(path), Some(version)) = (&tag.path, tag.version) {
+            self.restore_version(path.clone(), version)?;
+            return Ok(vec![path.clone()]);
+        }
+        self.ensure_writable()?;
+        let checkouts = self
+            .paths()
+            .into_iter()
+            .filter_map(|path| {
+                let version = self.moment_version(&path, &tag)?;
+                let version_tree = self.get_version_tree(&path)?;
+                let lines = if version == version_tree.current {
+                    Ok(None)
+                } else {
+                    self.checkout_lines(&version_tree, version).map(Some)
+                };
+                Some(lines.map(|lines| (version_tree, version, lines)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut paths = vec![];
+        for (version_tree, version, lines) in checkouts {
+            paths.push(version_tree.path.clone());
+            if let Some(lines) = lines {
+                self.write_checkout(version_tree, version, &lines)?;
+            }
+        }
+
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use std::fs;
+
+    #[test]
+    fn tagged_versions_are_pinned_and_restorable() {
+        let fixture = Fixture::new("test_tags");
+        let path = "test_tags/file.txt";
+        fs::write(path, "a\n").unwrap();
+        let mut store = fixture.store();
+        let first = store.version_tree(path).unwrap().current;
+
+        let moment = chrono::Utc::now().timestamp();
+        store.tag("before", Some(path), moment).unwrap();
+        store.tag("everything", None, moment).unwrap();
+        let duplicate = store.tag("before", None, moment).is_err();
+        // most likely within the same second, the tag covers the ids stored before it
+        fs::write(path, "b\n").unwrap();
+        store.rebaseline(path).unwrap();
+
+        let tagged = store
+            .tags_of(path)
+            .into_iter()
+            .map(|(version, _)| version)
+            .collect_vec();
+        let pinned = store.pinned(path);
+        let restored = store.restore_tag("before").unwrap();
+        let content = fs::read_to_string(path).unwrap();
+        store.untag("before").unwrap();
+        let names = store.tags().into_iter().map(|tag| tag.name).collect_vec();
+
+        assert!(duplicate);
+        assert_eq!(tagged, vec![first, first]);
+        assert_eq!(pinned, vec![first]);
+        assert_eq!(restored, vec![path.to_string()]);
+        assert_eq!(content, "a\n");
+        assert_eq!(names, vec!["everything".to_string()]);
+    }
+
+    #[test]
+    fn whole_tree_restores_check_every_file_first() {
+        let fixture = Fixture::new("test_tags_moment");
+        let paths = ["a", "b", "c"]
+            .iter()
+            .map(|name| format!("test_tags_moment/{}.txt", name))
+            .collect_vec();
+        paths
+            .iter()
+            .for_each(|path| fs::write(path, "1\n").unwrap());
+        let mut store = fixture.store();
+        store
+            .tag("moment", None, chrono::Utc::now().timestamp())
+            .unwrap();
+        for path in &paths {
+            fs::write(path, "2\n").unwrap();
+            store.rebaseline(path).unwrap();
+        }
+        // conflicts with the version restored, after the others are checked
+        let conflicting = store.paths().pop().unwrap();
+        fs::write(&conflicting, "3\n").unwrap();
+
+        let conflict = store.restore_tag("moment");
+        let untouched = paths
+            .iter()
+            .filter(|path| path.ne(&&conflicting))
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect_vec();
+        fs::write(&conflicting, "2\n").unwrap();
+        let restored = store.restore_tag("moment").unwrap();
+        let contents = paths
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect_vec();
+
+        assert!(conflict.is_err());
+        assert_eq!(untouched, vec!["2\n"; 2]);
+        assert_eq!(restored.into_iter().sorted().collect_vec(), paths);
+        assert_eq!(contents, vec!["1\n"; 3]);
+    }
+}
@@ -0,0 +1,249 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+///
+/// a single restore point inside a version tree,
+/// identified by the id of the version it belongs to
+///
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VersionNode {
+    #[serde(alias = "timestamp")]
+    pub version: i64,
+    pub parent: Option<i64>,
+}
+
+///
+/// vim-style undo tree of a single file.
+/// every stored version becomes a child of the version that was
+/// current when it was recorded, so undoing and editing again opens
+/// a new branch instead of discarding the old future.
+///
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VersionTree {
+    pub path: String,
+    pub nodes: Vec<VersionNode>,
+    pub current: i64,
+}
+
+impl VersionTree {
+    pub fn new(path: String, root: i64) -> VersionTree {
+        VersionTree {
+            path,
+            nodes: vec![VersionNode {
+                version: root,
+                parent: None,
+            }],
+            current: root,
+        }
+    }
+
+    /// builds a linear tree out of a plain version stack
+    pub fn linear(path: String, versions: &[i64]) -> VersionTree {
+        let mut versions = versions.iter().sorted();
+        let root = *versions.next().unwrap_or(&0);
+        let mut tree = VersionTree::new(path, root);
+        versions.for_each(|version| tree.add(*version));
+
+        tree
+    }
+
+    /// adds a new node below the current one and moves onto it
+    pub fn add(&mut self, version: i64) {
+        if !self.contains(version) {
+            self.nodes.push(VersionNode {
+                version,
+                parent: Some(self.current),
+            });
+        }
+        self.current = version;
+    }
+
+    /// puts a chain of older versions, oldest first, in front of the current root
+    pub fn graft(&mut self, history: &[i64]) {
+        let root = self.root();
+        let mut parent = None;
+        history
+            .iter()
+            .filter(|version| !self.contains(**version))
+            .copied()
+            .collect_vec()
+            .into_iter()
+            .for_each(|version| {
+                self.nodes.push(VersionNode { version, parent });
+                parent = Some(version);
+            });
+        if let Some(node) = self.nodes.iter_mut().find(|node| node.version == root) {
+            node.parent = parent;
+        }
+    }
+
+    /// drops a node, its children move up to its parent
+    pub fn remove(&mut self, version: i64) {
+        let parent = self.parent(version);
+        self.nodes.retain(|node| node.version != version);
+        self.nodes
+            .iter_mut()
+            .filter(|node| node.parent == Some(version))
+            .for_each(|node| node.parent = parent);
+    }
+
+    pub fn contains(&self, version: i64) -> bool {
+        self.nodes.iter().any(|node| node.version == version)
+    }
+
+    pub fn root(&self) -> i64 {
+        self.nodes
+            .iter()
+            .find(|node| node.parent.is_none())
+            .map(|node| node.version)
+            .unwrap_or(self.current)
+    }
+
+    pub fn parent(&self, version: i64) -> Option<i64> {
+        self.nodes
+            .iter()
+            .find(|node| node.version == version)
+            .and_then(|node| node.parent)
+    }
+
+    /// children of a node, oldest first
+    pub fn children(&self, version: i64) -> Vec<i64> {
+        self.nodes
+            .iter()
+            .filter(|node| node.parent == Some(version))
+            .map(|node| node.version)
+            .sorted()
+            .collect_vec()
+    }
+
+    /// path from the root down to the given node, root first
+    pub fn ancestry(&self, version: i64) -> Vec<i64> {
+        let mut ancestry = vec![version];
+        let mut node = version;
+        while let Some(parent) = self.parent(node) {
+            ancestry.push(parent);
+            node = parent;
+        }
+        ancestry.reverse();
+
+        ancestry
+    }
+
+    /// walks up to `count` steps towards the root
+    pub fn undo_target(&self, count: usize) -> i64 {
+        (0..count).fold(self.current, |node, _| self.parent(node).unwrap_or(node))
+    }
+
+    /// walks up to `count` steps down, always following the newest branch
+    pub fn redo_target(&self, count: usize) -> i64 {
+        (0..count).fold(self.current, |node, _| {
+            self.children(node).last().copied().unwrap_or(node)
+        })
+    }
+
+    ///
+    /// assigns every node a column for drawing the tree:
+    /// the oldest child continues its parent's column,
+    /// every further child opens a new one
+    ///
+    pub fn columns(&self) -> Vec<(i64, usize)> {
+        let mut columns = vec![];
+        let mut next_column = 1;
+        let mut pending = vec![(self.root(), 0)];
+
+        while let Some((node, column)) = pending.pop() {
+            columns.push((node, column));
+            let children = self.children(node);
+            children
+                .iter()
+                .enumerate()
+                .rev()
+                .for_each(|(index, child)| {
+                    if index == 0 {
+                        pending.push((*child, column));
+                    } else {
+                        pending.push((*child, next_column));
+                        next_column += 1;
+                    }
+                });
+        }
+
+        columns
+    }
+
+    pub fn column(&self, version: i64) -> Option<usize> {
+        self.columns()
+            .into_iter()
+            .find(|(node, _)| *node == version)
+            .map(|(_, column)| column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branched() -> VersionTree {
+        let mut tree = VersionTree::linear("test.txt".to_string(), &[1, 2, 3]);
+        tree.current = 1;
+        tree.add(4);
+        tree
+    }
+
+    #[test]
+    fn linear_tree_is_a_chain() {
+        let tree = VersionTree::linear("test.txt".to_string(), &[3, 1, 2]);
+
+        assert_eq!(tree.root(), 1);
+        assert_eq!(tree.current, 3);
+        assert_eq!(tree.ancestry(3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn editing_after_undo_opens_a_branch() {
+        let tree = branched();
+
+        assert_eq!(tree.children(1), vec![2, 4]);
+        assert_eq!(tree.parent(4), Some(1));
+        assert_eq!(tree.ancestry(3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn undo_and_redo_targets() {
+        let mut tree = branched();
+
+        assert_eq!(tree.undo_target(1), 1);
+        assert_eq!(tree.undo_target(5), 1);
+        tree.current = 1;
+        assert_eq!(tree.redo_target(1), 4);
+        tree.current = 2;
+        assert_eq!(tree.redo_target(3), 3);
+    }
+
+    #[test]
+    fn grafted_history_becomes_the_new_root() {
+        let mut tree = VersionTree::linear("test.txt".to_string(), &[1, 2]);
+        tree.graft(&[5, 3, 4]);
+
+        assert_eq!(tree.root(), 5);
+        assert_eq!(tree.ancestry(2), vec![5, 3, 4, 1, 2]);
+        assert_eq!(tree.current, 2);
+    }
+
+    #[test]
+    fn removed_node_hands_its_children_up() {
+        let mut tree = VersionTree::linear("test.txt".to_string(), &[1, 2, 3]);
+        tree.remove(2);
+
+        assert_eq!(tree.ancestry(3), vec![1, 3]);
+    }
+
+    #[test]
+    fn branches_get_their_own_column() {
+        let tree = branched();
+
+        assert_eq!(tree.column(2), Some(0));
+        assert_eq!(tree.column(3), Some(0));
+        assert_eq!(tree.column(4), Some(1));
+    }
+}
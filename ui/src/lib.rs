@@ -7,6 +7,7 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use chrono::Utc;
 use parking_lot::{Mutex, MutexGuard};
 use std::{
     error::Error,
@@ -17,7 +18,7 @@ use std::{
     time::{Duration, Instant},
 };
 use tui::{backend::CrosstermBackend, Terminal};
-use ui::UI;
+use ui::{SearchDraft, UI};
 
 pub enum Event<I> {
     Input(I),
@@ -78,6 +79,32 @@ fn on_versions(ui: Arc<Mutex<UI>>) -> JoinHandle<()> {
                         .add_item(file_versions.as_ref().unwrap().path.clone());
                 });
             ui.state.update_pane_content();
+            if let Some((path, version)) = ui.state.pending_hit.take() {
+                ui.state.select_version(&path, version);
+            }
+            // keeps an open blame pane up to date
+            if let Some((path, _)) = &ui.state.blame {
+                let path = path.clone();
+                ui.communication.on_blame(path);
+            }
+        }
+        if let Ok((path, blame)) = ui.communication.on_blame.try_recv() {
+            ui.on_blame(path, blame);
+        }
+        if let Ok((path, lines)) = ui.communication.on_preview.try_recv() {
+            ui.on_preview(path, lines);
+        }
+        if let Ok((path, changes)) = ui.communication.on_diff.try_recv() {
+            ui.on_diff(path, changes);
+        }
+        if let Ok(hits) = ui.communication.on_hits.try_recv() {
+            ui.on_hits(hits);
+        }
+        if let Ok(message) = ui.communication.on_error.try_recv() {
+            ui.state.status_message = Some(message);
+        }
+        if let Ok(message) = ui.communication.on_status.try_recv() {
+            ui.state.status_message = Some(message);
         }
         if ui.communication.on_quit.try_recv().is_ok() {
             break;
@@ -118,8 +145,54 @@ fn on_key(ui: Arc<Mutex<UI>>) -> JoinHandle<()> {
             let mut ui = ui.lock();
 
             if let Ok(ev) = ui.communication.on_key.try_recv() {
+                ui.state.status_message = None;
                 match ev {
+                    Event::Input(ev) if ui.state.note_draft.is_some() => {
+                        ui.on_note_key(ev.code);
+                    }
+                    Event::Input(ev) if ui.state.search_draft.is_some() => {
+                        ui.on_search_key(ev.code);
+                    }
+                    Event::Input(ev) if ui.state.restore_preview.is_some() => {
+                        ui.on_preview_key(ev.code);
+                    }
                     Event::Input(ev) => match ev.code {
+                        KeyCode::Char('n') => {
+                            ui.state.edit_note();
+                        }
+                        KeyCode::Char('b') => {
+                            ui.toggle_blame();
+                        }
+                        KeyCode::Char('r') => {
+                            ui.preview_restore();
+                        }
+                        KeyCode::Char('c') => {
+                            ui.compare_with_mark();
+                        }
+                        KeyCode::Char('d') => {
+                            ui.compare_with_disk();
+                        }
+                        KeyCode::Char('/') => {
+                            ui.state.search_draft = Some(SearchDraft::default());
+                        }
+                        KeyCode::Char(']') => {
+                            ui.next_hit();
+                        }
+                        KeyCode::Char('[') => {
+                            ui.previous_hit();
+                        }
+                        KeyCode::Char('g') => {
+                            let selected_path = ui.state.path_of_selected_file.clone();
+                            if let Some(timestamp) = ui.state.selected_version_timestamp() {
+                                ui.communication
+                                    .on_stash(Some(selected_path), timestamp, timestamp);
+                            }
+                        }
+                        KeyCode::Char('G') => {
+                            let now = Utc::now().timestamp();
+                            let time_frame = ui::time_frame(ui.state.tabs.get_index());
+                            ui.communication.on_stash(None, now - time_frame.value(), now);
+                        }
                         KeyCode::Char(c) => {
                             ui.state.on_key(c);
                         }
@@ -128,6 +201,12 @@ fn on_key(ui: Arc<Mutex<UI>>) -> JoinHandle<()> {
                             let selected_path = ui.state.path_of_selected_file.clone();
                             ui.communication.on_undo(selected_path, 1);
                         }
+                        KeyCode::Enter => {
+                            let selected_path = ui.state.path_of_selected_file.clone();
+                            if let Some(version) = ui.state.selected_version_id() {
+                                ui.communication.on_jump(selected_path, version);
+                            }
+                        }
                         // TODO
                         KeyCode::Tab => {
                             let selected_path = ui.state.path_of_selected_file.clone();
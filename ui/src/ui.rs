@@ -1,10 +1,16 @@
-use crate::util::process_new_version;
+use crate::util::{
+    hunk, note_label, process_blame, process_key_changes, process_new_version, process_note,
+    process_preview, tag_label, version_tree_label, with_checkbox,
+};
 use crate::util::{StatefulList, TabsState};
 use crate::Event;
-use crossterm::event::KeyEvent;
-use diff::LineDifference;
+use chrono::Utc;
+use crossterm::event::{KeyCode, KeyEvent};
+use diff::{Granularity, LineDifference};
 use flume::{Receiver, Sender};
-use store::store::{FileVersions, TimeFrame};
+use store::store::{
+    BlameLine, FileVersions, Hit, Occurrence, SearchQuery, TimeFrame, Version,
+};
 use tui::text::Spans;
 
 static GRAPH_X_WIDTH: usize = 100;
@@ -14,10 +20,24 @@ static GRAPH_X_WIDTH: usize = 100;
 /// 
 pub struct UICommunication {
     pub on_file_versions: Receiver<Vec<Option<FileVersions>>>,
+    pub on_error: Receiver<String>,
+    pub on_status: Receiver<String>,
     pub on_key: Receiver<Event<KeyEvent>>,
     pub on_quit: Receiver<()>,
     pub undo_to_handle: Sender<(String, usize)>,
     pub redo_to_handle: Sender<(String, usize)>,
+    pub jump_to_handle: Sender<(String, i64)>,
+    pub stash_to_handle: Sender<(Option<String>, i64, i64)>,
+    pub note_to_handle: Sender<(String, i64, String)>,
+    pub search_to_handle: Sender<SearchQuery>,
+    pub on_hits: Receiver<Vec<Hit>>,
+    pub blame_to_handle: Sender<String>,
+    pub on_blame: Receiver<(String, Vec<BlameLine>)>,
+    pub preview_to_handle: Sender<(String, Vec<LineDifference>)>,
+    pub on_preview: Receiver<(String, Result<Vec<String>, String>)>,
+    pub restore_changes_to_handle: Sender<(String, Vec<LineDifference>)>,
+    pub diff_to_handle: Sender<(String, i64, Option<i64>)>,
+    pub on_diff: Receiver<(String, Result<Vec<LineDifference>, String>)>,
     pub time_frame_change_to_handle: Sender<TimeFrame>,
     pub key_to_ui: Sender<Event<KeyEvent>>,
     pub quit_to_ui: Sender<()>,
@@ -39,23 +59,82 @@ impl UICommunication {
                 eprintln!("Could not redo step: {:?}", err);
             });
     }
+    pub fn on_jump(&mut self, path: String, version: i64) {
+        self.jump_to_handle
+            .send((path, version))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not jump to version: {:?}", err);
+            });
+    }
+    pub fn on_stash(&mut self, path: Option<String>, from: i64, to: i64) {
+        self.stash_to_handle
+            .send((path, from, to))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not create stash: {:?}", err);
+            });
+    }
+    pub fn on_note(&mut self, path: String, version: i64, text: String) {
+        self.note_to_handle
+            .send((path, version, text))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not save note: {:?}", err);
+            });
+    }
+    pub fn on_search(&mut self, query: SearchQuery) {
+        self.search_to_handle
+            .send(query)
+            .unwrap_or_else(|err| {
+                eprintln!("Could not search: {:?}", err);
+            });
+    }
+    pub fn on_blame(&mut self, path: String) {
+        self.blame_to_handle
+            .send(path)
+            .unwrap_or_else(|err| {
+                eprintln!("Could not blame file: {:?}", err);
+            });
+    }
+    pub fn on_preview_restore(&mut self, path: String, changes: Vec<LineDifference>) {
+        self.preview_to_handle
+            .send((path, changes))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not preview restore: {:?}", err);
+            });
+    }
+    pub fn on_restore_changes(&mut self, path: String, changes: Vec<LineDifference>) {
+        self.restore_changes_to_handle
+            .send((path, changes))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not restore changes: {:?}", err);
+            });
+    }
+    pub fn on_diff_between(&mut self, path: String, from: i64, to: Option<i64>) {
+        self.diff_to_handle
+            .send((path, from, to))
+            .unwrap_or_else(|err| {
+                eprintln!("Could not compare snapshots: {:?}", err);
+            });
+    }
     pub fn on_timeslice_change(&mut self, selected_slot: usize) {
-        let time_frame: TimeFrame;
-        match selected_slot {
-            0 => time_frame = TimeFrame::HOUR,
-            1 => time_frame = TimeFrame::DAY,
-            2 => time_frame = TimeFrame::WEEK,
-            // satifsy compiler
-            _ => time_frame = TimeFrame::DAY,
-        }
         self.time_frame_change_to_handle
-            .send(time_frame)
+            .send(time_frame(selected_slot))
             .unwrap_or_else(|err| {
                 eprintln!("Could not set timeframe: {:?}", err);
             });
     }
 }
 
+/// time frame shown by the given tab
+pub fn time_frame(selected_slot: usize) -> TimeFrame {
+    match selected_slot {
+        0 => TimeFrame::HOUR,
+        1 => TimeFrame::DAY,
+        2 => TimeFrame::WEEK,
+        3 => TimeFrame::ALL,
+        // satifsy compiler
+        _ => TimeFrame::DAY,
+    }
+}
 
 ///
 /// contains a vector for selectable time slots
@@ -73,6 +152,64 @@ pub struct UIConfig {
 }
 
 
+///
+/// a note being edited, bound to the snapshot
+/// that was selected when editing started
+///
+pub struct NoteDraft {
+    pub path: String,
+    pub version: i64,
+    pub text: String,
+}
+
+///
+/// a search being typed, literal unless `regex` is toggled
+///
+#[derive(Default)]
+pub struct SearchDraft {
+    pub pattern: String,
+    pub regex: bool,
+}
+
+///
+/// changes of a snapshot picked in the differences pane to be restored,
+/// in the order they were found, which is the order they are shown in
+///
+pub struct ChangeSelection {
+    pub version: i64,
+    pub cursor: usize,
+    pub picked: Vec<bool>,
+}
+
+///
+/// what restoring the picked changes would write, shown before anything
+/// is written. the lines are none until the preview arrived.
+///
+pub struct RestorePreview {
+    pub path: String,
+    pub changes: Vec<LineDifference>,
+    pub lines: Option<Vec<Spans<'static>>>,
+}
+
+///
+/// snapshot A of a comparison, kept across time frames
+///
+pub struct Mark {
+    pub path: String,
+    pub version: i64,
+    pub label: String,
+}
+
+///
+/// the combined changes between two snapshots, or a snapshot and the
+/// file on disk. the lines are none until the changes arrived.
+///
+pub struct Comparison {
+    pub path: String,
+    pub title: String,
+    pub lines: Option<Vec<Spans<'static>>>,
+}
+
 ///
 /// contains datastructures to store 
 /// tui states like selected pane, visible versions,
@@ -90,7 +227,23 @@ pub struct UIState {
     pub id_of_selected_file: usize,
     pub new_version: Vec<LineDifference>,
     pub processed_diffs: Vec<Spans<'static>>,
+    /// how finely modified lines are split to highlight what changed
+    pub granularity: Granularity,
     pub path_of_selected_file: String,
+    pub status_message: Option<String>,
+    pub note_draft: Option<NoteDraft>,
+    pub search_draft: Option<SearchDraft>,
+    pub hits: Vec<Hit>,
+    pub hit_index: usize,
+    /// version to select once the versions of the "all" tab arrived
+    pub pending_hit: Option<(String, i64)>,
+    /// blamed file and its lines while the blame pane is open
+    pub blame: Option<(String, Vec<Spans<'static>>)>,
+    /// changes being picked while the differences pane has a cursor
+    pub change_selection: Option<ChangeSelection>,
+    pub restore_preview: Option<RestorePreview>,
+    pub mark: Option<Mark>,
+    pub comparison: Option<Comparison>,
     pub should_quit: bool,
 }
 
@@ -107,7 +260,7 @@ impl UIState {
         if let Some(i) = self.filenames.get_index() {
                 self.id_of_selected_file = i;
         }
-        let versions_for_selected_file = &self.file_versions[self.id_of_selected_file as usize];
+        let versions_for_selected_file = &self.file_versions[self.id_of_selected_file];
         
         
         if versions_for_selected_file.is_none() {
@@ -141,7 +294,15 @@ impl UIState {
         }
         self.path_of_selected_file = versions_for_selected_file.path.clone();
         for v in &versions_for_selected_file.versions {
-            self.snapshots.add_item(v.datetime.to_string());
+            let label = version_tree_label(&versions_for_selected_file.version_tree, v);
+            let label = tag_label(label, &versions_for_selected_file.tags, v);
+            let label = match &self.mark {
+                Some(mark) if mark.path == versions_for_selected_file.path && mark.version == v.id => {
+                    format!("{} ◆ A", label)
+                }
+                _ => label,
+            };
+            self.snapshots.add_item(note_label(label, v));
         }
     }
     ///
@@ -158,9 +319,215 @@ impl UIState {
                     let selected_version = &selected_file.versions[i];
                     let diffs_for_this_version = &selected_version.changes;
                     self.processed_diffs.clear();
-                    self.processed_diffs = process_new_version(diffs_for_this_version.clone());
-                    self.processed_diffs.reverse();
+                    // a selection belongs to the snapshot it was started on
+                    if let Some(selection) = &self.change_selection {
+                        if selection.version != selected_version.id
+                            || selection.picked.len() != diffs_for_this_version.len()
+                        {
+                            self.change_selection = None;
+                        }
+                    }
+                    // structured files show their changed keys instead of lines
+                    self.processed_diffs = match &selected_version.structure {
+                        Some(structure) => process_key_changes(structure),
+                        None => {
+                            let mut processed_diffs =
+                                process_new_version(diffs_for_this_version.clone(), self.granularity);
+                            processed_diffs.reverse();
+                            match &self.change_selection {
+                                Some(selection) => processed_diffs
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(index, spans)| {
+                                        with_checkbox(
+                                            spans,
+                                            selection.picked[index],
+                                            selection.cursor == index,
+                                        )
+                                    })
+                                    .collect(),
+                                None => processed_diffs,
+                            }
+                        }
+                    };
+                    if let Some(note) = &selected_version.note {
+                        let mut processed_diffs = process_note(note);
+                        processed_diffs.append(&mut self.processed_diffs);
+                        self.processed_diffs = processed_diffs;
+                    }
+            }
+        }
+    }
+
+    ///
+    /// timestamp of the snapshot selected in the snapshot pane
+    ///
+    pub fn selected_version_timestamp(&mut self) -> Option<i64> {
+        self.selected_version()
+            .map(|version| version.datetime.timestamp())
+    }
+
+    ///
+    /// id of the snapshot selected in the snapshot pane
+    ///
+    pub fn selected_version_id(&mut self) -> Option<i64> {
+        self.selected_version().map(|version| version.id)
+    }
+
+    fn selected_version(&mut self) -> Option<&Version> {
+        let selected_file = self.file_versions.get(self.id_of_selected_file)?.as_ref()?;
+        let index = self.snapshots.get_index()?;
+
+        selected_file.versions.get(index)
+    }
+
+    ///
+    /// starts picking changes of the selected snapshot in the
+    /// differences pane, or stops it
+    ///
+    pub fn toggle_change_selection(&mut self) {
+        if self.change_selection.take().is_none() {
+            match self.selected_version() {
+                Some(version) if version.structure.is_some() => {
+                    self.status_message =
+                        Some("changed keys can't be picked, restore the snapshot instead".to_string());
+                }
+                Some(version) if !version.changes.is_empty() => {
+                    self.change_selection = Some(ChangeSelection {
+                        version: version.id,
+                        cursor: 0,
+                        picked: vec![false; version.changes.len()],
+                    });
+                }
+                _ => {}
+            }
+        }
+        self.update_snapshot_pane();
+    }
+
+    ///
+    /// picks the change under the cursor, or its whole hunk, or drops
+    /// them if they are all picked already
+    ///
+    pub fn toggle_picked(&mut self, whole_hunk: bool) {
+        let changes = match self.shown_changes() {
+            Some(changes) => changes,
+            None => return,
+        };
+        if let Some(selection) = self.change_selection.as_mut() {
+            let range = if whole_hunk {
+                hunk(&changes, selection.cursor)
+            } else {
+                selection.cursor..selection.cursor + 1
+            };
+            let pick = !selection.picked[range.clone()].iter().all(|picked| *picked);
+            selection.picked[range]
+                .iter_mut()
+                .for_each(|picked| *picked = pick);
+        }
+        self.update_snapshot_pane();
+    }
+
+    ///
+    /// the picked changes of the selected snapshot, in the order they
+    /// were found
+    ///
+    pub fn picked_changes(&mut self) -> Option<Vec<LineDifference>> {
+        let changes = self.shown_changes()?;
+        let selection = self.change_selection.as_ref()?;
+
+        Some(
+            changes
+                .into_iter()
+                .zip(&selection.picked)
+                .filter(|(_, picked)| **picked)
+                .map(|(change, _)| change)
+                .collect(),
+        )
+    }
+
+    /// changes of the snapshot being picked from, in the order they are shown
+    fn shown_changes(&mut self) -> Option<Vec<LineDifference>> {
+        let (id, count) = self
+            .change_selection
+            .as_ref()
+            .map(|selection| (selection.version, selection.picked.len()))?;
+        let version = self
+            .selected_version()
+            .filter(|version| version.id == id && version.changes.len() == count)?;
+
+        Some(version.changes.iter().rev().cloned().collect())
+    }
+
+    ///
+    /// marks the selected snapshot as A, the side a comparison starts
+    /// from, or unmarks it
+    ///
+    pub fn toggle_mark(&mut self) {
+        let path = self.path_of_selected_file.clone();
+        let (version, label) = match self.selected_version() {
+            Some(version) => (version.id, version.datetime.format("%Y-%m-%d %H:%M:%S").to_string()),
+            None => return,
+        };
+        self.mark = match self.mark.take() {
+            Some(mark) if mark.path == path && mark.version == version => None,
+            _ => Some(Mark {
+                path,
+                version,
+                label,
+            }),
+        };
+        self.update_file_pane();
+        self.update_snapshot_pane();
+    }
+
+    ///
+    /// starts editing the note of the selected snapshot
+    ///
+    pub fn edit_note(&mut self) {
+        let selected_file = match self.file_versions.get(self.id_of_selected_file) {
+            Some(Some(selected_file)) => selected_file,
+            _ => return,
+        };
+        if let Some(version) = self
+            .snapshots
+            .get_index()
+            .and_then(|index| selected_file.versions.get(index))
+        {
+            self.note_draft = Some(NoteDraft {
+                path: selected_file.path.clone(),
+                version: version.id,
+                text: version.note.clone().unwrap_or_default(),
+            });
+        }
+    }
+
+    ///
+    /// selects the file and its snapshot with the id `version`,
+    /// false if either is not listed
+    ///
+    pub fn select_version(&mut self, path: &str, version: i64) -> bool {
+        let file = match self.filenames.items.iter().position(|item| item.eq(path)) {
+            Some(file) => file,
+            None => return false,
+        };
+        self.filenames.state.select(Some(file));
+        self.update_file_pane();
+        let version = match &self.file_versions[self.id_of_selected_file] {
+            Some(file_versions) => file_versions
+                .versions
+                .iter()
+                .position(|stored| stored.id == version),
+            None => None,
+        };
+        match version {
+            Some(version) => {
+                self.snapshots.state.select(Some(version));
+                self.pane_ptr = -1;
+                self.update_snapshot_pane();
+                true
             }
+            None => false,
         }
     }
 
@@ -178,6 +545,11 @@ impl UIState {
     /// selects the following item in the list
     /// 
     pub fn on_up(&mut self) {
+        if let Some(selection) = self.change_selection.as_mut() {
+            selection.cursor = selection.cursor.saturating_sub(1);
+            self.update_snapshot_pane();
+            return;
+        }
         if self.pane_ptr > 0 {
             self.filenames.previous();
         } else if !self.snapshots.list_is_empty() {
@@ -189,6 +561,11 @@ impl UIState {
     /// selects the following item in the list
     /// 
     pub fn on_down(&mut self) {
+        if let Some(selection) = self.change_selection.as_mut() {
+            selection.cursor = (selection.cursor + 1).min(selection.picked.len() - 1);
+            self.update_snapshot_pane();
+            return;
+        }
         if self.pane_ptr > 0 {
             self.filenames.next();
         } else if !self.snapshots.list_is_empty() {
@@ -229,11 +606,7 @@ impl UIState {
         self.update_pane_content();
     }
 
-    /// expects a char to process actions
-    /// ```
-    /// //will quit the application
-    /// on_key('q') {}
-    /// ```
+    /// expects a char to process actions, `on_key('q')` quits the application
     pub fn on_key(&mut self, c: char) {
         match c {
             'q' => {
@@ -242,6 +615,22 @@ impl UIState {
             's' => {
                 self.pane_ptr *= -1;
             }
+            'w' => {
+                self.granularity = self.granularity.next();
+                self.update_snapshot_pane();
+            }
+            'v' => {
+                self.toggle_change_selection();
+            }
+            ' ' => {
+                self.toggle_picked(false);
+            }
+            'h' => {
+                self.toggle_picked(true);
+            }
+            'a' => {
+                self.toggle_mark();
+            }
             _ => {}
         }
     }
@@ -269,6 +658,7 @@ impl UI {
                     "1h".to_string(),
                     "24h".to_string(),
                     "7 Tage".to_string(),
+                    "all".to_string(),
                 ]),
                 should_quit: false,
                 file_versions: Vec::new(),
@@ -278,15 +668,277 @@ impl UI {
                 available_versions: Vec::new(),
                 y_scale_of_graph: 100.0,
                 processed_diffs: Vec::new(),
+                granularity: Granularity::default(),
                 new_version: Vec::new(),
                 path_of_selected_file: String::new(),
+                status_message: None,
+                note_draft: None,
+                search_draft: None,
+                hits: Vec::new(),
+                hit_index: 0,
+                pending_hit: None,
+                blame: None,
+                change_selection: None,
+                restore_preview: None,
+                mark: None,
+                comparison: None,
                 id_of_selected_file: 0,
                 pane_ptr: 1,
             },
             communication,
             timeslots: UITimeSlots {
-                slots: vec![TimeFrame::HOUR, TimeFrame::DAY, TimeFrame::WEEK],
+                slots: vec![
+                    TimeFrame::HOUR,
+                    TimeFrame::DAY,
+                    TimeFrame::WEEK,
+                    TimeFrame::ALL,
+                ],
+            },
+        }
+    }
+
+    ///
+    /// keys while a note is edited: esc saves it, enter starts a new line
+    ///
+    pub fn on_note_key(&mut self, code: KeyCode) {
+        let draft = match self.state.note_draft.as_mut() {
+            Some(draft) => draft,
+            None => return,
+        };
+        match code {
+            KeyCode::Char(c) => draft.text.push(c),
+            KeyCode::Enter => draft.text.push('\n'),
+            KeyCode::Backspace => {
+                draft.text.pop();
+            }
+            KeyCode::Esc => {
+                if let Some(draft) = self.state.note_draft.take() {
+                    self.communication
+                        .on_note(draft.path, draft.version, draft.text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ///
+    /// keys while a search is typed: tab toggles regular expressions,
+    /// enter searches, esc cancels
+    ///
+    pub fn on_search_key(&mut self, code: KeyCode) {
+        let draft = match self.state.search_draft.as_mut() {
+            Some(draft) => draft,
+            None => return,
+        };
+        match code {
+            KeyCode::Char(c) => draft.pattern.push(c),
+            KeyCode::Backspace => {
+                draft.pattern.pop();
+            }
+            KeyCode::Tab => draft.regex = !draft.regex,
+            KeyCode::Enter => {
+                if let Some(draft) = self.state.search_draft.take() {
+                    self.communication.on_search(SearchQuery {
+                        regex: draft.regex,
+                        ..SearchQuery::literal(&draft.pattern)
+                    });
+                }
+            }
+            KeyCode::Esc => self.state.search_draft = None,
+            _ => {}
+        }
+    }
+
+    pub fn on_hits(&mut self, hits: Vec<Hit>) {
+        self.state.hits = hits;
+        self.state.hit_index = 0;
+        if self.state.hits.is_empty() {
+            self.state.status_message = Some("no matches".to_string());
+            return;
+        }
+        self.show_hit();
+    }
+
+    pub fn next_hit(&mut self) {
+        if !self.state.hits.is_empty() {
+            self.state.hit_index = (self.state.hit_index + 1) % self.state.hits.len();
+            self.show_hit();
+        }
+    }
+
+    pub fn previous_hit(&mut self) {
+        if !self.state.hits.is_empty() {
+            let count = self.state.hits.len();
+            self.state.hit_index = (self.state.hit_index + count - 1) % count;
+            self.show_hit();
+        }
+    }
+
+    ///
+    /// selects the file and snapshot of the current hit. hits can be
+    /// older than the shown timeslice, so it switches to "all" first
+    /// and selects once those versions arrived.
+    ///
+    fn show_hit(&mut self) {
+        let hit = self.state.hits[self.state.hit_index].clone();
+        let sign = match hit.occurrence {
+            Occurrence::Appeared => '+',
+            Occurrence::Disappeared => '-',
+        };
+        self.state.status_message = Some(format!(
+            "match {}/{} line {}: {}{}",
+            self.state.hit_index + 1,
+            self.state.hits.len(),
+            hit.line_number,
+            sign,
+            hit.line.trim()
+        ));
+
+        let all = self.state.tabs.titles.len() - 1;
+        if self.state.tabs.get_index() != all {
+            self.state.tabs.index = all;
+            self.state.snapshots.unselect();
+            self.state.pending_hit = Some((hit.path, hit.version));
+            self.communication.on_timeslice_change(all);
+        } else if !self.state.select_version(&hit.path, hit.version) {
+            self.state.pending_hit = Some((hit.path, hit.version));
+        }
+    }
+
+    ///
+    /// opens the blame pane for the selected file, or closes it
+    ///
+    pub fn toggle_blame(&mut self) {
+        if self.state.blame.take().is_none() && !self.state.path_of_selected_file.is_empty() {
+            let path = self.state.path_of_selected_file.clone();
+            self.communication.on_blame(path);
+        }
+    }
+
+    pub fn on_blame(&mut self, path: String, blame: Vec<BlameLine>) {
+        let now = Utc::now().timestamp();
+        self.state.blame = Some((path, process_blame(&blame, now)));
+    }
+
+    ///
+    /// compares snapshot A with the selected snapshot as B, or closes an
+    /// open comparison
+    ///
+    pub fn compare_with_mark(&mut self) {
+        if self.state.comparison.take().is_some() {
+            return;
+        }
+        let path = self.state.path_of_selected_file.clone();
+        let (from, from_label) = match &self.state.mark {
+            Some(mark) if mark.path == path => (mark.version, mark.label.clone()),
+            Some(_) => {
+                self.state.status_message = Some("A is a snapshot of another file".to_string());
+                return;
+            }
+            None => {
+                self.state.status_message = Some("mark snapshot A with a first".to_string());
+                return;
+            }
+        };
+        if let Some(version) = self.state.selected_version() {
+            let (to, to_label) = (version.id, version.datetime.format("%Y-%m-%d %H:%M:%S"));
+            let title = format!("Differences from A {} to B {}", from_label, to_label);
+            self.compare(path, title, from, Some(to));
+        }
+    }
+
+    ///
+    /// compares the selected snapshot with the file on disk, or closes
+    /// an open comparison
+    ///
+    pub fn compare_with_disk(&mut self) {
+        if self.state.comparison.take().is_some() {
+            return;
+        }
+        let path = self.state.path_of_selected_file.clone();
+        if let Some(version) = self.state.selected_version() {
+            let (from, from_label) = (version.id, version.datetime.format("%Y-%m-%d %H:%M:%S"));
+            let title = format!("Differences from {} to the file on disk", from_label);
+            self.compare(path, title, from, None);
+        }
+    }
+
+    fn compare(&mut self, path: String, title: String, from: i64, to: Option<i64>) {
+        self.state.comparison = Some(Comparison {
+            path: path.clone(),
+            title,
+            lines: None,
+        });
+        self.communication.on_diff_between(path, from, to);
+    }
+
+    /// shows the compared snapshots, a refused comparison is dropped
+    pub fn on_diff(&mut self, path: String, changes: Result<Vec<LineDifference>, String>) {
+        let granularity = self.state.granularity;
+        match self.state.comparison.as_mut() {
+            Some(comparison) if comparison.path == path => match changes {
+                Ok(changes) => {
+                    comparison.lines = Some(process_new_version(changes, granularity));
+                }
+                Err(err) => {
+                    self.state.comparison = None;
+                    self.state.status_message = Some(err);
+                }
             },
+            _ => {}
+        }
+    }
+
+    ///
+    /// asks what restoring the picked changes would write. nothing is
+    /// written before the preview is confirmed.
+    ///
+    pub fn preview_restore(&mut self) {
+        let changes = match self.state.picked_changes() {
+            Some(changes) if !changes.is_empty() => changes,
+            _ => {
+                self.state.status_message =
+                    Some("pick changes first, v starts picking".to_string());
+                return;
+            }
+        };
+        let path = self.state.path_of_selected_file.clone();
+        self.state.restore_preview = Some(RestorePreview {
+            path: path.clone(),
+            changes: changes.clone(),
+            lines: None,
+        });
+        self.communication.on_preview_restore(path, changes);
+    }
+
+    /// shows what a restore would write, a refused restore is dropped
+    pub fn on_preview(&mut self, path: String, lines: Result<Vec<String>, String>) {
+        match self.state.restore_preview.as_mut() {
+            Some(preview) if preview.path == path => match lines {
+                Ok(lines) => preview.lines = Some(process_preview(&lines)),
+                Err(err) => {
+                    self.state.restore_preview = None;
+                    self.state.status_message = Some(err);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    ///
+    /// keys while a restore is previewed: enter restores, esc cancels
+    ///
+    pub fn on_preview_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter => {
+                if let Some(preview) = self.state.restore_preview.take() {
+                    self.state.change_selection = None;
+                    self.communication
+                        .on_restore_changes(preview.path, preview.changes);
+                }
+            }
+            KeyCode::Esc => self.state.restore_preview = None,
+            _ => {}
         }
     }
 }
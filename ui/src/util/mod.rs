@@ -1,4 +1,6 @@
-use diff::LineDifference;
+use diff::{ChangeKind, Granularity, KeyChange, LineDifference, Segment};
+use std::ops::Range;
+use store::store::{BlameLine, Tag, Version, VersionTree};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::ListState;
@@ -10,6 +12,10 @@ pub struct TabsState {
 static IS_DANGER: Color = Color::Rgb(191, 97, 106);
 static IS_SUCCESS: Color = Color::Rgb(163, 190, 140);
 static IS_LIGHT_WITE: Color = Color::Rgb(216, 222, 233);
+static IS_THIS_DAY: Color = Color::Rgb(235, 203, 139);
+static IS_THIS_WEEK: Color = Color::Rgb(208, 135, 112);
+static IS_OLDER: Color = Color::Rgb(129, 161, 193);
+static IS_MOVED: Color = Color::Rgb(180, 142, 173);
 
 impl TabsState {
     pub fn new(titles: Vec<String>) -> TabsState {
@@ -30,7 +36,15 @@ impl TabsState {
     }
 }
 
-pub fn process_new_version(diffs: Vec<LineDifference>) -> Vec<Spans<'static>> {
+///
+/// renders changes as the old line » the new line. modified lines are
+/// split at `granularity` and only the spans that changed are highlighted.
+/// moved lines get their own colour and where they moved from or to.
+///
+pub fn process_new_version(
+    diffs: Vec<LineDifference>,
+    granularity: Granularity,
+) -> Vec<Spans<'static>> {
     let mut v: Vec<Span> = vec![];
     let mut spans: Vec<Spans> = vec![];
     for diff in &diffs {
@@ -49,30 +63,52 @@ pub fn process_new_version(diffs: Vec<LineDifference>) -> Vec<Spans<'static>> {
         ));
         v.push(Span::raw(" » "));
 
-        let mut previous_line = diff.line.clone();
-        if previous_line.is_empty() {
-            previous_line = String::from("< empty line >");
-        }
-
-        v.push(Span::styled(
-            previous_line,
-            Style::default()
-                .add_modifier(Modifier::ITALIC)
-                .fg(IS_DANGER),
-        ));
-        v.push(Span::raw(" » "));
+        match (&diff.line, &diff.changed_line, diff.moved) {
+            (line, changed_line, Some(moved)) => {
+                let (content, direction) = match diff.kind {
+                    ChangeKind::Removed => (line, "to"),
+                    _ => (changed_line, "from"),
+                };
+                v.push(Span::styled(
+                    displayed_line(content),
+                    Style::default().add_modifier(Modifier::ITALIC).fg(IS_MOVED),
+                ));
+                v.push(Span::styled(
+                    format!("  moved {} line {}", direction, moved),
+                    Style::default().add_modifier(Modifier::DIM).fg(IS_MOVED),
+                ));
+            }
+            (_, Some(content), _) if diff.kind == ChangeKind::Blob => {
+                v.push(Span::styled(
+                    format!("< whole file, {} lines >", content.lines().count()),
+                    Style::default()
+                        .add_modifier(Modifier::ITALIC)
+                        .fg(IS_SUCCESS),
+                ));
+            }
+            (Some(line), Some(changed_line), _) if granularity != Granularity::Line => {
+                let (old, new) = diff::inline(line, changed_line, granularity);
+                v.extend(segment_spans(old, IS_DANGER));
+                v.push(Span::raw(" » "));
+                v.extend(segment_spans(new, IS_SUCCESS));
+            }
+            _ => {
+                v.push(Span::styled(
+                    displayed_line(&diff.line),
+                    Style::default()
+                        .add_modifier(Modifier::ITALIC)
+                        .fg(IS_DANGER),
+                ));
+                v.push(Span::raw(" » "));
 
-        let mut changed_line = diff.changed_line.clone();
-        if changed_line.is_empty() {
-            changed_line = String::from("< empty line >");
+                v.push(Span::styled(
+                    displayed_line(&diff.changed_line),
+                    Style::default()
+                        .add_modifier(Modifier::ITALIC)
+                        .fg(IS_SUCCESS),
+                ));
+            }
         }
-
-        v.push(Span::styled(
-            changed_line,
-            Style::default()
-                .add_modifier(Modifier::ITALIC)
-                .fg(IS_SUCCESS),
-        ));
         v.push(Span::raw("\n"));
         spans.push(Spans::from(v.clone()));
         v.clear();
@@ -80,6 +116,225 @@ pub fn process_new_version(diffs: Vec<LineDifference>) -> Vec<Spans<'static>> {
     spans
 }
 
+///
+/// renders changed keys of a structured file as key: old value » new value,
+/// in the order of the file
+///
+pub fn process_key_changes(changes: &[KeyChange]) -> Vec<Spans<'static>> {
+    changes
+        .iter()
+        .map(|change| {
+            let side = |value: &Option<String>, color| {
+                Span::styled(
+                    value.clone().unwrap_or_else(|| String::from("< none >")),
+                    Style::default().add_modifier(Modifier::ITALIC).fg(color),
+                )
+            };
+            Spans::from(vec![
+                Span::raw("\n"),
+                Span::styled(
+                    format!("{}:", change.key),
+                    Style::default()
+                        .add_modifier(Modifier::DIM)
+                        .fg(IS_LIGHT_WITE),
+                ),
+                Span::raw(" "),
+                side(&change.old, IS_DANGER),
+                Span::raw(" » "),
+                side(&change.new, IS_SUCCESS),
+                Span::raw("\n"),
+            ])
+        })
+        .collect()
+}
+
+/// changed segments stand out, the rest of the line is dimmed
+fn segment_spans(segments: Vec<Segment>, color: Color) -> Vec<Span<'static>> {
+    segments
+        .into_iter()
+        .map(|segment| {
+            let modifier = if segment.changed {
+                Modifier::BOLD | Modifier::REVERSED
+            } else {
+                Modifier::DIM | Modifier::ITALIC
+            };
+            Span::styled(
+                segment.text,
+                Style::default().add_modifier(modifier).fg(color),
+            )
+        })
+        .collect()
+}
+
+/// a side of a change, blank lines and missing ones spelled out
+fn displayed_line(line: &Option<String>) -> String {
+    match line.as_deref() {
+        None => String::from("< no line >"),
+        Some("") => String::from("< empty line >"),
+        Some(line) => line.to_string(),
+    }
+}
+
+///
+/// prefixes a snapshot with its branch in the version tree:
+/// one column per branch, ● marks the current version,
+/// ↺ marks versions that were written by a restore
+///
+pub fn version_tree_label(version_tree: &Option<VersionTree>, version: &Version) -> String {
+    let datetime = version.datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+    let version_tree = match version_tree {
+        Some(version_tree) => version_tree,
+        None => return datetime,
+    };
+
+    match version_tree.column(version.id) {
+        Some(column) => {
+            let marker = if version_tree.current == version.id {
+                "●"
+            } else {
+                "○"
+            };
+            format!("{}{} {}", "│ ".repeat(column), marker, datetime)
+        }
+        None => format!("↺ {}", datetime),
+    }
+}
+
+/// appends the names of the tags pointing at a snapshot
+pub fn tag_label(label: String, tags: &[(i64, Tag)], version: &Version) -> String {
+    let names = tags
+        .iter()
+        .filter(|(id, _)| *id == version.id)
+        .map(|(_, tag)| tag.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if names.is_empty() {
+        return label;
+    }
+
+    format!("{} ⚑ {}", label, names)
+}
+
+/// appends the first line of a snapshot's note
+pub fn note_label(label: String, version: &Version) -> String {
+    match version.note.as_ref().and_then(|note| note.lines().next()) {
+        Some(first_line) => format!("{} ✎ {}", label, first_line),
+        None => label,
+    }
+}
+
+///
+/// the note of a snapshot, shown above its differences
+///
+pub fn process_note(note: &str) -> Vec<Spans<'static>> {
+    note.lines()
+        .map(|line| {
+            Spans::from(Span::styled(
+                line.to_string(),
+                Style::default()
+                    .add_modifier(Modifier::ITALIC)
+                    .fg(IS_LIGHT_WITE),
+            ))
+        })
+        .chain(std::iter::once(Spans::from("")))
+        .collect()
+}
+
+///
+/// the current lines of a file with the time of their last change and
+/// how often they were rewritten. the time is coloured by age, from
+/// green for the last hour to blue for anything older than a week.
+///
+pub fn process_blame(blame: &[BlameLine], now: i64) -> Vec<Spans<'static>> {
+    blame
+        .iter()
+        .map(|line| {
+            let age = now - line.timestamp;
+            let color = if age < 60 * 60 {
+                IS_SUCCESS
+            } else if age < 24 * 60 * 60 {
+                IS_THIS_DAY
+            } else if age < 7 * 24 * 60 * 60 {
+                IS_THIS_WEEK
+            } else {
+                IS_OLDER
+            };
+            let datetime = chrono::NaiveDateTime::from_timestamp(line.timestamp, 0);
+            Spans::from(vec![
+                Span::styled(
+                    format!("l{:<4} ", line.line_number),
+                    Style::default()
+                        .add_modifier(Modifier::DIM)
+                        .fg(IS_LIGHT_WITE),
+                ),
+                Span::styled(
+                    format!(
+                        "{} {:>3}× ",
+                        datetime.format("%Y-%m-%d %H:%M:%S"),
+                        line.rewrites
+                    ),
+                    Style::default().fg(color),
+                ),
+                Span::raw(format!("│ {}", line.line)),
+            ])
+        })
+        .collect()
+}
+
+///
+/// the range of changes, in the order they were found, forming the hunk
+/// around `index`: neighbours touching the same or the following line
+///
+pub fn hunk(changes: &[LineDifference], index: usize) -> Range<usize> {
+    let touches = |pair: &[LineDifference]| pair[1].line_number <= pair[0].line_number + 1;
+    let start = changes[..=index]
+        .windows(2)
+        .rposition(|pair| !touches(pair))
+        .map_or(0, |position| position + 1);
+    let end = changes[index..]
+        .windows(2)
+        .position(|pair| !touches(pair))
+        .map_or(changes.len(), |position| index + position + 1);
+
+    start..end
+}
+
+/// puts a checkbox in front of a rendered change, highlighted under the cursor
+pub fn with_checkbox(spans: Spans<'static>, picked: bool, under_cursor: bool) -> Spans<'static> {
+    let style = if under_cursor {
+        Style::default()
+            .add_modifier(Modifier::REVERSED)
+            .fg(IS_LIGHT_WITE)
+    } else {
+        Style::default().fg(IS_LIGHT_WITE)
+    };
+    let checkbox = if picked { "[x]" } else { "[ ]" };
+    let mut spans = spans.0;
+    spans.insert(1, Span::styled(checkbox, style));
+    spans.insert(2, Span::raw(" "));
+
+    Spans::from(spans)
+}
+
+/// the lines a partial restore would write, numbered
+pub fn process_preview(lines: &[String]) -> Vec<Spans<'static>> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(line_number, line)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("l{:<4} ", line_number),
+                    Style::default()
+                        .add_modifier(Modifier::DIM)
+                        .fg(IS_LIGHT_WITE),
+                ),
+                Span::raw(format!("│ {}", line)),
+            ])
+        })
+        .collect()
+}
+
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
@@ -165,12 +420,123 @@ mod tests {
     #[test]
     fn diff_to_ui_representation() {
         let path = "test2.txt";
-        let diff = vec![LineDifference::new(
+        let diff = vec![LineDifference::added(
             path.to_string(),
             5,
-            "".to_string(),
             "Hello World".to_string(),
         )];
-        assert_eq!(2, 2);
+        let spans = process_new_version(diff, Granularity::Line);
+
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].0[2].content, "5");
+        assert_eq!(spans[0].0[6].content, "Hello World");
+    }
+
+    #[test]
+    fn only_changed_words_are_highlighted() {
+        let diff = vec![LineDifference::modified(
+            "test.rs".to_string(),
+            0,
+            "let a = 1;".to_string(),
+            "let a = 2;".to_string(),
+        )];
+
+        let whole = process_new_version(diff.clone(), Granularity::Line);
+        let words = process_new_version(diff, Granularity::Word);
+        let highlighted = words[0]
+            .0
+            .iter()
+            .filter(|span| span.style.add_modifier.contains(Modifier::REVERSED))
+            .map(|span| span.content.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(whole[0].0[4].content, "let a = 1;");
+        assert_eq!(highlighted, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn hunks_are_runs_of_touching_changes() {
+        let path = "test.txt".to_string();
+        let changes = vec![
+            LineDifference::added(path.clone(), 1, "a".to_string()),
+            LineDifference::added(path.clone(), 2, "b".to_string()),
+            LineDifference::removed(path.clone(), 3, "c".to_string()),
+            LineDifference::removed(path.clone(), 3, "d".to_string()),
+            LineDifference::added(path, 8, "e".to_string()),
+        ];
+
+        assert_eq!(hunk(&changes, 0), 0..4);
+        assert_eq!(hunk(&changes, 3), 0..4);
+        assert_eq!(hunk(&changes, 4), 4..5);
+
+        let picked = with_checkbox(
+            process_new_version(changes, Granularity::Line).remove(0),
+            true,
+            false,
+        );
+        assert_eq!(picked.0[1].content, "[x]");
+    }
+
+    #[test]
+    fn key_changes_to_ui_representation() {
+        let change = KeyChange {
+            key: "server.port".to_string(),
+            old: Some("80".to_string()),
+            new: None,
+        };
+
+        let spans = process_key_changes(&[change]);
+
+        assert_eq!(spans[0].0[1].content, "server.port:");
+        assert_eq!(spans[0].0[3].content, "80");
+        assert_eq!(spans[0].0[5].content, "< none >");
+    }
+
+    #[test]
+    fn blame_to_ui_representation() {
+        let line = |timestamp| BlameLine {
+            line_number: 0,
+            line: "fn main() {".to_string(),
+            version: 1,
+            timestamp,
+            rewrites: 2,
+        };
+        let now = 30 * 24 * 60 * 60;
+
+        let spans = process_blame(&[line(now - 60), line(0)], now);
+
+        assert_eq!(spans[0].0[1].content, "1970-01-30 23:59:00   2× ");
+        assert_eq!(spans[0].0[1].style.fg, Some(IS_SUCCESS));
+        assert_eq!(spans[1].0[1].style.fg, Some(IS_OLDER));
+        assert_eq!(spans[1].0[2].content, "│ fn main() {");
+    }
+
+    #[test]
+    fn version_tree_to_ui_representation() {
+        let mut version_tree = VersionTree::linear("test.txt".to_string(), &[1, 2]);
+        version_tree.current = 1;
+        version_tree.add(3);
+        let version = |id: i64| Version {
+            id,
+            datetime: chrono::NaiveDateTime::from_timestamp(id * 60, 250_000_000),
+            changes: vec![],
+            note: None,
+            structure: None,
+        };
+
+        let labels: Vec<String> = [1, 2, 3, 4]
+            .iter()
+            .map(|id| version_tree_label(&Some(version_tree.clone()), &version(*id)))
+            .collect();
+
+        assert_eq!(
+            labels,
+            vec![
+                "○ 1970-01-01 00:01:00",
+                "○ 1970-01-01 00:02:00",
+                "│ ● 1970-01-01 00:03:00",
+                "↺ 1970-01-01 00:04:00",
+            ]
+        );
     }
 }
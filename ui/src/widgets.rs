@@ -57,7 +57,7 @@ impl UI {
                 [
                     Constraint::Length(30),
                     Constraint::Min(8),
-                    Constraint::Length(7),
+                    Constraint::Length(14),
                     Constraint::Percentage(1),
                 ]
                 .as_ref(),
@@ -83,10 +83,90 @@ impl UI {
             )
             .margin(1)
             .split(area);
+        if let Some(draft) = &self.state.note_draft {
+            let block = Block::default()
+                .border_style(Style::default().fg(IS_HIGHLIGHTED))
+                .borders(Borders::ALL)
+                .title("Note (esc saves, an empty note is removed)");
+            let text: Vec<Spans> = format!("{}▏", draft.text)
+                .split('\n')
+                .map(|line| Spans::from(line.to_string()))
+                .collect();
+            let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
+            f.render_widget(paragraph, area);
+            return;
+        }
+        if let Some(draft) = &self.state.search_draft {
+            let block = Block::default()
+                .border_style(Style::default().fg(IS_HIGHLIGHTED))
+                .borders(Borders::ALL)
+                .title("Search (enter searches, tab toggles regex, esc cancels)");
+            let mode = if draft.regex { "regex" } else { "text" };
+            let text = Spans::from(vec![
+                Span::styled(
+                    format!("{} ", mode),
+                    Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+                ),
+                Span::raw(format!("{}▏", draft.pattern)),
+            ]);
+            let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
+            f.render_widget(paragraph, area);
+            return;
+        }
+        if let Some((path, blame)) = &self.state.blame {
+            let block = Block::default()
+                .border_style(Style::default().fg(IS_HIGHLIGHTED))
+                .borders(Borders::ALL)
+                .title(format!("Blame {} (b closes)", path));
+            let paragraph = Paragraph::new(blame.clone()).block(block);
+            f.render_widget(paragraph, area);
+            return;
+        }
+        if let Some(comparison) = &self.state.comparison {
+            let block = Block::default()
+                .border_style(Style::default().fg(IS_HIGHLIGHTED))
+                .borders(Borders::ALL)
+                .title(format!("{} (c or d closes)", comparison.title));
+            let text = comparison.lines.clone().unwrap_or_default();
+            let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
+            f.render_widget(paragraph, area);
+            return;
+        }
+        if let Some(preview) = &self.state.restore_preview {
+            let block = Block::default()
+                .border_style(Style::default().fg(IS_HIGHLIGHTED))
+                .borders(Borders::ALL)
+                .title(format!(
+                    "Restoring {} change(s) gives {} (enter restores, esc cancels)",
+                    preview.changes.len(),
+                    preview.path
+                ));
+            let text = preview.lines.clone().unwrap_or_default();
+            let paragraph = Paragraph::new(text).block(block);
+            f.render_widget(paragraph, area);
+            return;
+        }
+        let heading = match self.state.change_selection {
+            Some(_) => format!(
+                "Differences by {}, picking (space line, h hunk, r restore, v stops) ",
+                self.state.granularity.as_str()
+            ),
+            None => format!("Differences by {} ", self.state.granularity.as_str()),
+        };
+        let title = match &self.state.status_message {
+            Some(message) => Spans::from(vec![
+                Span::raw(heading),
+                Span::styled(
+                    message.clone(),
+                    Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+                ),
+            ]),
+            None => Spans::from(heading.trim_end().to_string()),
+        };
         let block = Block::default()
             .border_style(Style::default().fg(IS_BORDER))
             .borders(Borders::ALL)
-            .title("Differences");
+            .title(title);
         // let block = block.border_type(BorderType::Thick);
         let text: Vec<Spans> = self.state.processed_diffs.clone();
         let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
@@ -144,6 +224,167 @@ impl UI {
                     .fg(IS_LIGHT_WITE),
             ),
         ]);
+        let restore = Spans::from(vec![
+            Span::styled(
+                "enter ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Restore selected snapshot",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
+        let note = Spans::from(vec![
+            Span::styled(
+                "n ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Edit note of selected snapshot",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "b ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Blame selected file",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
+        let stash = Spans::from(vec![
+            Span::styled(
+                "g ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Git stash snapshot",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "G ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Git stash timeslice",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
+        let search = Spans::from(vec![
+            Span::styled(
+                "/ ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Search changed lines",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "[ ] ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Previous / next match",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
+        let highlight = Spans::from(vec![
+            Span::styled(
+                "w ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Highlight changed lines, words or characters",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
+        let pick = Spans::from(vec![
+            Span::styled(
+                "v ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Pick changes",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "space h ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Line / hunk",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "r ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Restore picked",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
+        let compare = Spans::from(vec![
+            Span::styled(
+                "a ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Mark snapshot A",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "c ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Compare A with selected",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+            Span::from(" , "),
+            Span::styled(
+                "d ",
+                Style::default().add_modifier(Modifier::BOLD).fg(IS_WARNING),
+            ),
+            Span::styled(
+                "Compare selected with file on disk",
+                Style::default()
+                    .add_modifier(Modifier::DIM)
+                    .fg(IS_LIGHT_WITE),
+            ),
+        ]);
         let arrow_up_down = Spans::from(vec![
             Span::styled(
                 "▲ ",
@@ -206,7 +447,20 @@ impl UI {
             .border_style(Style::default().fg(IS_BORDER))
             .borders(Borders::ALL)
             .title("Shortcuts");
-        let text: Vec<Spans> = vec![quit, modifier, undo_redo, arrow_left_right, arrow_up_down];
+        let text: Vec<Spans> = vec![
+            quit,
+            modifier,
+            undo_redo,
+            restore,
+            note,
+            stash,
+            search,
+            highlight,
+            pick,
+            compare,
+            arrow_left_right,
+            arrow_up_down,
+        ];
         let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
         f.render_widget(paragraph, area);
     }